@@ -83,9 +83,11 @@ def __add_ndarry_to_data(new_array, data):
 
 
 def __get_ndarray_from_jdx(filepath, first_x, last_x, dimensions):
-    spectrum = mol_swatter.Spectrum(filepath)
-    if not spectrum.is_valid():
-        print("Invalid spectrum : " + filepath)
+    try:
+        # x and y values are kept in the file's units, the data set is all 1/cm and transmittance
+        spectrum = mol_swatter.Spectrum(filepath).convert_x("1/CM").convert_y("TRANSMITTANCE")
+    except (ValueError, IOError) as e:
+        print("Invalid spectrum : " + filepath + " : " + str(e))
         return
     spectrum = spectrum.transform(first_x, last_x, dimensions)
     y_values = spectrum.get_y_values()
@@ -11,9 +11,10 @@ DATA_DIR = "../scraper/raw_data/"
 
 
 def demonstrate_spectrum_parser(file_path):
-    spectrum = mol_swatter.Spectrum(file_path)
-    if not spectrum.is_valid():
-        print("\tINVALID FILE :" + file_path)
+    try:
+        spectrum = mol_swatter.Spectrum(file_path).convert_x("1/CM")
+    except (ValueError, IOError) as e:
+        print("\tINVALID FILE :" + file_path + " : " + str(e))
         return
     print(spectrum.to_string())
     new_spectrum = spectrum.transform(800, 3500, 256)
@@ -2,18 +2,50 @@
  * Contains a list of all of the functional groups used to create the funct group vector
  *
  * Functional groups are defined as a custom struct, structural data is 
- *  defined as a molecule
+ *  defined as a SMARTS pattern
  *
  **/
 
-use crate::molecule::molecule::*;
+use super::smarts::{SmartsQuery, parse_smarts};
 
 // contains all the data required to express a functional group,
-// stored as an Molecule subset
+// stored as the query parsed from its SMARTS pattern or atom list
 // compared with other Molecules as a subgraph
+// Groups can also be read from a file, see library
 pub struct FunctGroup {
-    pub name : &'static str,
-    pub molecule: Molecule,
+    pub name : String,
+    pub category : Option<String>,
+    pub description : Option<String>,
+    // the SMARTS the group was written as, None for a group given as a list of atoms
+    pub smarts : Option<String>,
+    pub query : SmartsQuery,
+    // the names of the more general groups this one refines, see hierarchy
+    pub parents : Vec<String>,
+    // whether the group takes its atoms from its ancestors, so an ancestor isn't also reported
+    // on the same atoms
+    pub consumes : bool,
+}
+impl FunctGroup {
+    // a built in pattern that doesn't parse is a bug, the recognizer tests build every group
+    fn new(name : &str, category : &str, smarts : &str) -> FunctGroup {
+        let query = parse_smarts(smarts).unwrap_or_else(|e| panic!("bad SMARTS for {}: {}", name, e));
+        FunctGroup {
+            name : name.to_string(),
+            category : Some(category.to_string()),
+            description : None,
+            smarts : Some(smarts.to_string()),
+            query,
+            parents : Vec::new(),
+            consumes : false,
+        }
+    }
+
+    // a built in group that refines its parents and consumes their atoms
+    fn refines(mut self, parents : &[&str]) -> FunctGroup {
+        self.parents = parents.iter().map(|x| x.to_string()).collect();
+        self.consumes = true;
+        self
+    }
 }
 impl std::fmt::Display for FunctGroup {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -22,8 +54,8 @@ impl std::fmt::Display for FunctGroup {
 }
 
 
-// returns a vec of all the functional groups 
-// calls all of the molecule contructors so might be slightly expensive
+// returns a vec of all the built in functional groups, the default when no library file is given
+// parses all of the SMARTS patterns so might be slightly expensive
 // YOU NEED TO PUSH THE NEW FUNCTIONAL GROUP TO THIS AFTER DEFINING IT :)
 pub fn get_funct_groups() -> Vec<FunctGroup> {
     let mut o : Vec<FunctGroup> = Vec::new();
@@ -42,224 +74,134 @@ pub fn get_funct_groups() -> Vec<FunctGroup> {
     o
 }
 
-/* Remember that "R" stands for a bond to hydrogen or carbon or nothing!
+/* Remember that "R" used to stand for a bond to hydrogen or carbon!
+ *
+ * SMARTS has no "R" atom, the hydrogens are implicit. An atom with an "R" on it is written
+ * with an "R" test, [#8;!H0,$(*-[#6])] is an oxygen with a hydrogen or a single bond to a carbon.
+ * Atoms that need two "R"s use [...;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]
  *
- * Thus, a carboxylic acid will contain ROR,
- * to signify an ether : COC  is correct, ROR could mean a hydroxide or even water!
+ * Elements are written by atomic number, [#6] is any carbon, "C" would only be an aliphatic one
+ *
+ * A group that is a more specific form of other groups refines them, a carboxyl is reported
+ * instead of the carbonyl and hydroxyl on its atoms when the result is resolved, see hierarchy
  *
  */
 
 // *************** Aryl ************************
 
+// Molecules are aromatized before they are matched, so this matches either Kekulé structure
 pub fn aryl() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "C", "C", "C", "C", "C"));
-    molecule.add_bond(0, 1, 2);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(2, 3, 2);
-    molecule.add_bond(3, 4, 1);
-    molecule.add_bond(4, 5, 2);
-    molecule.add_bond(5, 0, 1);
-    FunctGroup { molecule, name : "Aryl"}
+    FunctGroup::new("Aryl", "Aromatic", "c1ccccc1")
 }
 
 // ************* oxygen containing *************
 
-// any sp2 carbon bonded to a neutral oxygen
-// (the oxygen  must not be bonded to any other atoms)
+// any carbon double bonded to an oxygen
 pub fn carbonyl() -> FunctGroup {
-    // initializes a new molecule with only C and O
-    let mut carboxyl = Molecule::new(vec!("C", "O"));
-    // adds a double bond between C and O
-    carboxyl.add_bond(0, 1, 2);
-    // returns a functional group made from the carboxyl molecule
-    FunctGroup {
-        molecule : carboxyl,
-        name : "Carbonyl"
-    }
+    FunctGroup::new("Carbonyl", "Oxygen", "[#6]=[#8]")
 }
 
 pub fn carboxyl() -> FunctGroup {
-    let mut mol = Molecule::new(vec!("C", "O", "O"));
-    mol.add_bond(0, 1, 2);
-    mol.add_bond(0, 2, 1);
-    FunctGroup {
-        molecule : mol,
-        name : "Carboxyl"
-    }
+    FunctGroup::new("Carboxyl", "Oxygen", "[#6](=[#8])-[#8]")
+        .refines(&["Carbonyl", "Hydroxyl"])
 }
 
 pub fn acid_chloride() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "O", "Cl"));
-    molecule.add_bond(0, 1, 2);
-    molecule.add_bond(0, 2, 1);
-    FunctGroup { molecule, name : "Acid Chloride" }
+    FunctGroup::new("Acid Chloride", "Oxygen", "[#6](=[#8])-Cl")
+        .refines(&["Carbonyl"])
 }
 
 
 
 pub fn hydroxyl() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "O"));
-    molecule.add_bond(0, 1, 1);
-    FunctGroup { molecule, name : "Hydroxyl" }
+    FunctGroup::new("Hydroxyl", "Oxygen", "[#6]-[#8]")
 }
 
 pub fn ester() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "O", "O", "C"));
-    molecule.add_bond(0, 1, 2);
-    molecule.add_bond(0, 2, 1);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup { molecule, name : "Ester" }
+    FunctGroup::new("Ester", "Oxygen", "[#6](=[#8])-[#8]-[#6]")
+        .refines(&["Carboxyl", "Ether"])
 }
 
 pub fn peroxide() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "O", "O", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup {molecule, name : "Peroxide"}
+    FunctGroup::new("Peroxide", "Oxygen", "[#8;!H0,$(*-[#6])]-[#8;!H0,$(*-[#6])]")
 }
 
 pub fn ether() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "O", "C"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    FunctGroup { molecule , name : "Ether"}
+    FunctGroup::new("Ether", "Oxygen", "[#6]-[#8]-[#6]")
 }
 
 pub fn anhydride() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "C", "O", "O", "C", "O", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 2);
-    molecule.add_bond(1, 3, 1);
-    molecule.add_bond(3, 4, 1);
-    molecule.add_bond(4, 5, 2);
-    molecule.add_bond(4, 6, 1);
-    FunctGroup {molecule, name : "Anhydride"}
+    FunctGroup::new("Anhydride", "Oxygen", "[#6;!H0,$(*-[#6])](=[#8])-[#8]-[#6;!H0,$(*-[#6])]=[#8]")
+        .refines(&["Ester"])
 }
 
 // ***************** nitrogen containing ***********************
 
 pub fn amide() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "C", "O", "N", "R", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 2);
-    molecule.add_bond(1, 3, 1);
-    molecule.add_bond(3, 4, 1);
-    molecule.add_bond(3, 5, 1);
-    FunctGroup {molecule, name : "Amide"}
+    FunctGroup::new("Amide", "Nitrogen", "[#6;!H0,$(*-[#6])](=[#8])-[#7;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]")
+        .refines(&["Carbonyl", "Amine"])
 }
 pub fn amine() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("N", "R"));
-    molecule.add_bond(0, 1, 1);
-    FunctGroup{molecule, name : "Amine"}
+    FunctGroup::new("Amine", "Nitrogen", "[#7;!H0,$(*-[#6])]")
 }
 // TODO this only recognizes a nitrogen doublebonded to a carbon, doesnt specify what the other
 // group bonded to the nitrogen is
 pub fn imine() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("N", "R"));
-    molecule.add_bond(0, 1, 2);
-    FunctGroup{molecule, name : "Imine"}
+    FunctGroup::new("Imine", "Nitrogen", "[#7]=[#6]")
 }
 pub fn nitrile() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "C", "N"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 3);
-    FunctGroup{molecule, name : "Nitrile"}
+    FunctGroup::new("Nitrile", "Nitrogen", "[#6;!H0,$(*-[#6])]#[#7]")
 }
 pub fn pyridyl() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("C", "C", "C", "C", "C", "N"));
-    molecule.add_bond(0, 1, 2);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(3, 4, 2);
-    molecule.add_bond(4, 5, 1);
-    molecule.add_bond(5, 0, 2);
-    FunctGroup{molecule, name : "Pyridyl"}
+    FunctGroup::new("Pyridyl", "Nitrogen", "c1ccncc1")
 }
+// Drawn charge separated, R-[N+](=O)[O-], only the elements and bonds are compared so the
+// uncharged form matches too
 pub fn nitro() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "N", "O", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 2);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup{molecule, name : "Nitro"}
+    FunctGroup::new("Nitro", "Nitrogen", "[#7;!H0,$(*-[#6])](=[#8])-[#8]")
 }
 
 // ************* sulfur containing *****************
 
 pub fn sulfide() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "S", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    FunctGroup{molecule, name : "Sulfide"}
+    FunctGroup::new("Sulfide", "Sulfur", "[#16;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]")
 }
 pub fn disulfide() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "S", "S", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup{molecule, name : "Disulfide"}
+    FunctGroup::new("Disulfide", "Sulfur", "[#16;!H0,$(*-[#6])]-[#16;!H0,$(*-[#6])]")
 }
 pub fn sulfoxide() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "S", "O", "R"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 2);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup{molecule, name : "Sulfoxide"}
+    FunctGroup::new("Sulfoxide", "Sulfur", "[#16;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]=[#8]")
+        .refines(&["Sulfide"])
 }
 
 // ************ phosphorus containing **************
 
 pub fn phosphonic_acid() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "P", "O", "O", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 2);
-    molecule.add_bond(1, 3, 1);
-    molecule.add_bond(1, 4, 1);
-    FunctGroup{molecule, name : "Phosphonic Acid"}
+    FunctGroup::new("Phosphonic Acid", "Phosphorus", "[#15;!H0,$(*-[#6])](=[#8])(-[#8])-[#8]")
 }
 pub fn phosphate() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "O", "P", "O", "O", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(2, 3, 2);
-    molecule.add_bond(2, 4, 1);
-    molecule.add_bond(2, 5, 1);
-    FunctGroup{molecule, name : "Phosphate"}
+    FunctGroup::new("Phosphate", "Phosphorus", "[#8;!H0,$(*-[#6])]-[#15](=[#8])(-[#8])-[#8]")
 }
 
 // ****************** boron containing ***************************
 
 pub fn borono() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "B", "O", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(2, 3, 1);
-    FunctGroup{molecule, name : "Borono"}
+    FunctGroup::new("Borono", "Boron", "[#5;!H0,$(*-[#6])](-[#8])-[#8]")
 }
 pub fn boronate() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "B", "R", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(1, 3, 1);
-    FunctGroup{molecule, name : "Boronate"}
+    FunctGroup::new("Boronate", "Boron", "[#5;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]-[#8]")
 }
 pub fn borino() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "B", "R", "O"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    molecule.add_bond(1, 3, 1);
-    FunctGroup{molecule, name : "Borino"}
+    FunctGroup::new("Borino", "Boron", "[#5;H2,H1$(*-[#6]),$(*(-[#6])-[#6])]-[#8]")
 }
 
 // ******************* organo metallics ****************************8
 
 pub fn r_li() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "Li"));
-    molecule.add_bond(0, 1, 1);
-    FunctGroup{molecule, name : "RLi"}
+    FunctGroup::new("RLi", "Organometallic", "[#3;!H0,$(*-[#6])]")
 }
+// any halogen on the magnesium
 pub fn grignard() -> FunctGroup {
-    let mut molecule = Molecule::new(vec!("R", "Mg", "X"));
-    molecule.add_bond(0, 1, 1);
-    molecule.add_bond(1, 2, 1);
-    FunctGroup{molecule, name : "Grignard"}
+    FunctGroup::new("Grignard", "Organometallic", "[#12;!H0,$(*-[#6])]-[F,Cl,Br,I]")
 }
+
@@ -0,0 +1,187 @@
+/*
+Parent/child relationships between functional groups, used to report the most specific group
+found on each atom.
+
+A group lists the more general groups it refines as its parents, a carboxyl is a carbonyl and a
+hydroxyl on the same carbon. When a group consumes its atoms, a match of one of its ancestors is
+dropped if every one of the match's atoms was taken by the group or by another group below that
+ancestor. So the carbonyl and hydroxyl of a carboxylic acid are reported as just the carboxyl,
+while a ketone elsewhere in the same molecule is still a carbonyl.
+*/
+
+use std::fmt;
+
+use super::funct_groups::FunctGroup;
+use super::recognizer::Mapping;
+
+// Error type returned when the parents of a set of groups don't form a hierarchy
+#[derive(Debug, PartialEq)]
+pub enum HierarchyError {
+    // A group names a parent that isn't one of the groups
+    UnknownParent { group : String, parent : String },
+    // A group is its own ancestor
+    Cycle { group : String },
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HierarchyError::UnknownParent { group, parent } =>
+                write!(f, "group {:?}: unknown parent group {:?}", group, parent),
+            HierarchyError::Cycle { group } => write!(f, "group {:?}: is its own ancestor", group),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+// The matches left once the most specific groups have taken their atoms
+pub struct Resolved {
+    // the kept matches of each group, in the same order as the groups
+    pub matches : Vec<Vec<Mapping>>,
+    // the most specific group found on each atom of the molecule, None for atoms in no group
+    pub atom_groups : Vec<Option<usize>>,
+}
+
+pub struct Hierarchy {
+    // descendants[x][y] is true when group y is below group x
+    descendants : Vec<Vec<bool>>,
+    // the groups from the most specific to the least, every group comes after the groups below it
+    order : Vec<usize>,
+}
+
+impl Hierarchy {
+    // Parents are found by name, a parent name shared by several groups makes all of them parents
+    pub fn new(groups : &[FunctGroup]) -> Result<Hierarchy, HierarchyError> {
+        let mut children : Vec<Vec<usize>> = vec![Vec::new(); groups.len()];
+        for (child, group) in groups.iter().enumerate() {
+            for parent in &group.parents {
+                let mut found = false;
+                for (i, x) in groups.iter().enumerate() {
+                    if x.name == *parent {
+                        children[i].push(child);
+                        found = true;
+                    }
+                }
+                if !found {
+                    return Err(HierarchyError::UnknownParent { group : group.name.clone(), parent : parent.clone() })
+                }
+            }
+        }
+
+        let mut descendants = vec![vec![false; groups.len()]; groups.len()];
+        for (group, below) in descendants.iter_mut().enumerate() {
+            let mut stack = children[group].clone();
+            while let Some(x) = stack.pop() {
+                if x == group {
+                    return Err(HierarchyError::Cycle { group : groups[group].name.clone() })
+                }
+                if !below[x] {
+                    below[x] = true;
+                    stack.extend(&children[x]);
+                }
+            }
+        }
+
+        // a group has more descendants than any group below it, the sort is stable so groups of
+        // the same rank stay in their listed order
+        let mut order : Vec<usize> = (0..groups.len()).collect();
+        order.sort_by_key(|x| descendants[*x].iter().filter(|y| **y).count());
+
+        Ok(Hierarchy { descendants, order })
+    }
+
+    // Whether group below is a descendant of group above
+    pub fn is_below(&self, below : usize, above : usize) -> bool {
+        self.descendants[above][below]
+    }
+
+    // Drops the matches of each group whose atoms were all consumed by groups below it
+    // matches are the matches of every group, atom_count the number of atoms in the molecule
+    pub fn resolve(&self, groups : &[FunctGroup], matches : &[Vec<Mapping>], atom_count : usize) -> Resolved {
+        // the consuming groups that have taken each atom
+        let mut consumed : Vec<Vec<usize>> = vec![Vec::new(); atom_count];
+        let mut kept : Vec<Vec<Mapping>> = vec![Vec::new(); groups.len()];
+        let mut atom_groups : Vec<Option<usize>> = vec![None; atom_count];
+
+        for group in self.order.iter().copied() {
+            for mapping in &matches[group] {
+                let atoms : Vec<usize> = mapping.iter().flatten().copied().collect();
+                let covered = !atoms.is_empty() && atoms.iter()
+                    .all(|x| consumed[*x].iter().any(|y| self.is_below(*y, group)));
+                if covered {
+                    continue
+                }
+                for x in atoms {
+                    if groups[group].consumes {
+                        consumed[x].push(group);
+                    }
+                    atom_groups[x].get_or_insert(group);
+                }
+                kept[group].push(mapping.clone());
+            }
+        }
+
+        Resolved { matches : kept, atom_groups }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::funct_groups::funct_groups::get_funct_groups;
+    use crate::funct_groups::smarts::parse_smarts;
+
+    fn group(name : &str, parents : &[&str]) -> FunctGroup {
+        FunctGroup {
+            name : name.to_string(),
+            category : None,
+            description : None,
+            smarts : None,
+            query : parse_smarts("C").unwrap(),
+            parents : parents.iter().map(|x| x.to_string()).collect(),
+            consumes : true,
+        }
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let groups = vec![group("A", &[]), group("B", &["A"]), group("C", &["B"]), group("D", &[])];
+        let hierarchy = Hierarchy::new(&groups).unwrap();
+        assert!(hierarchy.is_below(2, 0));
+        assert!(hierarchy.is_below(1, 0));
+        assert!(!hierarchy.is_below(0, 1));
+        assert!(!hierarchy.is_below(3, 0));
+        assert_eq!(hierarchy.order, vec![2, 3, 1, 0]);
+
+        assert_eq!(Hierarchy::new(&[group("A", &["B"])]).err(),
+            Some(HierarchyError::UnknownParent { group : "A".to_string(), parent : "B".to_string() }));
+        assert_eq!(Hierarchy::new(&[group("A", &["B"]), group("B", &["A"])]).err(),
+            Some(HierarchyError::Cycle { group : "A".to_string() }));
+        assert!(Hierarchy::new(&get_funct_groups()).is_ok());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut groups = vec![group("A", &[]), group("B", &["A"]), group("C", &[])];
+        let matches = vec![
+            vec![vec![Some(0), Some(1)], vec![Some(1), Some(3)]],
+            vec![vec![Some(0), Some(1), Some(2)]],
+            vec![vec![Some(1), Some(2)]],
+        ];
+        let resolved = Hierarchy::new(&groups).unwrap().resolve(&groups, &matches, 5);
+        // the first match of A is all inside B, the second only partly
+        assert_eq!(resolved.matches[0], vec![vec![Some(1), Some(3)]]);
+        assert_eq!(resolved.matches[1], matches[1]);
+        // C isn't related to B, so it keeps its match
+        assert_eq!(resolved.matches[2], matches[2]);
+        assert_eq!(resolved.atom_groups, vec![Some(1), Some(1), Some(1), Some(0), None]);
+
+        // a group that doesn't consume its atoms hides nothing
+        groups[1].consumes = false;
+        let resolved = Hierarchy::new(&groups).unwrap().resolve(&groups, &matches, 5);
+        assert_eq!(resolved.matches[0], matches[0]);
+        assert_eq!(resolved.atom_groups[0], Some(1));
+    }
+}
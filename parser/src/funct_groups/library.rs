@@ -0,0 +1,266 @@
+/*
+Functional group libraries read from a TOML or JSON file, so groups can be added without
+rebuilding. Every group has a name, an optional description and category, and either a SMARTS
+pattern or a list of atoms and bonds:
+
+    [[groups]]
+    name = "Ketone"
+    category = "Oxygen"
+    description = "A carbonyl between two carbons"
+    smarts = "[#6][CX3](=O)[#6]"
+
+    [[groups]]
+    name = "Acyl Halide"
+    atoms = ["[#6]", "O", "[F,Cl,Br,I]"]
+    bonds = [[0, 1, 2], [0, 2, 1]]
+
+A group can name the more general groups it refines as its parents, and with consumes = true a
+match of one of those is not reported on the atoms the group took when the result is resolved:
+
+    [[groups]]
+    name = "Methyl Ketone"
+    smarts = "[CH3]C(=O)[#6]"
+    parents = ["Ketone"]
+    consumes = true
+
+The JSON form is the same, {"groups" : [{"name" : "Ketone", ...}, ...]}. The atoms of an atom
+list are single SMARTS atoms, and the bonds are (atom, atom, bond type) with the .mol bond types,
+1, 2, 3 and 4 for single, double, triple and aromatic, 5 to 8 for the query bonds.
+*/
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::molecule::error::SmartsError;
+use super::funct_groups::FunctGroup;
+use super::hierarchy::{Hierarchy, HierarchyError};
+use super::smarts::{SmartsQuery, BondExpression, BondPrimitive, Expression, parse_smarts};
+
+// Error type returned when a functional group library can't be read
+#[derive(Debug)]
+pub enum LibraryError {
+    // The file could not be read
+    Io(std::io::Error),
+    // The file doesn't end in .toml or .json
+    UnknownFormat { path : String },
+    // The file isn't valid TOML or JSON, or doesn't have the fields of a library
+    Syntax(String),
+    // A group has both or neither of a SMARTS pattern and an atom list
+    MissingPattern { name : String },
+    // A group's SMARTS pattern doesn't parse
+    BadSmarts { name : String, error : SmartsError },
+    // An atom of an atom list isn't a single SMARTS atom
+    BadAtom { name : String, atom : String },
+    // A bond of an atom list has an atom that isn't in the list, an unknown bond type, or bonds
+    // two atoms that are already bonded
+    BadBond { name : String, bond : (usize, usize, i32) },
+    // The parents of the groups name a missing group or form a cycle
+    Hierarchy(HierarchyError),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LibraryError::Io(e) => write!(f, "could not read functional group library: {}", e),
+            LibraryError::UnknownFormat { path } =>
+                write!(f, "{}: functional group libraries are .toml or .json files", path),
+            LibraryError::Syntax(e) => write!(f, "bad functional group library: {}", e),
+            LibraryError::MissingPattern { name } =>
+                write!(f, "group {:?}: needs either a SMARTS pattern or a list of atoms", name),
+            LibraryError::BadSmarts { name, error } => write!(f, "group {:?}: {}", name, error),
+            LibraryError::BadAtom { name, atom } =>
+                write!(f, "group {:?}: {:?} is not a single SMARTS atom", name, atom),
+            LibraryError::BadBond { name, bond } => write!(f, "group {:?}: bad bond {:?}", name, bond),
+            LibraryError::Hierarchy(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LibraryError::Io(e) => Some(e),
+            LibraryError::BadSmarts { error, .. } => Some(error),
+            LibraryError::Hierarchy(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LibraryError {
+    fn from(e : std::io::Error) -> Self {
+        LibraryError::Io(e)
+    }
+}
+
+impl From<HierarchyError> for LibraryError {
+    fn from(e : HierarchyError) -> Self {
+        LibraryError::Hierarchy(e)
+    }
+}
+
+// The formats a library can be written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LibraryFormat {
+    Toml,
+    Json,
+}
+
+impl LibraryFormat {
+    // Reads a format name or file extension, "toml" or "json" in any case
+    pub fn parse(name : &str) -> Option<LibraryFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "toml" => Some(LibraryFormat::Toml),
+            "json" => Some(LibraryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LibraryFile {
+    groups : Vec<GroupEntry>,
+}
+
+// A group as it is written in the file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupEntry {
+    name : String,
+    description : Option<String>,
+    category : Option<String>,
+    smarts : Option<String>,
+    atoms : Option<Vec<String>>,
+    #[serde(default)]
+    bonds : Vec<(usize, usize, i32)>,
+    #[serde(default)]
+    parents : Vec<String>,
+    #[serde(default)]
+    consumes : bool,
+}
+
+// The bond expression of a .mol bond type
+fn bond_expression(bond_type : i32) -> Option<BondExpression> {
+    let primitive = |x| Expression::Primitive(x);
+    let either = |x, y| Expression::Or(vec![Expression::Primitive(x), Expression::Primitive(y)]);
+    match bond_type {
+        1 => Some(primitive(BondPrimitive::Single)),
+        2 => Some(primitive(BondPrimitive::Double)),
+        3 => Some(primitive(BondPrimitive::Triple)),
+        4 => Some(primitive(BondPrimitive::Aromatic)),
+        5 => Some(either(BondPrimitive::Single, BondPrimitive::Double)),
+        6 => Some(either(BondPrimitive::Single, BondPrimitive::Aromatic)),
+        7 => Some(either(BondPrimitive::Double, BondPrimitive::Aromatic)),
+        8 => Some(primitive(BondPrimitive::Any)),
+        _ => None,
+    }
+}
+
+// Builds a query from an atom list, each atom is one SMARTS atom
+fn query_from_atoms(name : &str, atoms : &[String], bonds : &[(usize, usize, i32)]) -> Result<SmartsQuery, LibraryError> {
+    let mut query = SmartsQuery { atoms : Vec::new(), bonds : Vec::new() };
+    for atom in atoms {
+        let bad_atom = || LibraryError::BadAtom { name : name.to_string(), atom : atom.clone() };
+        let mut parsed = parse_smarts(atom).map_err(|_| bad_atom())?;
+        if parsed.atoms.len() != 1 {
+            return Err(bad_atom())
+        }
+        query.atoms.push(parsed.atoms.remove(0));
+    }
+    for (x, y, bond_type) in bonds.iter().copied() {
+        let bad_bond = || LibraryError::BadBond { name : name.to_string(), bond : (x, y, bond_type) };
+        let bonded = query.bonds.iter().any(|(a, b, _)| (*a == x && *b == y) || (*a == y && *b == x));
+        if x >= atoms.len() || y >= atoms.len() || x == y || bonded {
+            return Err(bad_bond())
+        }
+        query.bonds.push((x, y, bond_expression(bond_type).ok_or_else(bad_bond)?));
+    }
+    Ok(query)
+}
+
+fn build_group(entry : GroupEntry) -> Result<FunctGroup, LibraryError> {
+    let query = match (&entry.smarts, &entry.atoms) {
+        (Some(smarts), None) => parse_smarts(smarts).map_err(|error| LibraryError::BadSmarts { name : entry.name.clone(), error })?,
+        (None, Some(atoms)) => query_from_atoms(&entry.name, atoms, &entry.bonds)?,
+        _ => return Err(LibraryError::MissingPattern { name : entry.name }),
+    };
+    Ok(FunctGroup {
+        name : entry.name,
+        category : entry.category,
+        description : entry.description,
+        smarts : entry.smarts,
+        query,
+        parents : entry.parents,
+        consumes : entry.consumes,
+    })
+}
+
+// Reads the groups of a library from its contents
+pub fn parse_library(contents : &str, format : LibraryFormat) -> Result<Vec<FunctGroup>, LibraryError> {
+    let file : LibraryFile = match format {
+        LibraryFormat::Toml => toml::from_str(contents).map_err(|e| LibraryError::Syntax(e.to_string()))?,
+        LibraryFormat::Json => serde_json::from_str(contents).map_err(|e| LibraryError::Syntax(e.to_string()))?,
+    };
+    let groups = file.groups.into_iter().map(build_group).collect::<Result<Vec<FunctGroup>, LibraryError>>()?;
+    Hierarchy::new(&groups)?;
+    Ok(groups)
+}
+
+// Reads the groups of a library file, the format comes from the .toml or .json extension
+pub fn load_library(path : &str) -> Result<Vec<FunctGroup>, LibraryError> {
+    let format = Path::new(path).extension().and_then(|x| x.to_str()).and_then(LibraryFormat::parse)
+        .ok_or_else(|| LibraryError::UnknownFormat { path : path.to_string() })?;
+    parse_library(&std::fs::read_to_string(path)?, format)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::funct_groups::recognizer::has_query_match;
+    use crate::molecule::smiles::parse_smiles;
+
+    static TEST_DIR : &str = "src/funct_groups/test_files/";
+
+    #[test]
+    fn test_load_library() {
+        let toml = load_library(&(TEST_DIR.to_owned() + "groups.toml")).unwrap();
+        let json = load_library(&(TEST_DIR.to_owned() + "groups.json")).unwrap();
+        for groups in [&toml, &json].iter() {
+            let names : Vec<&str> = groups.iter().map(|x| x.name.as_str()).collect();
+            assert_eq!(names, vec!["Ketone", "Acyl Halide", "Alkyne"]);
+            assert_eq!(groups[0].category.as_deref(), Some("Oxygen"));
+            assert_eq!(groups[0].description.as_deref(), Some("A carbonyl between two carbons"));
+            assert_eq!(groups[1].smarts, None);
+            assert_eq!(groups[2].category, None);
+        }
+        assert_eq!(toml[1].query, json[1].query);
+
+        // the atom list finds the same acid halides as the SMARTS would
+        let acyl_halide = &toml[1].query;
+        assert!(has_query_match(&parse_smiles("CC(=O)Br").unwrap(), acyl_halide));
+        assert!(!has_query_match(&parse_smiles("CC(=O)O").unwrap(), acyl_halide));
+        assert!(has_query_match(&parse_smiles("CC(C)=O").unwrap(), &toml[0].query));
+        assert!(!has_query_match(&parse_smiles("CC(=O)OC").unwrap(), &toml[0].query));
+    }
+
+    #[test]
+    fn test_bad_libraries() {
+        let toml = |x : &str| parse_library(x, LibraryFormat::Toml);
+        assert!(matches!(toml("[[groups]]\nsmarts = \"C\""), Err(LibraryError::Syntax(_))));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\nsmart = \"C\""), Err(LibraryError::Syntax(_))));
+        assert!(matches!(toml("[[groups]]\nname = \"A\""), Err(LibraryError::MissingPattern { .. })));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\nsmarts = \"C(\""), Err(LibraryError::BadSmarts { .. })));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\natoms = [\"CC\"]"), Err(LibraryError::BadAtom { .. })));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\natoms = [\"R\"]"), Err(LibraryError::BadAtom { .. })));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\natoms = [\"C\", \"O\"]\nbonds = [[0, 2, 1]]"), Err(LibraryError::BadBond { .. })));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\natoms = [\"C\", \"O\"]\nbonds = [[0, 1, 9]]"), Err(LibraryError::BadBond { .. })));
+        assert!(matches!(parse_library("{\"groups\" : [}", LibraryFormat::Json), Err(LibraryError::Syntax(_))));
+        assert!(matches!(toml("[[groups]]\nname = \"A\"\nsmarts = \"C\"\nparents = [\"B\"]"), Err(LibraryError::Hierarchy(_))));
+        assert!(matches!(load_library("src/molecule/test_files/Methane.mol"), Err(LibraryError::UnknownFormat { .. })));
+        assert!(matches!(load_library("src/funct_groups/test_files/missing.toml"), Err(LibraryError::Io(_))));
+    }
+}
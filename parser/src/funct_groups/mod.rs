@@ -1,2 +1,6 @@
 pub mod funct_groups;
-pub mod recognizer;
\ No newline at end of file
+pub mod recognizer;
+pub mod vf2;
+pub mod smarts;
+pub mod library;
+pub mod hierarchy;
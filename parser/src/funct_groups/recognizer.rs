@@ -1,8 +1,12 @@
 /*
-Traverses the molecule adjacency matrix and recognizes against the functional group patterns
+Recognizes the functional group patterns in a molecule
 */
 
+use std::collections::HashSet;
+
 use crate::molecule::molecule::*;
+use super::vf2;
+use super::smarts::{SmartsQuery, SmartsTarget};
 
 static DEBUG_LEVEL : i32 = 1;
 
@@ -15,137 +19,93 @@ macro_rules! debug_println {
 }
 
 
-// finds sub instances of the subMole in majorMol
-// goes through each atom of the majorMol, until it exhausts the search or finds the whole
-// subgraph of subMol in majorMol
-// Matches atom and bond arrangements\
-// Usually, the least common organic atoms are at the end of the list, so it would be more
-// efficient to start from the end of the atom vec
-// TODO Needs to be optimized.
-pub fn is_subgraph(maj_mol : &Molecule, sub_mol : &Molecule) -> bool {
-
-    debug_println!("{}", maj_mol.to_string());
-    debug_println!("{}", sub_mol.to_string());
-  
-    let maj_atoms = &maj_mol.atoms;
-    let sub_atoms = &sub_mol.atoms;
-
-    // sub_atom is the first atom in the sub_mol that is not R
-    let sub_atom_j = get_not_r(sub_atoms);
-    let sub_atom = &sub_atoms[sub_atom_j];
-
-    for i in (0..maj_atoms.len()).rev() {
-        let maj_atom = &maj_atoms[i];
-        if maj_atom == sub_atom {
-
-            // visited keeps track of the visited sub atoms
-            let mut sub_visited = vec![false; sub_atoms.len()];
-            let mut maj_visited = vec![false; maj_atoms.len()];
-            
-            let res = is_subgraph_from_here(maj_mol, i, sub_mol, sub_atom_j ,
-                &mut sub_visited, &mut maj_visited);
-
-            if res {return true}
-        }
-    }
-    false
-} 
-
-fn get_not_r(atoms : &Vec<String>) -> usize {
-    for j in (0..atoms.len()).rev() {
-        if atoms[j] != "R" {
-            return j     
-        }
-    }
-    return 0
+// Gets a molecule ready to be matched against the functional groups: every implicit hydrogen is
+// added as an atom, so drawn and implicit hydrogens are the same and an "R" can stand for any of
+// them, and its aromatic rings get aromatic bonds, so either Kekulé structure matches the
+// aromatic patterns. The atoms of the molecule keep their indexes, the new hydrogens come after
+pub fn prepare_target(maj_mol : &Molecule) -> Molecule {
+    let mut target = maj_mol.clone();
+    target.aromatize();
+    target.add_explicit_hydrogens();
+    target
 }
 
-fn is_subgraph_from_here(maj_mol : &Molecule, maj_i : usize,
-    sub_mol : &Molecule, sub_i : usize, visited_sub_atoms : &mut Vec<bool>,
-    visited_maj_atoms : &mut Vec<bool>) -> bool {
-
-    debug_println!("is_subgraph_from_here(maj_i : {}, sub_i : {})", maj_i, sub_i);
-
-    // If cyclic
-    if visited_sub_atoms[sub_i] {
-        debug_println!("true, sub visited");
-        return true 
-    }
-    if visited_maj_atoms[maj_i] {
-        debug_println!("false, maj visited");
-        return false 
-    }
-
-    // marks the current sub_atom as visited
-    visited_sub_atoms[sub_i] = true;
-    visited_maj_atoms[maj_i] = true;
+// A match of a pattern, the molecule atom of each pattern atom, None where an "R" matched one of
+// the implicit hydrogens
+pub type Mapping = Vec<Option<usize>>;
 
-    let maj_bonds = get_bond_list(maj_mol, maj_i);
-    let sub_bonds = get_bond_list(sub_mol, sub_i);
-
-    for j in 0..sub_bonds.len() {
-        if sub_bonds[j] == 0 {
-            continue
-        }
-        
-        // Allows "R" to act as H atom, meaning nothing - which means no maj_bonds apart from 0
-        let mut should_check_maj_bonds = false;
-        let mut cntr = 0;
-        if sub_mol.atoms[j] == "R" {
-            should_check_maj_bonds = true; 
-        }
-        
-        let mut at_least_one = false;
-        for i in 0..maj_bonds.len() {
-            if maj_bonds[i] == 0 {
-                continue
-            }
+// Whether sub_mol is a subgraph of a molecule from prepare_target
+// Pattern atoms match by Atom::matches, so "R" matches a carbon or a hydrogen, and bonds match by
+// type. The recognizer only compares elements and bonds, not charges
+pub fn is_prepared_subgraph(target : &Molecule, sub_mol : &Molecule) -> bool {
+    debug_println!("{}", target.to_string());
+    debug_println!("{}", sub_mol.to_string());
+    vf2::has_match(target, sub_mol, |p, t| sub_mol.atoms[p].matches(&target.atoms[t]), vf2::molecule_bonds_match(target, sub_mol))
+}
 
-            // Counts the number of maj_bonds
-            if should_check_maj_bonds {
-                cntr += 1;
+// Every match of sub_mol in a molecule from prepare_target, atom_count is the number of atoms the
+// molecule had before it was prepared
+// With unique, matches that put the pattern's non "R" atoms on the same molecule atoms are
+// collapsed into the first of them, so benzene has one aryl group instead of 12, and methylamine
+// has one amine whether the "R" is the carbon or either hydrogen
+pub fn prepared_matches(target : &Molecule, atom_count : usize, sub_mol : &Molecule, unique : bool) -> Vec<Mapping> {
+    let core : Vec<usize> = (0..sub_mol.atoms.len()).filter(|x| sub_mol.atoms[*x] != Atom::Query(QueryAtom::R)).collect();
+    let mut seen : HashSet<Vec<usize>> = HashSet::new();
+    let mut out = Vec::new();
+    vf2::for_each_match(target, sub_mol, |p, t| sub_mol.atoms[p].matches(&target.atoms[t]), vf2::molecule_bonds_match(target, sub_mol), |mapping| {
+        if unique {
+            let mut key : Vec<usize> = core.iter().map(|x| mapping[*x]).collect();
+            key.sort_unstable();
+            if !seen.insert(key) {
+                return false
             }
+        }
+        out.push(mapping.iter().map(|x| if *x < atom_count { Some(*x) } else { None }).collect());
+        false
+    });
+    out
+}
 
-            if sub_bonds[j] != maj_bonds[i] {
-                continue
-            }
+// Every match of sub_mol in maj_mol, see prepared_matches
+pub fn find_matches(maj_mol : &Molecule, sub_mol : &Molecule, unique : bool) -> Vec<Mapping> {
+    prepared_matches(&prepare_target(maj_mol), maj_mol.atoms.len(), sub_mol, unique)
+}
 
-            if sub_mol.atoms[j] == "R" && maj_mol.atoms[i] == "C" {
-                at_least_one = true;
-                break
-            }
+// finds sub instances of the subMole in majorMol
+// Checks every mapping of the sub_mol atoms onto distinct maj_mol atoms until one has every
+// sub_mol bond, see vf2
+pub fn is_subgraph(maj_mol : &Molecule, sub_mol : &Molecule) -> bool {
+    is_prepared_subgraph(&prepare_target(maj_mol), sub_mol)
+}
 
-            // sub_mol.atoms[j] refers to the recieving atom of the current bond that is being 
-            // examined.
-            if sub_mol.atoms[j] != maj_mol.atoms[i] {
-                continue
+// Every match of a SMARTS query in a target, as atoms of the molecule the target was made from
+// With unique, matches of the same set of atoms are collapsed into the first of them, so benzene
+// has one aryl group instead of 12
+pub fn query_matches(target : &SmartsTarget, query : &SmartsQuery, unique : bool) -> Vec<Mapping> {
+    let mut seen : HashSet<Vec<usize>> = HashSet::new();
+    let mut out = Vec::new();
+    query.for_each_match(target, |mapping| {
+        if unique {
+            let mut key = mapping.to_vec();
+            key.sort_unstable();
+            if !seen.insert(key) {
+                return false
             }
-
-            at_least_one = is_subgraph_from_here(maj_mol, i, sub_mol, j, visited_sub_atoms, 
-                visited_maj_atoms);
-            if at_least_one {break}
         }
-        
-        // handles 0 maj_bonds    (can still count as being bonded to hydrogen)
-        if should_check_maj_bonds && cntr == 0 {
-            continue
-        }
-
-        if !at_least_one {debug_println!("false"); return false}
-    }
-    debug_println!("true");
-    true
+        out.push(mapping.iter().map(|x| Some(target.original[*x])).collect());
+        false
+    });
+    out
 }
 
+// Every match of a SMARTS query in maj_mol, see query_matches
+pub fn find_query_matches(maj_mol : &Molecule, query : &SmartsQuery, unique : bool) -> Vec<Mapping> {
+    query_matches(&SmartsTarget::new(maj_mol), query, unique)
+}
 
-// Returns a vec of the bond list
-fn get_bond_list(mol : &Molecule, index : usize) -> Vec<i32> {
-    let len = mol.atoms.len();
-    let mut out : Vec<i32> = Vec::new();
-    for x in 0..len {
-        out.push(mol.get(index, x));
-    }
-    out
+// Whether a SMARTS query is found in maj_mol
+pub fn has_query_match(maj_mol : &Molecule, query : &SmartsQuery) -> bool {
+    query.has_match(&SmartsTarget::new(maj_mol))
 }
 
 
@@ -153,47 +113,45 @@ fn get_bond_list(mol : &Molecule, index : usize) -> Vec<i32> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::funct_groups::funct_groups::aryl;
-    use crate::funct_groups::funct_groups::carboxyl;
-    use crate::funct_groups::funct_groups::ether;
+    use crate::funct_groups::funct_groups::*;
     use crate::molecule::parser::parse_mol;
-    use crate::funct_groups::funct_groups::carbonyl;
+    use crate::molecule::smiles::parse_smiles;
+    use crate::funct_groups::smarts::parse_smarts;
 
     #[test]
     fn demonstration() {
-        assert!(is_subgraph(&parse_mol("src/molecule/test_files/Pentanoic acid.mol"), &carbonyl().molecule));
+        assert!(has_query_match(&parse_mol("src/molecule/test_files/Pentanoic acid.mol").unwrap(), &carbonyl().query));
     }
     #[test]
     fn test_is_subgraph() {
-        let maj_mol = &parse_mol("src/molecule/test_files/Pentanoic acid.mol");
-        let sub_mol = &carbonyl().molecule;
-        let mut res = is_subgraph(maj_mol, sub_mol);
+        let maj_mol = &parse_mol("src/molecule/test_files/Pentanoic acid.mol").unwrap();
+        let sub_mol = &carbonyl().query;
+        let mut res = has_query_match(maj_mol, sub_mol);
         assert!(res);
-        let sub_mol = &carboxyl().molecule;
-        println!("{}", sub_mol.to_string());
-        res = is_subgraph(maj_mol, sub_mol); 
+        let sub_mol = &carboxyl().query;
+        res = has_query_match(maj_mol, sub_mol); 
         assert!(res);
 
-        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol");
-        let sub_mol = &carbonyl().molecule;
-        res = is_subgraph(maj_mol, sub_mol);
+        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol").unwrap();
+        let sub_mol = &carbonyl().query;
+        res = has_query_match(maj_mol, sub_mol);
         assert!(res);
-        let sub_mol = &carboxyl().molecule;
-        res = is_subgraph(maj_mol, sub_mol);
+        let sub_mol = &carboxyl().query;
+        res = has_query_match(maj_mol, sub_mol);
         assert!(res);
 
-        let maj_mol = &parse_mol("src/molecule/test_files/Methane.mol");
-        assert_eq!(is_subgraph(maj_mol, sub_mol), false);
-        let sub_mol = &carboxyl().molecule;
-        assert_eq!(is_subgraph(maj_mol, sub_mol), false);
-        let sub_mol = &aryl().molecule;
-        assert_eq!(is_subgraph(maj_mol, sub_mol), false);
+        let maj_mol = &parse_mol("src/molecule/test_files/Methane.mol").unwrap();
+        assert!(!has_query_match(maj_mol, sub_mol));
+        let sub_mol = &carboxyl().query;
+        assert!(!has_query_match(maj_mol, sub_mol));
+        let sub_mol = &aryl().query;
+        assert!(!has_query_match(maj_mol, sub_mol));
     }
     #[test]
     fn test_r_wildcard() {
-        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol");
-        let sub_mol = &ether().molecule;
-        assert_eq!(is_subgraph(maj_mol, sub_mol), false);
+        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol").unwrap();
+        let sub_mol = &ether().query;
+        assert!(!has_query_match(maj_mol, sub_mol));
     }
     #[test]
     fn test_debug_print() {
@@ -201,9 +159,137 @@ mod tests {
     }
     #[test]
     fn test_cyclic() {
-        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol");
-        println!("{}", maj_mol.to_string());
-        assert!(is_subgraph(maj_mol, &carboxyl().molecule));
-        assert!(is_subgraph(maj_mol, &aryl().molecule))
+        let maj_mol = &parse_mol("src/molecule/test_files/Benzoic acid.mol").unwrap();
+        println!("{}", maj_mol);
+        assert!(has_query_match(maj_mol, &carboxyl().query));
+        assert!(has_query_match(maj_mol, &aryl().query))
+    }
+    #[test]
+    fn test_kekule_structures() {
+        // both Kekulé structures and the aromatic SMILES of toluene and pyridine
+        for smiles in ["CC1=CC=CC=C1", "CC1C=CC=CC=1", "Cc1ccccc1"].iter() {
+            let maj_mol = parse_smiles(smiles).unwrap();
+            assert!(has_query_match(&maj_mol, &aryl().query), "{}", smiles);
+            assert!(!has_query_match(&maj_mol, &pyridyl().query), "{}", smiles);
+        }
+        for smiles in ["C1=CC=NC=C1", "C1C=CN=CC=1", "c1ccncc1"].iter() {
+            let maj_mol = parse_smiles(smiles).unwrap();
+            assert!(has_query_match(&maj_mol, &pyridyl().query), "{}", smiles);
+        }
+    }
+    #[test]
+    fn test_regression_suite() {
+        // (pattern, molecules that have it, molecules that don't)
+        let suite : Vec<(FunctGroup, Vec<&str>, Vec<&str>)> = vec![
+            (carbonyl(), vec!["CC(C)=O", "O=Cc1ccccc1"], vec!["CCO", "C=C"]),
+            (carboxyl(), vec!["CC(=O)O", "OC(=O)c1ccccc1"], vec!["CC(C)=O", "CCO"]),
+            (acid_chloride(), vec!["CC(=O)Cl"], vec!["CCCl", "CC(=O)O"]),
+            (aryl(), vec!["c1ccccc1", "C1=CC=CC=C1", "Oc1ccc2ccccc2c1"], vec!["C1CCCCC1", "c1ccncc1", "C1=CCC=CC1"]),
+            (hydroxyl(), vec!["CCO", "Oc1ccccc1"], vec!["C", "O", "CC=O"]),
+            (ester(), vec!["CC(=O)OC"], vec!["CC(=O)O", "COC"]),
+            (peroxide(), vec!["COOC", "OO"], vec!["CCO", "COC"]),
+            (ether(), vec!["CCOCC", "COc1ccccc1"], vec!["CCO", "OC(=O)c1ccccc1", "COOC"]),
+            (anhydride(), vec!["CC(=O)OC(C)=O"], vec!["CC(=O)OCC", "CC(=O)O"]),
+            (amide(), vec!["CC(N)=O", "CN(C)C=O"], vec!["CC(=O)O", "CCN"]),
+            (amine(), vec!["CN", "CN(C)C"], vec!["CC#N", "c1ccncc1"]),
+            (imine(), vec!["CC=NC"], vec!["CCN", "CC#N"]),
+            (nitrile(), vec!["CC#N"], vec!["CN", "CC=N"]),
+            (pyridyl(), vec!["c1ccncc1", "C1=CC=NC=C1"], vec!["c1ccccc1", "C1CCNCC1"]),
+            (nitro(), vec!["C[N+](=O)[O-]"], vec!["CN", "CN=O"]),
+            (sulfide(), vec!["CSC", "CS"], vec!["CC(C)=O", "CCO"]),
+            (disulfide(), vec!["CSSC"], vec!["CSC"]),
+            (sulfoxide(), vec!["CS(C)=O"], vec!["CSC", "CC(C)=O"]),
+            (phosphonic_acid(), vec!["CP(=O)(O)O"], vec!["COP(=O)(O)O", "CP(C)C"]),
+            (phosphate(), vec!["COP(=O)(O)O"], vec!["CP(=O)(O)O"]),
+            (borono(), vec!["OB(O)c1ccccc1"], vec!["CB(C)C", "CBOO"]),
+            (r_li(), vec!["CCCC[Li]"], vec!["[Li+].[Cl-]"]),
+            (grignard(), vec!["C[Mg]Br"], vec!["Cl[Mg]Cl", "CBr"]),
+        ];
+        for (group, positives, negatives) in suite {
+            for smiles in positives {
+                assert!(has_query_match(&parse_smiles(smiles).unwrap(), &group.query), "{} in {}", group, smiles);
+            }
+            for smiles in negatives {
+                assert!(!has_query_match(&parse_smiles(smiles).unwrap(), &group.query), "{} not in {}", group, smiles);
+            }
+        }
+    }
+    #[test]
+    fn test_find_matches() {
+        let benzene = parse_smiles("c1ccccc1").unwrap();
+        assert_eq!(find_query_matches(&benzene, &aryl().query, false).len(), 12);
+        assert_eq!(find_query_matches(&benzene, &aryl().query, true).len(), 1);
+
+        // the two ester groups of dimethyl oxalate, each ester runs one way, C(=O)-O-CH3
+        let oxalate = parse_smiles("COC(=O)C(=O)OC").unwrap();
+        let matches = find_query_matches(&oxalate, &ester().query, true);
+        assert_eq!(matches, vec![
+            vec![Some(2), Some(3), Some(1), Some(0)],
+            vec![Some(4), Some(5), Some(6), Some(7)],
+        ]);
+
+        // drawn hydrogens are made implicit, the other atoms keep their indexes
+        let mut explicit = parse_smiles("OCC(=O)O").unwrap();
+        explicit.add_explicit_hydrogens();
+        let mut matches = find_query_matches(&explicit, &hydroxyl().query, true);
+        matches.sort();
+        assert_eq!(matches, vec![vec![Some(1), Some(0)], vec![Some(2), Some(4)]]);
+
+        // a drawn "R" is the carbon or one of the two hydrogens of methylamine
+        let mut amine = Molecule::new(vec!["N", "R"]);
+        amine.add_bond(0, 1, 1);
+        let methylamine = parse_smiles("CN").unwrap();
+        let matches = find_matches(&methylamine, &amine, false);
+        assert_eq!(matches.len(), 3);
+        assert!(matches.contains(&vec![Some(1), Some(0)]));
+        assert!(matches.contains(&vec![Some(1), None]));
+        assert_eq!(find_matches(&methylamine, &amine, true).len(), 1);
+        let mut explicit = parse_smiles("CN").unwrap();
+        explicit.add_explicit_hydrogens();
+        let matches = find_matches(&explicit, &amine, false);
+        assert!(matches.iter().all(|x| x.iter().all(|y| y.is_some())));
+        assert_eq!(matches.len(), 3);
+    }
+    #[test]
+    fn test_smarts_groups() {
+        // what the hand built groups couldn't say
+        let halide = parse_smarts("[#6]-[F,Cl,Br,I]").unwrap();
+        for smiles in ["CF", "CCl", "CBr", "CI"].iter() {
+            assert!(has_query_match(&parse_smiles(smiles).unwrap(), &halide), "{}", smiles);
+        }
+        // a carbonyl that isn't part of an acid or ester
+        let ketone = parse_smarts("[#6][CX3;!$(C-[#8])](=O)[#6]").unwrap();
+        assert!(has_query_match(&parse_smiles("CC(C)=O").unwrap(), &ketone));
+        assert!(!has_query_match(&parse_smiles("CC(=O)OC").unwrap(), &ketone));
+        // an alcohol on a ring carbon, and a hydroxyl on an aromatic ring
+        let ring_alcohol = parse_smarts("[OH]-[C;R]").unwrap();
+        assert!(has_query_match(&parse_smiles("OC1CCCCC1").unwrap(), &ring_alcohol));
+        assert!(!has_query_match(&parse_smiles("OCC1CCCCC1").unwrap(), &ring_alcohol));
+        let phenol = parse_smarts("[OH]-a").unwrap();
+        assert!(has_query_match(&parse_smiles("Oc1ccccc1").unwrap(), &phenol));
+        assert!(!has_query_match(&parse_smiles("OC1CCCCC1").unwrap(), &phenol));
+    }
+    #[test]
+    fn test_branched_and_ring_patterns() {
+        // the old search stopped at the first pattern atom it came back to, and never freed the
+        // atoms of a branch that didn't work out
+        // a ring pattern needs a ring, not a chain that comes back to the same element
+        let mut cyclopropane = Molecule::new(vec!["C", "C", "C"]);
+        cyclopropane.add_bond(0, 1, 1);
+        cyclopropane.add_bond(1, 2, 1);
+        cyclopropane.add_bond(2, 0, 1);
+        assert!(!is_subgraph(&parse_smiles("CCCC").unwrap(), &cyclopropane));
+        assert!(is_subgraph(&parse_smiles("CC1CC1").unwrap(), &cyclopropane));
+        // two branches can't use the same atom
+        let mut branched = Molecule::new(vec!["C", "O", "O"]);
+        branched.add_bond(0, 1, 1);
+        branched.add_bond(0, 2, 1);
+        assert!(!is_subgraph(&parse_smiles("CO").unwrap(), &branched));
+        assert!(is_subgraph(&parse_smiles("OCO").unwrap(), &branched));
+        // the first oxygen tried has the wrong neighbour, the search has to back up to the other
+        let mut chain = Molecule::new(vec!["C", "O", "N"]);
+        chain.add_bond(0, 1, 1);
+        chain.add_bond(1, 2, 1);
+        assert!(is_subgraph(&parse_smiles("OC(O)ON").unwrap(), &chain));
     }
 }
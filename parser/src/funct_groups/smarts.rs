@@ -0,0 +1,834 @@
+/*
+SMARTS patterns, read into a query graph of atom and bond expressions that the VF2 matcher
+searches for
+
+An atom is either written without brackets from the organic subset (C, c, Cl, ...), "*" for any
+atom, "a" for any aromatic atom and "A" for any aliphatic atom, or as a bracket expression like
+[C,N;!H0]. Inside brackets primitives are joined by "!" (not), "&" or nothing (and), "," (or) and
+";" (and again, but looser than ","), from the tightest to the loosest. The primitives are
+
+    C, c, Cl, se   an aliphatic or aromatic element     #n   atomic number n, aromatic or not
+    Dn   n bonded atoms                Xn   n connections, counting hydrogens
+    Hn   n hydrogens                   hn   n implicit hydrogens
+    vn   n bond orders, counting hydrogens
+    Rn   in n rings, R alone is in any ring, R0 in none
+    rn   in a ring of n atoms          xn   n ring bonds
+    +n, -n   charge                    n    isotope, when it comes first
+    $(...)   the first atom of the recursive SMARTS can be matched on this atom
+
+Two letter element symbols are read first, so [Cr] is chromium, a ring size is [C;r5]. [H] is a
+hydrogen atom, but [CH] is a carbon with one hydrogen.
+
+Bonds are "-", "=", "#" and ":" for single, double, triple and aromatic, "~" for any bond and "@"
+for a ring bond, joined by the same operators. A bond that isn't written is single or aromatic.
+Chirality and the "/" and "\" directions are read but not checked.
+
+Queries are matched against a SmartsTarget, which has its hydrogens made implicit where they can
+be and its aromatic rings aromatized, so H counts hydrogens whether or not they were drawn.
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use crate::molecule::molecule::Molecule;
+use crate::molecule::element::Element;
+use crate::molecule::error::SmartsError;
+use crate::molecule::rings::RingInfo;
+use super::vf2::{self, PatternGraph};
+
+// The bond type used for aromatic bonds, as in the .mol bond block
+static AROMATIC_BOND : i32 = 4;
+
+// Atoms that can be written without brackets
+static ORGANIC_SUBSET : [&str; 10] = ["Cl", "Br", "B", "C", "N", "O", "P", "S", "F", "I"];
+static AROMATIC_ORGANIC_SUBSET : [&str; 6] = ["b", "c", "n", "o", "p", "s"];
+// Aromatic atoms that are only allowed inside brackets
+static AROMATIC_BRACKET_ONLY : [&str; 3] = ["se", "as", "te"];
+// The characters a bond expression between two atoms is made of
+static BOND_CHARACTERS : &str = "-=#:~@/\\!&,;";
+
+// An atom or bond expression, primitives joined by not, and, or
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression<T> {
+    Primitive(T),
+    Not(Box<Expression<T>>),
+    And(Vec<Expression<T>>),
+    Or(Vec<Expression<T>>),
+}
+
+impl<T> Expression<T> {
+    // Whether the expression holds when each primitive is checked by f
+    pub fn evaluate<F : Fn(&T) -> bool>(&self, f : &F) -> bool {
+        match self {
+            Expression::Primitive(x) => f(x),
+            Expression::Not(x) => !x.evaluate(f),
+            Expression::And(x) => x.iter().all(|y| y.evaluate(f)),
+            Expression::Or(x) => x.iter().any(|y| y.evaluate(f)),
+        }
+    }
+}
+
+// The things an atom expression can check, see the top of the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtomPrimitive {
+    Any,
+    Aromatic,
+    Aliphatic,
+    // #n, the element whether or not it is aromatic
+    Element(Element),
+    AliphaticElement(Element),
+    AromaticElement(Element),
+    Isotope(i32),
+    Charge(i32),
+    Hydrogens(i32),
+    ImplicitHydrogens(i32),
+    Degree(usize),
+    Connectivity(i32),
+    Valence(i32),
+    // None is in any ring
+    RingCount(Option<usize>),
+    RingSize(Option<usize>),
+    RingConnectivity(Option<usize>),
+    Recursive(Box<SmartsQuery>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BondPrimitive {
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+    Any,
+    Ring,
+}
+
+pub type AtomExpression = Expression<AtomPrimitive>;
+pub type BondExpression = Expression<BondPrimitive>;
+
+// A parsed SMARTS pattern, an expression for every atom and every bond
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartsQuery {
+    pub atoms : Vec<AtomExpression>,
+    pub bonds : Vec<(usize, usize, BondExpression)>,
+}
+
+impl PatternGraph for SmartsQuery {
+    fn atom_count(&self) -> usize {
+        self.atoms.len()
+    }
+
+    fn bonded_atoms(&self, atom : usize) -> Vec<usize> {
+        self.bonds.iter().filter_map(|(x, y, _)| {
+            if *x == atom {
+                Some(*y)
+            } else if *y == atom {
+                Some(*x)
+            } else {
+                None
+            }
+        }).collect()
+    }
+}
+
+impl SmartsQuery {
+    // The expression of the bond between two query atoms
+    fn bond(&self, p : usize, q : usize) -> Option<&BondExpression> {
+        self.bonds.iter().find(|(x, y, _)| (*x == p && *y == q) || (*x == q && *y == p)).map(|x| &x.2)
+    }
+
+    // Whether the query atom matches the target atom, on its own
+    pub fn atom_matches(&self, atom : usize, target : &SmartsTarget, target_atom : usize) -> bool {
+        target.atom_matches(&self.atoms[atom], target_atom)
+    }
+
+    // Whether the query bond between p and q matches the target bond between x and y
+    pub fn bond_matches(&self, p : usize, q : usize, target : &SmartsTarget, x : usize, y : usize) -> bool {
+        match self.bond(p, q) {
+            Some(expression) => target.bond_matches(expression, x, y),
+            None => false,
+        }
+    }
+
+    // Calls on_match with every mapping of the query onto the target molecule, the target atom of
+    // each query atom. on_match returns true to stop the search
+    pub fn for_each_match<F : FnMut(&[usize]) -> bool>(&self, target : &SmartsTarget, on_match : F) {
+        vf2::for_each_match(&target.molecule, self,
+            |p, t| self.atom_matches(p, target, t),
+            |p, q, t, u| self.bond_matches(p, q, target, t, u),
+            on_match);
+    }
+
+    // Whether the query is found anywhere in the target
+    pub fn has_match(&self, target : &SmartsTarget) -> bool {
+        let mut found = false;
+        self.for_each_match(target, |_| {
+            found = true;
+            true
+        });
+        found
+    }
+
+    // Whether the query is found with its first atom on the target atom, for recursive SMARTS
+    fn has_match_at(&self, target : &SmartsTarget, atom : usize) -> bool {
+        vf2::has_match(&target.molecule, self,
+            |p, t| (p != 0 || t == atom) && self.atom_matches(p, target, t),
+            |p, q, t, u| self.bond_matches(p, q, target, t, u))
+    }
+}
+
+// A molecule ready to be searched with SMARTS queries, the hydrogens are made implicit where
+// they can be and the aromatic rings are aromatized, and what the primitives look at is worked
+// out once
+pub struct SmartsTarget {
+    pub molecule : Molecule,
+    // the index of each atom in the molecule the target was made from
+    pub original : Vec<usize>,
+    rings : RingInfo,
+    ring_counts : Vec<usize>,
+    ring_bonds : HashSet<(usize, usize)>,
+    aromatic : Vec<bool>,
+    hydrogens : Vec<i32>,
+    implicit_hydrogens : Vec<i32>,
+}
+
+impl SmartsTarget {
+    pub fn new(molecule : &Molecule) -> SmartsTarget {
+        let mut prepared = molecule.clone();
+        let original = prepared.remove_explicit_hydrogens();
+        prepared.aromatize();
+
+        let rings = prepared.rings();
+        let mut ring_bonds = HashSet::new();
+        for ring in &rings.rings {
+            for i in 0..ring.len() {
+                let (x, y) = (ring[i], ring[(i + 1) % ring.len()]);
+                ring_bonds.insert((x.min(y), x.max(y)));
+            }
+        }
+        let atoms = 0..prepared.atoms.len();
+        SmartsTarget {
+            ring_counts : rings.atom_ring_counts(),
+            rings,
+            ring_bonds,
+            aromatic : atoms.clone().map(|x| prepared.neighbours(x).any(|(_, bond)| bond == AROMATIC_BOND)).collect(),
+            hydrogens : atoms.clone().map(|x| prepared.total_hydrogens(x)).collect(),
+            implicit_hydrogens : atoms.map(|x| prepared.implicit_hydrogens(x)).collect(),
+            molecule : prepared,
+            original,
+        }
+    }
+
+    fn is_ring_bond(&self, x : usize, y : usize) -> bool {
+        self.ring_bonds.contains(&(x.min(y), x.max(y)))
+    }
+
+    fn atom_matches(&self, expression : &AtomExpression, atom : usize) -> bool {
+        let molecule = &self.molecule;
+        expression.evaluate(&|primitive : &AtomPrimitive| match primitive {
+            AtomPrimitive::Any => true,
+            AtomPrimitive::Aromatic => self.aromatic[atom],
+            AtomPrimitive::Aliphatic => !self.aromatic[atom],
+            AtomPrimitive::Element(x) => molecule.atoms[atom].is_element(*x),
+            AtomPrimitive::AliphaticElement(x) => molecule.atoms[atom].is_element(*x) && !self.aromatic[atom],
+            AtomPrimitive::AromaticElement(x) => molecule.atoms[atom].is_element(*x) && self.aromatic[atom],
+            AtomPrimitive::Isotope(x) => molecule.isotopes[atom] == *x,
+            AtomPrimitive::Charge(x) => molecule.charges[atom] == *x,
+            AtomPrimitive::Hydrogens(x) => self.hydrogens[atom] == *x,
+            AtomPrimitive::ImplicitHydrogens(x) => self.implicit_hydrogens[atom] == *x,
+            AtomPrimitive::Degree(x) => molecule.degree(atom) == *x,
+            AtomPrimitive::Connectivity(x) => molecule.degree(atom) as i32 + self.implicit_hydrogens[atom] == *x,
+            AtomPrimitive::Valence(x) => {
+                let bonds : f64 = molecule.neighbours(atom).map(|(y, _)| molecule.bond_order(atom, y)).sum();
+                (bonds + self.implicit_hydrogens[atom] as f64).round() as i32 == *x
+            },
+            AtomPrimitive::RingCount(None) | AtomPrimitive::RingSize(None) => self.ring_counts[atom] > 0,
+            AtomPrimitive::RingCount(Some(x)) => self.ring_counts[atom] == *x,
+            AtomPrimitive::RingSize(Some(0)) => self.ring_counts[atom] == 0,
+            AtomPrimitive::RingSize(Some(x)) => self.rings.atom_ring_sizes(atom).contains(x),
+            AtomPrimitive::RingConnectivity(x) => {
+                let count = molecule.neighbours(atom).filter(|(y, _)| self.is_ring_bond(atom, *y)).count();
+                match x {
+                    Some(x) => count == *x,
+                    None => count > 0,
+                }
+            },
+            AtomPrimitive::Recursive(query) => query.has_match_at(self, atom),
+        })
+    }
+
+    fn bond_matches(&self, expression : &BondExpression, x : usize, y : usize) -> bool {
+        let bond = self.molecule.get(x, y);
+        expression.evaluate(&|primitive : &BondPrimitive| match primitive {
+            BondPrimitive::Single => bond == 1,
+            BondPrimitive::Double => bond == 2,
+            BondPrimitive::Triple => bond == 3,
+            BondPrimitive::Aromatic => bond == AROMATIC_BOND,
+            BondPrimitive::Any => true,
+            BondPrimitive::Ring => self.is_ring_bond(x, y),
+        })
+    }
+}
+
+// Turns an aromatic symbol into the element symbol, "se" -> "Se"
+fn capitalize(symbol : &str) -> String {
+    let mut chars = symbol.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+// Reads a run of digits starting at i, stopping at end, returns the number and the index after it
+fn read_number(chars : &[char], mut i : usize, end : usize) -> (Option<u32>, usize) {
+    let start = i;
+    while i < end && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i == start {
+        return (None, i)
+    }
+    let number : String = chars[start..i].iter().collect();
+    (number.parse().ok(), i)
+}
+
+// The index of the close character that matches the open character at start, None if it isn't
+// closed before end
+fn find_close(chars : &[char], start : usize, end : usize, open : char, close : char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().take(end).skip(start) {
+        if *c == open {
+            depth += 1;
+        } else if *c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i)
+            }
+        }
+    }
+    None
+}
+
+// Reads an expression from start to end, read_primitive reads the primitive at an index and
+// returns it with the index after it
+// "!" binds tightest, then "&" (or two primitives written next to each other), then ",", then ";"
+fn read_expression<T, F>(start : usize, end : usize, chars : &[char], mut read_primitive : F) -> Result<Expression<T>, SmartsError>
+where F : FnMut(usize) -> Result<(T, usize), SmartsError> {
+    // one expression out of several, or the expression itself if there is one
+    fn join<T>(mut parts : Vec<Expression<T>>, f : fn(Vec<Expression<T>>) -> Expression<T>) -> Expression<T> {
+        if parts.len() == 1 { parts.pop().unwrap() } else { f(parts) }
+    }
+
+    // the ";" parts, the "," parts of the current ";" part, and the "&" parts of the current "," part
+    let mut low : Vec<Expression<T>> = Vec::new();
+    let mut or : Vec<Expression<T>> = Vec::new();
+    let mut and : Vec<Expression<T>> = Vec::new();
+    // Some(negated) once a "!" has been read, until its primitive is
+    let mut not : Option<bool> = None;
+    // true at the start and after an operator, until a primitive is read
+    let mut needs_primitive = true;
+
+    let mut i = start;
+    while i < end {
+        match chars[i] {
+            op @ ('&' | ',' | ';') => {
+                if needs_primitive || not.is_some() {
+                    return Err(SmartsError::BadExpression { position : i })
+                }
+                if op != '&' {
+                    or.push(join(std::mem::take(&mut and), Expression::And));
+                }
+                if op == ';' {
+                    low.push(join(std::mem::take(&mut or), Expression::Or));
+                }
+                needs_primitive = true;
+                i += 1;
+            },
+            '!' => {
+                not = Some(!not.unwrap_or(false));
+                i += 1;
+            },
+            _ => {
+                let (primitive, next) = read_primitive(i)?;
+                let mut expression = Expression::Primitive(primitive);
+                if not.take() == Some(true) {
+                    expression = Expression::Not(Box::new(expression));
+                }
+                and.push(expression);
+                needs_primitive = false;
+                i = next;
+            },
+        }
+    }
+    if needs_primitive || not.is_some() {
+        return Err(SmartsError::BadExpression { position : end })
+    }
+    or.push(join(and, Expression::And));
+    low.push(join(or, Expression::Or));
+    Ok(join(low, Expression::And))
+}
+
+// Reads the primitive of a bracket atom at i, atom_read is set once an element or any atom
+// primitive has been read, after that "H" is a hydrogen count
+fn read_atom_primitive(chars : &[char], i : usize, end : usize, atom_read : &mut bool) -> Result<(AtomPrimitive, usize), SmartsError> {
+    let c = chars[i];
+    let at = |x : usize| if x < end { Some(chars[x]) } else { None };
+    let unknown = || SmartsError::UnknownPrimitive { position : i, character : c };
+
+    // two letter symbols have to be tried first so "Cl" isn't read as "C"
+    if let Some(next) = at(i + 1) {
+        let pair : String = [c, next].iter().collect();
+        let element = if c.is_ascii_uppercase() && next.is_ascii_lowercase() {
+            Element::from_symbol(&pair).map(AtomPrimitive::AliphaticElement)
+        } else if AROMATIC_BRACKET_ONLY.contains(&pair.as_str()) {
+            Element::from_symbol(&capitalize(&pair)).map(AtomPrimitive::AromaticElement)
+        } else {
+            None
+        };
+        if let Some(element) = element {
+            *atom_read = true;
+            return Ok((element, i + 2))
+        }
+    }
+    if AROMATIC_ORGANIC_SUBSET.contains(&c.to_string().as_str()) {
+        *atom_read = true;
+        let element = Element::from_symbol(&capitalize(&c.to_string())).ok_or_else(unknown)?;
+        return Ok((AtomPrimitive::AromaticElement(element), i + 1))
+    }
+
+    let (number, next) = read_number(chars, i + 1, end);
+    if matches!(c, '*' | 'a' | 'A' | '#' | '$') || (c.is_ascii_uppercase() && !matches!(c, 'H' | 'D' | 'X' | 'R')) {
+        *atom_read = true;
+    }
+    let primitive = match c {
+        '*' => (AtomPrimitive::Any, i + 1),
+        'a' => (AtomPrimitive::Aromatic, i + 1),
+        'A' => (AtomPrimitive::Aliphatic, i + 1),
+        '#' => {
+            let element = number.and_then(Element::from_atomic_number).ok_or_else(|| SmartsError::UnknownElement {
+                position : i,
+                symbol : chars[i..next].iter().collect(),
+            })?;
+            (AtomPrimitive::Element(element), next)
+        },
+        'H' if !*atom_read && matches!(at(i + 1), None | Some('+') | Some('-')) => {
+            *atom_read = true;
+            (AtomPrimitive::AliphaticElement(Element::H), i + 1)
+        },
+        'H' => (AtomPrimitive::Hydrogens(number.unwrap_or(1) as i32), next),
+        'h' => (AtomPrimitive::ImplicitHydrogens(number.unwrap_or(1) as i32), next),
+        'D' => (AtomPrimitive::Degree(number.unwrap_or(1) as usize), next),
+        'X' => (AtomPrimitive::Connectivity(number.unwrap_or(1) as i32), next),
+        'v' => (AtomPrimitive::Valence(number.unwrap_or(1) as i32), next),
+        'R' => (AtomPrimitive::RingCount(number.map(|x| x as usize)), next),
+        'r' => (AtomPrimitive::RingSize(number.map(|x| x as usize)), next),
+        'x' => (AtomPrimitive::RingConnectivity(number.map(|x| x as usize)), next),
+        '+' | '-' => {
+            let unit = if c == '+' { 1 } else { -1 };
+            match number {
+                Some(number) => (AtomPrimitive::Charge(unit * number as i32), next),
+                None => {
+                    // the older ++ style
+                    let mut j = i + 1;
+                    while at(j) == Some(c) {
+                        j += 1;
+                    }
+                    (AtomPrimitive::Charge(unit * (j - i) as i32), j)
+                },
+            }
+        },
+        '0'..='9' => {
+            let (isotope, next) = read_number(chars, i, end);
+            (AtomPrimitive::Isotope(isotope.ok_or_else(unknown)? as i32), next)
+        },
+        // chirality isn't checked
+        '@' => {
+            let mut j = i + 1;
+            while at(j) == Some('@') {
+                j += 1;
+            }
+            (AtomPrimitive::Any, j)
+        },
+        '$' => {
+            if at(i + 1) != Some('(') {
+                return Err(SmartsError::BadExpression { position : i })
+            }
+            let close = find_close(chars, i + 1, end, '(', ')').ok_or(SmartsError::BadExpression { position : i })?;
+            if close == i + 2 {
+                return Err(SmartsError::BadExpression { position : i })
+            }
+            (AtomPrimitive::Recursive(Box::new(parse_range(chars, i + 2, close)?)), close + 1)
+        },
+        _ if c.is_ascii_uppercase() => {
+            let element = Element::from_symbol(&c.to_string()).ok_or_else(unknown)?;
+            (AtomPrimitive::AliphaticElement(element), i + 1)
+        },
+        _ => return Err(unknown()),
+    };
+    Ok(primitive)
+}
+
+fn read_bond_primitive(chars : &[char], i : usize) -> Result<(BondPrimitive, usize), SmartsError> {
+    let primitive = match chars[i] {
+        // directions aren't checked, they are single bonds
+        '-' | '/' | '\\' => BondPrimitive::Single,
+        '=' => BondPrimitive::Double,
+        '#' => BondPrimitive::Triple,
+        ':' => BondPrimitive::Aromatic,
+        '~' => BondPrimitive::Any,
+        '@' => BondPrimitive::Ring,
+        c => return Err(SmartsError::UnknownPrimitive { position : i, character : c }),
+    };
+    Ok((primitive, i + 1))
+}
+
+// Reads an atom written without brackets at i, returns the expression and the index after it
+fn read_organic_atom(chars : &[char], i : usize, end : usize) -> Option<(AtomExpression, usize)> {
+    let primitive = match chars[i] {
+        '*' => Some(AtomPrimitive::Any),
+        'a' => Some(AtomPrimitive::Aromatic),
+        'A' => Some(AtomPrimitive::Aliphatic),
+        _ => None,
+    };
+    if let Some(primitive) = primitive {
+        return Some((Expression::Primitive(primitive), i + 1))
+    }
+    // two letter symbols have to be tried first so "Cl" isn't read as "C"
+    if i + 1 < end {
+        let pair : String = chars[i..i + 2].iter().collect();
+        if ORGANIC_SUBSET.contains(&pair.as_str()) {
+            let element = Element::from_symbol(&pair)?;
+            return Some((Expression::Primitive(AtomPrimitive::AliphaticElement(element)), i + 2))
+        }
+    }
+    let single = chars[i].to_string();
+    if ORGANIC_SUBSET.contains(&single.as_str()) {
+        let element = Element::from_symbol(&single)?;
+        return Some((Expression::Primitive(AtomPrimitive::AliphaticElement(element)), i + 1))
+    }
+    if AROMATIC_ORGANIC_SUBSET.contains(&single.as_str()) {
+        let element = Element::from_symbol(&capitalize(&single))?;
+        return Some((Expression::Primitive(AtomPrimitive::AromaticElement(element)), i + 1))
+    }
+    None
+}
+
+// A bond read from the SMARTS string, the expression is None when it was left out
+struct SmartsBond {
+    from : usize,
+    to : usize,
+    expression : Option<BondExpression>,
+    position : usize,
+}
+
+// Parses a SMARTS string into a query
+pub fn parse_smarts(smarts : &str) -> Result<SmartsQuery, SmartsError> {
+    let chars : Vec<char> = smarts.trim().chars().collect();
+    parse_range(&chars, 0, chars.len())
+}
+
+// Parses the SMARTS from start to end, recursive SMARTS are parsed from inside their "$( )"
+fn parse_range(chars : &[char], start : usize, end : usize) -> Result<SmartsQuery, SmartsError> {
+    let mut atoms : Vec<AtomExpression> = Vec::new();
+    let mut bonds : Vec<SmartsBond> = Vec::new();
+
+    // the atom the next atom is bonded to
+    let mut prev : Option<usize> = None;
+    // the bond expression waiting for the next atom or ring closure
+    let mut pending : Option<(BondExpression, usize)> = None;
+    // the atoms the open branches start from, and where the branch was opened
+    let mut branches : Vec<(usize, usize)> = Vec::new();
+    // the open ring closures, ring number -> (atom, bond expression, position)
+    let mut rings : HashMap<u32, (usize, Option<BondExpression>, usize)> = HashMap::new();
+
+    let mut i = start;
+    while i < end {
+        let c = chars[i];
+        let position = i;
+
+        // atoms
+        let atom = if c == '[' {
+            let close = find_close(chars, i, end, '[', ']').ok_or(SmartsError::BadExpression { position })?;
+            let mut atom_read = false;
+            let expression = read_expression(i + 1, close, chars, |x| read_atom_primitive(chars, x, close, &mut atom_read))?;
+            Some((expression, close + 1))
+        } else {
+            read_organic_atom(chars, i, end)
+        };
+        if let Some((atom, next)) = atom {
+            let index = atoms.len();
+            atoms.push(atom);
+            if let Some(from) = prev {
+                // errors in the bond point at the bond expression if there is one
+                let (expression, position) = match pending.take() {
+                    Some((expression, position)) => (Some(expression), position),
+                    None => (None, position),
+                };
+                bonds.push(SmartsBond { from, to : index, expression, position });
+            } else if let Some((_, position)) = pending {
+                return Err(SmartsError::MissingAtom { position })
+            }
+            prev = Some(index);
+            i = next;
+            continue
+        }
+
+        // bonds, everything up to the next atom or ring closure
+        if BOND_CHARACTERS.contains(c) {
+            if prev.is_none() || pending.is_some() {
+                return Err(SmartsError::MissingAtom { position })
+            }
+            let mut next = i;
+            while next < end && BOND_CHARACTERS.contains(chars[next]) {
+                next += 1;
+            }
+            pending = Some((read_expression(i, next, chars, |x| read_bond_primitive(chars, x))?, position));
+            i = next;
+            continue
+        }
+
+        match c {
+            '(' => {
+                let from = prev.ok_or(SmartsError::MissingAtom { position })?;
+                if pending.is_some() {
+                    return Err(SmartsError::UnbalancedBranch { position })
+                }
+                branches.push((from, position));
+            },
+            ')' => {
+                let (from, _) = branches.pop().ok_or(SmartsError::UnbalancedBranch { position })?;
+                if let Some((_, position)) = pending {
+                    return Err(SmartsError::MissingAtom { position })
+                }
+                if chars[i - 1] == '(' {
+                    return Err(SmartsError::UnbalancedBranch { position })
+                }
+                prev = Some(from);
+            },
+            '.' => {
+                if let Some((_, position)) = pending {
+                    return Err(SmartsError::MissingAtom { position })
+                }
+                prev = None;
+            },
+            '0'..='9' | '%' => {
+                let (ring, next) = if c == '%' {
+                    // %nn is always two digits
+                    let (ring, _) = read_number(chars, i + 1, end.min(i + 3));
+                    match ring {
+                        Some(ring) if ring >= 10 => (ring, i + 3),
+                        _ => return Err(SmartsError::UnexpectedCharacter { position, character : c }),
+                    }
+                } else {
+                    (c.to_digit(10).unwrap(), i + 1)
+                };
+                let atom = prev.ok_or(SmartsError::MissingAtom { position })?;
+                let expression = pending.take().map(|x| x.0);
+                match rings.remove(&ring) {
+                    // the bond can be written at either end
+                    Some((open_atom, open_expression, _)) =>
+                        bonds.push(SmartsBond { from : open_atom, to : atom, expression : expression.or(open_expression), position }),
+                    None => {
+                        rings.insert(ring, (atom, expression, position));
+                    },
+                }
+                i = next;
+                continue
+            },
+            _ => return Err(SmartsError::UnexpectedCharacter { position, character : c }),
+        }
+        i += 1;
+    }
+
+    if let Some((_, position)) = pending {
+        return Err(SmartsError::MissingAtom { position })
+    }
+    if let Some((_, position)) = branches.pop() {
+        return Err(SmartsError::UnbalancedBranch { position })
+    }
+    if let Some((ring, (_, _, position))) = rings.into_iter().min_by_key(|(_, x)| x.2) {
+        return Err(SmartsError::UnclosedRing { position, ring })
+    }
+
+    let mut query = SmartsQuery { atoms, bonds : Vec::new() };
+    for bond in bonds {
+        if bond.from == bond.to || query.bond(bond.from, bond.to).is_some() {
+            return Err(SmartsError::BadRingClosure { position : bond.position })
+        }
+        // a bond that isn't written is single or aromatic
+        let expression = bond.expression.unwrap_or_else(|| Expression::Or(vec![
+            Expression::Primitive(BondPrimitive::Single),
+            Expression::Primitive(BondPrimitive::Aromatic),
+        ]));
+        query.bonds.push((bond.from, bond.to, expression));
+    }
+    Ok(query)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::molecule::smiles::parse_smiles;
+
+    // the number of matches of the query, each set of atoms counted once
+    fn count(smiles : &str, smarts : &str) -> usize {
+        let target = SmartsTarget::new(&parse_smiles(smiles).unwrap());
+        let query = parse_smarts(smarts).unwrap();
+        let mut seen : HashSet<Vec<usize>> = HashSet::new();
+        query.for_each_match(&target, |mapping| {
+            let mut key = mapping.to_vec();
+            key.sort_unstable();
+            seen.insert(key);
+            false
+        });
+        seen.len()
+    }
+
+    fn primitive(x : AtomPrimitive) -> AtomExpression {
+        Expression::Primitive(x)
+    }
+
+    #[test]
+    fn test_parse_expressions() {
+        let query = parse_smarts("[C,N;!H0]").unwrap();
+        assert_eq!(query.atoms, vec![Expression::And(vec![
+            Expression::Or(vec![
+                primitive(AtomPrimitive::AliphaticElement(Element::C)),
+                primitive(AtomPrimitive::AliphaticElement(Element::N)),
+            ]),
+            Expression::Not(Box::new(primitive(AtomPrimitive::Hydrogens(0)))),
+        ])]);
+
+        // "&" binds tighter than ",", and a primitive next to another is an "&"
+        let query = parse_smarts("[cH1,n&X2]").unwrap();
+        assert_eq!(query.atoms, vec![Expression::Or(vec![
+            Expression::And(vec![
+                primitive(AtomPrimitive::AromaticElement(Element::C)),
+                primitive(AtomPrimitive::Hydrogens(1)),
+            ]),
+            Expression::And(vec![
+                primitive(AtomPrimitive::AromaticElement(Element::N)),
+                primitive(AtomPrimitive::Connectivity(2)),
+            ]),
+        ])]);
+
+        // two letter symbols, hydrogen atoms and hydrogen counts
+        assert_eq!(parse_smarts("[Cl]").unwrap().atoms, vec![primitive(AtomPrimitive::AliphaticElement(Element::Cl))]);
+        assert_eq!(parse_smarts("[H]").unwrap().atoms, vec![primitive(AtomPrimitive::AliphaticElement(Element::H))]);
+        assert_eq!(parse_smarts("[#6H3]").unwrap().atoms, vec![Expression::And(vec![
+            primitive(AtomPrimitive::Element(Element::C)),
+            primitive(AtomPrimitive::Hydrogens(3)),
+        ])]);
+        assert_eq!(parse_smarts("[13C-2]").unwrap().atoms, vec![Expression::And(vec![
+            primitive(AtomPrimitive::Isotope(13)),
+            primitive(AtomPrimitive::AliphaticElement(Element::C)),
+            primitive(AtomPrimitive::Charge(-2)),
+        ])]);
+        assert_eq!(parse_smarts("[se]").unwrap().atoms, vec![primitive(AtomPrimitive::AromaticElement(Element::Se))]);
+
+        // bonds, written and left out
+        let query = parse_smarts("C!@C=,#N").unwrap();
+        assert_eq!(query.bonds[0].2, Expression::Not(Box::new(Expression::Primitive(BondPrimitive::Ring))));
+        assert_eq!(query.bonds[1].2, Expression::Or(vec![
+            Expression::Primitive(BondPrimitive::Double),
+            Expression::Primitive(BondPrimitive::Triple),
+        ]));
+        let query = parse_smarts("c1ccccc1").unwrap();
+        assert_eq!(query.bonds.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_smarts("[C"), Err(SmartsError::BadExpression { position : 0 }));
+        assert_eq!(parse_smarts("[]"), Err(SmartsError::BadExpression { position : 1 }));
+        assert_eq!(parse_smarts("[C,]"), Err(SmartsError::BadExpression { position : 3 }));
+        assert_eq!(parse_smarts("[C;;N]"), Err(SmartsError::BadExpression { position : 3 }));
+        assert_eq!(parse_smarts("[C!]"), Err(SmartsError::BadExpression { position : 3 }));
+        assert_eq!(parse_smarts("[Cq]"), Err(SmartsError::UnknownPrimitive { position : 2, character : 'q' }));
+        assert_eq!(parse_smarts("[#200]"), Err(SmartsError::UnknownElement { position : 1, symbol : "#200".to_string() }));
+        assert_eq!(parse_smarts("[$()]"), Err(SmartsError::BadExpression { position : 1 }));
+        assert_eq!(parse_smarts("[$(C]"), Err(SmartsError::BadExpression { position : 1 }));
+        assert_eq!(parse_smarts("C-"), Err(SmartsError::MissingAtom { position : 1 }));
+        assert_eq!(parse_smarts("=C"), Err(SmartsError::MissingAtom { position : 0 }));
+        assert_eq!(parse_smarts("C(C"), Err(SmartsError::UnbalancedBranch { position : 1 }));
+        assert_eq!(parse_smarts("C1CC"), Err(SmartsError::UnclosedRing { position : 1, ring : 1 }));
+        assert_eq!(parse_smarts("C11"), Err(SmartsError::BadRingClosure { position : 2 }));
+        assert_eq!(parse_smarts("C?"), Err(SmartsError::UnexpectedCharacter { position : 1, character : '?' }));
+    }
+
+    #[test]
+    fn test_atom_primitives() {
+        // any halogen
+        assert_eq!(count("ClCC(Br)CF", "[F,Cl,Br,I]"), 3);
+        // aromatic and aliphatic, by element and on their own
+        assert_eq!(count("Cc1ccccc1", "c"), 6);
+        assert_eq!(count("Cc1ccccc1", "C"), 1);
+        assert_eq!(count("Cc1ccccc1", "[#6]"), 7);
+        assert_eq!(count("Cc1ccncc1", "a"), 6);
+        assert_eq!(count("Cc1ccncc1", "[A]"), 1);
+        // drawn and implicit hydrogens count the same
+        assert_eq!(count("CCO", "[CH3]"), 1);
+        assert_eq!(count("[H]C([H])([H])C([H])([H])O[H]", "[CH3]"), 1);
+        assert_eq!(count("CCO", "[OX2H]"), 1);
+        assert_eq!(count("CC(C)(C)C", "[CD4]"), 1);
+        assert_eq!(count("CC(C)(C)C", "[CX4]"), 5);
+        assert_eq!(count("CC=O", "[Cv4]"), 2);
+        assert_eq!(count("C[N+](C)(C)C.[O-]C", "[+]"), 1);
+        assert_eq!(count("C[N+](C)(C)C.[O-]C", "[O-1]"), 1);
+        assert_eq!(count("[13CH4].C", "[13C]"), 1);
+    }
+
+    #[test]
+    fn test_ring_primitives() {
+        // in a ring, in no ring, ring size and ring bonds on methylcyclohexane
+        assert_eq!(count("CC1CCCCC1", "[R]"), 6);
+        assert_eq!(count("CC1CCCCC1", "[R0]"), 1);
+        assert_eq!(count("CC1CCCCC1", "[r6]"), 6);
+        assert_eq!(count("CC1CCCCC1", "[r5]"), 0);
+        assert_eq!(count("CC1CCCCC1", "[x2]"), 6);
+        assert_eq!(count("CC1CCCCC1", "C@C"), 6);
+        assert_eq!(count("CC1CCCCC1", "C!@C"), 1);
+        // the fused atoms of naphthalene are in two rings and have three ring bonds
+        assert_eq!(count("c1ccc2ccccc2c1", "[R2]"), 2);
+        assert_eq!(count("c1ccc2ccccc2c1", "[x3]"), 2);
+    }
+
+    #[test]
+    fn test_bonds() {
+        // Kekulé benzene is aromatized, so its bonds are aromatic
+        assert_eq!(count("C1=CC=CC=C1", "c:c"), 6);
+        assert_eq!(count("C1=CC=CC=C1", "C=C"), 0);
+        assert_eq!(count("C=CC#N", "C~*"), 3);
+        assert_eq!(count("C=CC#N", "C-,=C"), 2);
+        assert_eq!(count("C=CC#N", "*#*"), 1);
+        // a bond left out is single or aromatic, so biphenyl's link is found both ways
+        assert_eq!(count("c1ccccc1-c1ccccc1", "cc"), 13);
+        assert_eq!(count("c1ccccc1-c1ccccc1", "c-c"), 1);
+    }
+
+    #[test]
+    fn test_recursive() {
+        // carbons not bonded to an oxygen
+        assert_eq!(count("CC(=O)OC", "[C;!$(C~O)]"), 1);
+        assert_eq!(count("CC(=O)OC", "[C;$(C~O)]"), 2);
+        // an oxygen of an acid, the recursive SMARTS starts from the atom it is in
+        assert_eq!(count("CC(=O)O.CCO", "[OH;$(OC=O)]"), 1);
+        // nested
+        assert_eq!(count("NCC(=O)O.NCCO", "[N;$(N[$(C=O)])]"), 0);
+        assert_eq!(count("NCC(=O)O.NCCO", "[N;$(N[$(CC=O)])]"), 1);
+    }
+
+    #[test]
+    fn test_matches_use_target_atoms() {
+        // the target keeps the index each atom had before its hydrogens were made implicit
+        let molecule = parse_smiles("[H]OC").unwrap();
+        let target = SmartsTarget::new(&molecule);
+        assert_eq!(target.original, vec![1, 2]);
+        let query = parse_smarts("[OH]C").unwrap();
+        let mut matches = Vec::new();
+        query.for_each_match(&target, |x| {
+            matches.push(x.iter().map(|y| target.original[*y]).collect::<Vec<usize>>());
+            false
+        });
+        assert_eq!(matches, vec![vec![1, 2]]);
+    }
+}
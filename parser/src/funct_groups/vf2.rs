@@ -0,0 +1,254 @@
+/*
+VF2 subgraph monomorphism, finds every way the atoms of a pattern can be mapped onto distinct atoms
+of a target so that every pattern bond is a target bond. The target can have more bonds between
+the mapped atoms than the pattern, the way a carbonyl pattern is found in a ring.
+
+The pattern atoms are matched one at a time, in breadth first order so each atom after the first
+of its fragment is next to an atom that is already mapped, and only the neighbours of that
+atom's image are tried. A candidate is kept when the atom and bond predicates agree and it has
+enough free neighbours left for the pattern atom's free neighbours, otherwise the search backs up.
+*/
+
+use crate::molecule::molecule::Molecule;
+
+// The query bond types from the .mol bond block, any other bond type only matches itself
+static SINGLE_OR_DOUBLE : i32 = 5;
+static SINGLE_OR_AROMATIC : i32 = 6;
+static DOUBLE_OR_AROMATIC : i32 = 7;
+static ANY_BOND : i32 = 8;
+
+// The default bond predicate, compares bond types with the .mol query bond types standing for
+// more than one bond type
+pub fn bond_matches(pattern_bond : i32, target_bond : i32) -> bool {
+    match pattern_bond {
+        x if x == SINGLE_OR_DOUBLE => target_bond == 1 || target_bond == 2,
+        x if x == SINGLE_OR_AROMATIC => target_bond == 1 || target_bond == 4,
+        x if x == DOUBLE_OR_AROMATIC => target_bond == 2 || target_bond == 4,
+        x if x == ANY_BOND => true,
+        x => x == target_bond,
+    }
+}
+
+// The atoms and bonds of a pattern, so a Molecule or a SMARTS query can be searched for
+pub trait PatternGraph {
+    // The number of atoms in the pattern
+    fn atom_count(&self) -> usize;
+    // The atoms bonded to an atom
+    fn bonded_atoms(&self, atom : usize) -> Vec<usize>;
+}
+
+impl PatternGraph for Molecule {
+    fn atom_count(&self) -> usize {
+        self.atoms.len()
+    }
+
+    fn bonded_atoms(&self, atom : usize) -> Vec<usize> {
+        self.neighbours(atom).map(|(x, _)| x).collect()
+    }
+}
+
+// The state of a search, the predicates get (pattern atom, target atom) and
+// (pattern atom, pattern atom, target atom, target atom) for a pattern bond and the target bond
+// its atoms are mapped onto
+struct Vf2<'a, A, B> {
+    target : &'a Molecule,
+    atom_match : A,
+    bond_match : B,
+    // the bonded atoms of each pattern atom
+    pattern_neighbours : Vec<Vec<usize>>,
+    // the pattern atoms in the order they are matched, with an earlier neighbour of each
+    order : Vec<(usize, Option<usize>)>,
+    // the target atom of each pattern atom, and the pattern atom of each target atom
+    core_pattern : Vec<Option<usize>>,
+    core_target : Vec<Option<usize>>,
+}
+
+impl<'a, A, B> Vf2<'a, A, B>
+where A : Fn(usize, usize) -> bool, B : Fn(usize, usize, usize, usize) -> bool {
+    fn new<P : PatternGraph + ?Sized>(target : &'a Molecule, pattern : &P, atom_match : A, bond_match : B) -> Self {
+        let pattern_neighbours : Vec<Vec<usize>> = (0..pattern.atom_count()).map(|x| pattern.bonded_atoms(x)).collect();
+        Vf2 {
+            target,
+            atom_match,
+            bond_match,
+            order : match_order(&pattern_neighbours),
+            core_pattern : vec![None; pattern_neighbours.len()],
+            core_target : vec![None; target.atoms.len()],
+            pattern_neighbours,
+        }
+    }
+
+    // Whether the pattern atom can be mapped to the target atom with the atoms mapped so far
+    fn feasible(&self, p : usize, t : usize) -> bool {
+        if self.core_target[t].is_some() || !(self.atom_match)(p, t) {
+            return false
+        }
+        let mut free_pattern = 0;
+        for q in &self.pattern_neighbours[p] {
+            match self.core_pattern[*q] {
+                Some(u) => {
+                    if self.target.get(t, u) == 0 || !(self.bond_match)(p, *q, t, u) {
+                        return false
+                    }
+                },
+                None => free_pattern += 1,
+            }
+        }
+        // the pattern atom's unmapped neighbours each need a different unmapped target neighbour
+        let free_target = self.target.neighbours(t).filter(|(u, _)| self.core_target[*u].is_none()).count();
+        free_pattern <= free_target
+    }
+
+    // Extends the mapping from the depth'th atom of the order, calls on_match with every full
+    // mapping. Returns true once on_match asks to stop
+    fn search(&mut self, depth : usize, on_match : &mut dyn FnMut(&[usize]) -> bool) -> bool {
+        if depth == self.order.len() {
+            let mapping : Vec<usize> = self.core_pattern.iter().map(|x| x.unwrap()).collect();
+            return on_match(&mapping)
+        }
+        let (p, parent) = self.order[depth];
+        let candidates : Vec<usize> = match parent {
+            Some(q) => self.target.neighbours(self.core_pattern[q].unwrap()).map(|(t, _)| t).collect(),
+            None => (0..self.target.atoms.len()).collect(),
+        };
+        for t in candidates {
+            if !self.feasible(p, t) {
+                continue
+            }
+            self.core_pattern[p] = Some(t);
+            self.core_target[t] = Some(p);
+            let stop = self.search(depth + 1, on_match);
+            self.core_pattern[p] = None;
+            self.core_target[t] = None;
+            if stop {
+                return true
+            }
+        }
+        false
+    }
+}
+
+// Orders the pattern atoms breadth first, starting each fragment from its most connected atom,
+// which has the fewest places to go in the target
+fn match_order(neighbours : &[Vec<usize>]) -> Vec<(usize, Option<usize>)> {
+    let mut order = Vec::new();
+    let mut seen = vec![false; neighbours.len()];
+    while let Some(start) = (0..neighbours.len()).filter(|x| !seen[*x]).max_by_key(|x| (neighbours[*x].len(), std::cmp::Reverse(*x))) {
+        seen[start] = true;
+        order.push((start, None));
+        let mut i = order.len() - 1;
+        while i < order.len() {
+            let atom = order[i].0;
+            let mut next : Vec<usize> = neighbours[atom].iter().copied().filter(|x| !seen[*x]).collect();
+            next.sort_by_key(|x| std::cmp::Reverse(neighbours[*x].len()));
+            for x in next {
+                seen[x] = true;
+                order.push((x, Some(atom)));
+            }
+            i += 1;
+        }
+    }
+    order
+}
+
+// Calls on_match with every mapping of the pattern onto the target, the target atom of each
+// pattern atom. on_match returns true to stop the search
+pub fn for_each_match<P, A, B, F>(target : &Molecule, pattern : &P, atom_match : A, bond_match : B, mut on_match : F)
+where P : PatternGraph + ?Sized, A : Fn(usize, usize) -> bool, B : Fn(usize, usize, usize, usize) -> bool, F : FnMut(&[usize]) -> bool {
+    let mut state = Vf2::new(target, pattern, atom_match, bond_match);
+    state.search(0, &mut on_match);
+}
+
+// Whether the pattern is a subgraph of the target
+pub fn has_match<P, A, B>(target : &Molecule, pattern : &P, atom_match : A, bond_match : B) -> bool
+where P : PatternGraph + ?Sized, A : Fn(usize, usize) -> bool, B : Fn(usize, usize, usize, usize) -> bool {
+    let mut found = false;
+    for_each_match(target, pattern, atom_match, bond_match, |_| {
+        found = true;
+        true
+    });
+    found
+}
+
+// The bond predicate for a Molecule pattern, compares the pattern and target bond types with
+// bond_matches
+pub fn molecule_bonds_match<'a>(target : &'a Molecule, pattern : &'a Molecule) -> impl Fn(usize, usize, usize, usize) -> bool + 'a {
+    move |p, q, t, u| bond_matches(pattern.get(p, q), target.get(t, u))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::molecule::smiles::parse_smiles;
+
+    fn elements_match<'a>(target : &'a Molecule, pattern : &'a Molecule) -> impl Fn(usize, usize) -> bool + 'a {
+        move |p, t| pattern.atoms[p].matches(&target.atoms[t])
+    }
+
+    fn all_matches<A : Fn(usize, usize) -> bool>(target : &Molecule, pattern : &Molecule, atom_match : A) -> Vec<Vec<usize>> {
+        let mut out = Vec::new();
+        for_each_match(target, pattern, atom_match, molecule_bonds_match(target, pattern), |x| {
+            out.push(x.to_vec());
+            false
+        });
+        out
+    }
+
+    fn count(target : &str, pattern : &str) -> usize {
+        let target = parse_smiles(target).unwrap();
+        let pattern = parse_smiles(pattern).unwrap();
+        all_matches(&target, &pattern, elements_match(&target, &pattern)).len()
+    }
+
+    #[test]
+    fn test_counts() {
+        // a chain of three carbons runs both ways through propane
+        assert_eq!(count("CCC", "CCC"), 2);
+        assert_eq!(count("CCC", "CC"), 4);
+        // the 12 ways around benzene
+        assert_eq!(count("c1ccccc1", "c1ccccc1"), 12);
+        // isobutane's centre has 3 neighbours, so 6 orders
+        assert_eq!(count("CC(C)C", "CC(C)C"), 6);
+        assert_eq!(count("CCCC", "CC(C)C"), 0);
+        assert_eq!(count("CC", "CCC"), 0);
+    }
+
+    #[test]
+    fn test_monomorphism() {
+        // the ring bond closing cyclohexane isn't in the hexane pattern, but it can still map
+        assert_eq!(count("C1CCCCC1", "CCCCCC"), 12);
+        // the pattern's ring needs the target's ring
+        assert_eq!(count("CCCCCC", "C1CCCCC1"), 0);
+        // every atom maps to a different atom, so two oxygens need two oxygens
+        assert_eq!(count("CO", "OCO"), 0);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let target = parse_smiles("C=CC#N").unwrap();
+        let mut pattern = Molecule::new(vec!["C", "C"]);
+        pattern.add_bond(0, 1, ANY_BOND);
+        assert_eq!(all_matches(&target, &pattern, elements_match(&target, &pattern)).len(), 4);
+        // the atom predicate can look at anything, here only charged atoms
+        let target = parse_smiles("C[N+](C)(C)C.CN").unwrap();
+        let pattern = parse_smiles("CN").unwrap();
+        let charged = |p : usize, t : usize| pattern.atoms[p].matches(&target.atoms[t])
+            && (!target.atoms[t].is_element(crate::molecule::element::Element::N) || target.charges[t] == 1);
+        assert_eq!(all_matches(&target, &pattern, charged).len(), 4);
+        // a single or double query bond
+        let mut pattern = Molecule::new(vec!["C", "C"]);
+        pattern.add_bond(0, 1, SINGLE_OR_DOUBLE);
+        let double = parse_smiles("C=C").unwrap();
+        assert!(has_match(&double, &pattern, |_, _| true, molecule_bonds_match(&double, &pattern)));
+        let triple = parse_smiles("C#C").unwrap();
+        assert!(!has_match(&triple, &pattern, |_, _| true, molecule_bonds_match(&triple, &pattern)));
+    }
+
+    #[test]
+    fn test_disconnected_pattern() {
+        // the two fragments of the pattern can't share atoms
+        assert_eq!(count("CC.O", "C.O"), 2);
+        assert_eq!(count("O", "O.O"), 0);
+    }
+}
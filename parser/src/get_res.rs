@@ -4,10 +4,18 @@ For examples on how to use the FunctGetRes check out the tests
 */
 
 
+use std::collections::HashMap;
+
 use super::funct_groups::recognizer;
+use super::funct_groups::recognizer::Mapping;
 use super::funct_groups::funct_groups::*;
+use super::funct_groups::smarts::SmartsTarget;
+use super::funct_groups::library::{load_library, parse_library, LibraryError, LibraryFormat};
+use super::funct_groups::hierarchy::{Hierarchy, HierarchyError, Resolved};
 use super::Molecule;
 use crate::molecule::parser::*;
+use crate::molecule::error::MolParseError;
+use crate::molecule::sdf::*;
 
 
 // Stores the result of a calculation. Includes the vec of functional groups tested on
@@ -21,7 +29,7 @@ impl std::fmt::Display for FunctGroupResult<'_> {
         let mut out = String::new();
         out += "[";
         for i in 0..self.functional_groups.len() {
-            out += self.functional_groups[i].name;
+            out += &self.functional_groups[i].name;
             out += " : ";
             out += &self.result[i].to_string(); // sorry
             out += ", ";
@@ -31,46 +39,131 @@ impl std::fmt::Display for FunctGroupResult<'_> {
     }
 }
 
+// Stores every match of every functional group, in the same order as the functional groups
+// Each match has the molecule atom of each atom of the group's SMARTS, see recognizer::Mapping
+pub struct FunctGroupMatches <'a> {
+    pub functional_groups : &'a Vec<FunctGroup>,
+    pub matches : Vec<Vec<Mapping>>,
+}
+// Display implementation for printing the counts
+impl std::fmt::Display for FunctGroupMatches<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let counts : Vec<String> = self.functional_groups.iter().zip(self.counts())
+            .map(|(group, count)| format!("{} : {}", group.name, count))
+            .collect();
+        writeln!(f, "[{}]", counts.join(", "))
+    }
+}
+impl FunctGroupMatches<'_> {
+    // The number of times each group was found
+    pub fn counts(&self) -> Vec<usize> {
+        self.matches.iter().map(|x| x.len()).collect()
+    }
+}
+
+// The result and data items of a single .sdf record, or the error from parsing it along with the
+// data items that could still be read
+pub type SdfRecordResult = Result<(Vec<bool>, HashMap<String, String>), SdfRecordError>;
+
 // stores data for the getter, so it doesnt have to calculate the functional groups every time.
 pub struct GetRes {
     pub functional_groups : Option<Vec<FunctGroup>>,
+    // the parents of the functional groups, built with them
+    hierarchy : Option<Hierarchy>,
 }
 
 impl<'a> GetRes { 
+    // Uses the built in functional groups
     pub fn new() -> GetRes {
-        GetRes { functional_groups : None}
+        GetRes { functional_groups : None, hierarchy : None }
+    }
+
+    // Uses the given functional groups instead of the built in ones
+    // Errors if their parents don't form a hierarchy
+    pub fn from_funct_groups(functional_groups : Vec<FunctGroup>) -> Result<GetRes, HierarchyError> {
+        let hierarchy = Hierarchy::new(&functional_groups)?;
+        Ok(GetRes { functional_groups : Some(functional_groups), hierarchy : Some(hierarchy) })
+    }
+
+    // Uses the functional groups of a .toml or .json library file, see library
+    pub fn from_file(library_file : &str) -> Result<GetRes, LibraryError> {
+        Ok(GetRes::from_funct_groups(load_library(library_file)?)?)
+    }
+
+    // Uses the functional groups of the contents of a library file
+    pub fn from_string(library : &str, format : LibraryFormat) -> Result<GetRes, LibraryError> {
+        Ok(GetRes::from_funct_groups(parse_library(library, format)?)?)
     }
 
     pub fn get_funct_groups(&mut self) -> Vec<String> {
         self.gen_funct_groups();
         let mut out : Vec<String> = Vec::new();
         for funct_group in self.functional_groups.as_ref().unwrap() {
-            out.push(String::from(funct_group.name));
+            out.push(funct_group.name.clone());
         }
         out
     }
 
+    // The SMARTS pattern of each functional group, in the same order as get_funct_groups, None
+    // for groups given as a list of atoms
+    pub fn get_funct_group_smarts(&mut self) -> Vec<Option<String>> {
+        self.gen_funct_groups();
+        self.functional_groups.as_ref().unwrap().iter().map(|x| x.smarts.clone()).collect()
+    }
+
+    // The category of each functional group, if it has one
+    pub fn get_funct_group_categories(&mut self) -> Vec<Option<String>> {
+        self.gen_funct_groups();
+        self.functional_groups.as_ref().unwrap().iter().map(|x| x.category.clone()).collect()
+    }
+
+    // The description of each functional group, if it has one
+    pub fn get_funct_group_descriptions(&mut self) -> Vec<Option<String>> {
+        self.gen_funct_groups();
+        self.functional_groups.as_ref().unwrap().iter().map(|x| x.description.clone()).collect()
+    }
+
     // pass a file get the result
-    pub fn get_res_from_file(&mut self, mol_file : &str) -> FunctGroupResult {
-        let mol : &Molecule = &parse_mol(mol_file);
-        self.get_res(mol)
+    pub fn get_res_from_file(&mut self, mol_file : &str) -> Result<FunctGroupResult<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol(mol_file)?;
+        Ok(self.get_res(mol))
+    }
+
+    // pass the contents of a .mol file get the result
+    pub fn get_res_from_string(&mut self, mol_contents : &str) -> Result<FunctGroupResult<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol_str(mol_contents)?;
+        Ok(self.get_res(mol))
+    }
+
+    // pass an .sdf file, get the result and the data items of every record in the file, in order
+    // records that fail to parse get their error in place of a result
+    pub fn get_res_from_sdf(&mut self, sdf_file : &str) -> Result<Vec<SdfRecordResult>, MolParseError> {
+        let mut out = Vec::new();
+        for record in parse_sdf(sdf_file)? {
+            out.push(record.map(|x| (self.get_res(&x.molecule).result, x.data)));
+        }
+        Ok(out)
     }
 
     // gets the result and prints the output
-    pub fn get_res_from_file_and_print(&mut self, mol_file : &str) -> FunctGroupResult {
-        let res = self.get_res_from_file(mol_file);
+    pub fn get_res_from_file_and_print(&mut self, mol_file : &str) -> Result<FunctGroupResult<'_>, MolParseError> {
+        let res = self.get_res_from_file(mol_file)?;
         println!("{}", res);
-        res
+        Ok(res)
     }
 
     // Pass a Molecule to get the result
-    pub fn get_res(&mut self, mol : &Molecule) -> FunctGroupResult {
+    pub fn get_res(&mut self, mol : &Molecule) -> FunctGroupResult<'_> {
         // generates the functional groups if necessary
         self.gen_funct_groups();
 
+        // The hydrogens and aromatic rings are normalized once, so a molecule gets the same
+        // result whether or not its hydrogens were drawn, and whichever Kekulé structure was drawn
+        let target = SmartsTarget::new(mol);
+
         let mut res : Vec<bool> = Vec::new();
         for group in self.functional_groups.as_ref().unwrap() {
-            res.push(recognizer::is_subgraph(mol, &group.molecule));
+            res.push(group.query.has_match(&target));
         }
 
         FunctGroupResult{
@@ -79,15 +172,108 @@ impl<'a> GetRes {
         }
     }
 
+    // Pass a Molecule to get every match of every group
+    // With unique, matches of the same atoms that only differ by the symmetry of the group are
+    // counted once
+    pub fn get_matches(&mut self, mol : &Molecule, unique : bool) -> FunctGroupMatches<'_> {
+        self.gen_funct_groups();
+
+        let target = SmartsTarget::new(mol);
+        let mut matches : Vec<Vec<Mapping>> = Vec::new();
+        for group in self.functional_groups.as_ref().unwrap() {
+            matches.push(recognizer::query_matches(&target, &group.query, unique));
+        }
+
+        FunctGroupMatches {
+            functional_groups : self.functional_groups.as_ref().unwrap(),
+            matches
+        }
+    }
+
+    // Pass a Molecule to get the result with only the most specific groups, a group is found
+    // when it has a match that wasn't consumed by a group below it, see hierarchy
+    pub fn get_resolved_res(&mut self, mol : &Molecule) -> FunctGroupResult<'_> {
+        let result = self.get_resolved_matches(mol, true).counts().iter().map(|x| *x > 0).collect();
+        FunctGroupResult {
+            functional_groups : self.functional_groups.as_ref().unwrap(),
+            result
+        }
+    }
+
+    // Pass a Molecule to get the matches left once the most specific groups have taken their atoms
+    pub fn get_resolved_matches(&mut self, mol : &Molecule, unique : bool) -> FunctGroupMatches<'_> {
+        let matches = self.resolve(mol, unique).matches;
+        FunctGroupMatches {
+            functional_groups : self.functional_groups.as_ref().unwrap(),
+            matches
+        }
+    }
+
+    // Pass a Molecule to get the index of the most specific group found on each of its atoms
+    pub fn get_atom_groups(&mut self, mol : &Molecule) -> Vec<Option<usize>> {
+        self.resolve(mol, true).atom_groups
+    }
+
+    // pass a file get the resolved result
+    pub fn get_resolved_res_from_file(&mut self, mol_file : &str) -> Result<FunctGroupResult<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol(mol_file)?;
+        Ok(self.get_resolved_res(mol))
+    }
+
+    // pass the contents of a .mol file get the resolved result
+    pub fn get_resolved_res_from_string(&mut self, mol_contents : &str) -> Result<FunctGroupResult<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol_str(mol_contents)?;
+        Ok(self.get_resolved_res(mol))
+    }
+
+    fn resolve(&mut self, mol : &Molecule, unique : bool) -> Resolved {
+        let matches = self.get_matches(mol, unique).matches;
+        self.hierarchy.as_ref().unwrap().resolve(self.functional_groups.as_ref().unwrap(), &matches, mol.atoms.len())
+    }
+
+    // pass a file get every match
+    pub fn get_matches_from_file(&mut self, mol_file : &str, unique : bool) -> Result<FunctGroupMatches<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol(mol_file)?;
+        Ok(self.get_matches(mol, unique))
+    }
+
+    // pass the contents of a .mol file get every match
+    pub fn get_matches_from_string(&mut self, mol_contents : &str, unique : bool) -> Result<FunctGroupMatches<'_>, MolParseError> {
+        let mol : &Molecule = &parse_mol_str(mol_contents)?;
+        Ok(self.get_matches(mol, unique))
+    }
+
     // Pass a .mol file and get a Vec<Vec(i32)>> representation of the adjacency graph
-    pub fn get_matrix(&self, file_path : &str) -> Vec<Vec<i32>> {
-        let mol : &Molecule = &parse_mol(file_path);
-        mol.get_matrix().to_vec()
+    pub fn get_matrix(&self, file_path : &str) -> Result<Vec<Vec<i32>>, MolParseError> {
+        let mol : &Molecule = &parse_mol(file_path)?;
+        Ok(mol.get_matrix())
+    }
+
+    // Pass the contents of a .mol file and get a Vec<Vec(i32)>> representation of the adjacency graph
+    pub fn get_matrix_from_string(&self, mol_contents : &str) -> Result<Vec<Vec<i32>>, MolParseError> {
+        let mol : &Molecule = &parse_mol_str(mol_contents)?;
+        Ok(mol.get_matrix())
+    }
+
+    // Pass a .mol file and get the x, y, z coordinates of every atom, in the same order as the
+    // rows of get_matrix
+    pub fn get_coordinates(&self, file_path : &str) -> Result<Vec<Vec<f32>>, MolParseError> {
+        let mol : &Molecule = &parse_mol(file_path)?;
+        Ok(mol.get_coordinates().iter().map(|x| x.to_vec()).collect())
+    }
+
+    // Pass the contents of a .mol file and get the x, y, z coordinates of every atom
+    pub fn get_coordinates_from_string(&self, mol_contents : &str) -> Result<Vec<Vec<f32>>, MolParseError> {
+        let mol : &Molecule = &parse_mol_str(mol_contents)?;
+        Ok(mol.get_coordinates().iter().map(|x| x.to_vec()).collect())
     }
 
     fn gen_funct_groups(&mut self) {
         if self.functional_groups.is_none() {
-            self.functional_groups = Some(get_funct_groups());
+            let functional_groups = get_funct_groups();
+            // the built in parents are checked by the hierarchy tests
+            self.hierarchy = Some(Hierarchy::new(&functional_groups).unwrap());
+            self.functional_groups = Some(functional_groups);
         }
     }
 }
@@ -103,7 +289,7 @@ mod tests {
     #[test]
     fn test_funct_get_res() {
         // Demonstration of FunctGetRes instatiation and result getting
-        let mol = &parser::parse_mol("src/molecule/test_files/Benzoic acid.mol");
+        let mol = &parser::parse_mol("src/molecule/test_files/Benzoic acid.mol").unwrap();
         let mut res_getter = GetRes::new();
         let res = res_getter.get_res(mol);
         println!("Benzoic acid : {}", res);
@@ -111,13 +297,13 @@ mod tests {
     #[test]
     fn test_get_res_from_file() {
         let mut res_getter = GetRes::new();
-        let res = res_getter.get_res_from_file("src/molecule/test_files/Methane.mol");
+        let res = res_getter.get_res_from_file("src/molecule/test_files/Methane.mol").unwrap();
         println!("Methane : {}", res);
     }
     #[test]
     fn test_get_matrix() {
         let res_getter = GetRes::new();
-        let res = res_getter.get_matrix("src/molecule/test_files/Methane.mol");
+        let res = res_getter.get_matrix("src/molecule/test_files/Methane.mol").unwrap();
         for x in 0..res.len() {
             for y in 0..res[x].len() {
                 print!("{}, ", res[x][y]);            
@@ -125,4 +311,128 @@ mod tests {
             println!("");
         }
     }
+    #[test]
+    fn test_get_res_from_string() {
+        let contents = std::fs::read_to_string("src/molecule/test_files/Benzoic acid.mol").unwrap();
+        let mut res_getter = GetRes::new();
+        let from_string = res_getter.get_res_from_string(&contents).unwrap().result;
+        let from_file = res_getter.get_res_from_file("src/molecule/test_files/Benzoic acid.mol").unwrap().result;
+        assert_eq!(from_string, from_file);
+        assert_eq!(res_getter.get_matrix_from_string(&contents).unwrap(),
+            res_getter.get_matrix("src/molecule/test_files/Benzoic acid.mol").unwrap());
+    }
+    #[test]
+    fn test_get_res_from_sdf() {
+        let mut res_getter = GetRes::new();
+        let res = res_getter.get_res_from_sdf("src/molecule/test_files/Methane, Benzoic acid, Pentanoic acid.sdf").unwrap();
+        assert_eq!(res.len(), 3);
+        let (benzoic_acid, data) = res[1].as_ref().unwrap();
+        assert_eq!(benzoic_acid, &res_getter.get_res_from_file("src/molecule/test_files/Benzoic acid.mol").unwrap().result);
+        assert_eq!(data["PUBCHEM_COMPOUND_CID"], "243");
+    }
+    #[test]
+    fn test_get_coordinates() {
+        let res_getter = GetRes::new();
+        let coordinates = res_getter.get_coordinates("src/molecule/test_files/Methane.mol").unwrap();
+        assert_eq!(coordinates.len(), res_getter.get_matrix("src/molecule/test_files/Methane.mol").unwrap().len());
+        assert_eq!(coordinates[1], vec![1.039, 0.987, 0.0]);
+    }
+    #[test]
+    fn test_explicit_hydrogens_same_result() {
+        let mut res_getter = GetRes::new();
+        for entry in std::fs::read_dir("src/molecule/test_files/").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().unwrap() != "mol" {
+                continue
+            }
+            let molecule = parser::parse_mol(path.to_str().unwrap()).unwrap();
+            let mut explicit = molecule.clone();
+            explicit.add_explicit_hydrogens();
+            let mut implicit = molecule.clone();
+            implicit.remove_explicit_hydrogens();
+            let result = res_getter.get_res(&molecule).result;
+            assert_eq!(result, res_getter.get_res(&explicit).result, "{:?}", path);
+            assert_eq!(result, res_getter.get_res(&implicit).result, "{:?}", path);
+        }
+    }
+    #[test]
+    fn test_get_matches() {
+        let mut res_getter = GetRes::new();
+        let groups = res_getter.get_funct_groups();
+        let result = res_getter.get_res_from_file("src/molecule/test_files/Benzoic acid.mol").unwrap().result;
+        let matches = res_getter.get_matches_from_file("src/molecule/test_files/Benzoic acid.mol", true).unwrap();
+        let counts = matches.counts();
+        // a group is found when it has at least one match
+        assert_eq!(counts.iter().map(|x| *x > 0).collect::<Vec<bool>>(), result);
+        let count = |name : &str| counts[groups.iter().position(|x| x == name).unwrap()];
+        assert_eq!(count("Aryl"), 1);
+        assert_eq!(count("Carboxyl"), 1);
+        assert_eq!(count("Ether"), 0);
+
+        // without collapsing, the ring is found 12 ways
+        let all = res_getter.get_matches_from_file("src/molecule/test_files/Benzoic acid.mol", false).unwrap();
+        println!("Benzoic acid : {}", all);
+        assert_eq!(all.counts()[groups.iter().position(|x| x == "Aryl").unwrap()], 12);
+    }
+    #[test]
+    fn test_from_file() {
+        let mut res_getter = GetRes::from_file("src/funct_groups/test_files/groups.toml").unwrap();
+        assert_eq!(res_getter.get_funct_groups(), vec!["Ketone", "Acyl Halide", "Alkyne"]);
+        assert_eq!(res_getter.get_funct_group_categories()[1], Some("Halogen".to_string()));
+        assert_eq!(res_getter.get_funct_group_smarts()[1], None);
+        let result = res_getter.get_res_from_file("src/molecule/test_files/Benzoic acid.mol").unwrap().result;
+        assert_eq!(result, vec![false, false, false]);
+        let result = res_getter.get_res(&crate::molecule::smiles::parse_smiles("CC(=O)CC#C").unwrap()).result;
+        assert_eq!(result, vec![true, false, true]);
+
+        // the built in groups are the default
+        let mut default = GetRes::new();
+        assert_eq!(default.get_funct_groups()[0], "Carbonyl");
+        assert_eq!(default.get_funct_group_categories()[0], Some("Oxygen".to_string()));
+        assert!(GetRes::from_file("src/funct_groups/test_files/missing.json").is_err());
+
+        let contents = std::fs::read_to_string("src/funct_groups/test_files/groups.toml").unwrap();
+        let mut from_string = GetRes::from_string(&contents, LibraryFormat::Toml).unwrap();
+        assert_eq!(from_string.get_funct_groups(), vec!["Ketone", "Acyl Halide", "Alkyne"]);
+        assert!(GetRes::from_string(&contents, LibraryFormat::Json).is_err());
+    }
+    #[test]
+    fn test_bad_file() {
+        let mut res_getter = GetRes::new();
+        assert!(res_getter.get_res_from_file("src/molecule/test_files/Methane.jdx").is_err());
+        assert!(res_getter.get_matrix("src/molecule/test_files/Methane.jdx").is_err());
+    }
+    #[test]
+    fn test_resolved_res() {
+        let mut res_getter = GetRes::new();
+        let groups = res_getter.get_funct_groups();
+        let index = |name : &str| groups.iter().position(|x| x == name).unwrap();
+        let smiles = |x : &str| crate::molecule::smiles::parse_smiles(x).unwrap();
+
+        // acetic acid's carbonyl and hydroxyl are part of its carboxyl
+        let acid = smiles("CC(=O)O");
+        let raw = res_getter.get_res(&acid).result;
+        assert!(raw[index("Carbonyl")] && raw[index("Hydroxyl")] && raw[index("Carboxyl")]);
+        let resolved = res_getter.get_resolved_res(&acid).result;
+        assert!(!resolved[index("Carbonyl")] && !resolved[index("Hydroxyl")] && resolved[index("Carboxyl")]);
+        assert_eq!(res_getter.get_atom_groups(&acid), vec![None, Some(index("Carboxyl")), Some(index("Carboxyl")), Some(index("Carboxyl"))]);
+
+        // a ketone on the same molecule is still a carbonyl, and an ester isn't a carboxyl
+        let keto_ester = smiles("CC(=O)CC(=O)OC");
+        let resolved = res_getter.get_resolved_matches(&keto_ester, true);
+        let counts = resolved.counts();
+        assert_eq!(counts[index("Carbonyl")], 1);
+        assert_eq!(counts[index("Ester")], 1);
+        assert_eq!(counts[index("Carboxyl")], 0);
+        assert_eq!(counts[index("Ether")], 0);
+        assert_eq!(res_getter.get_matches(&keto_ester, true).counts()[index("Carbonyl")], 2);
+
+        // groups from a library resolve by their own parents
+        let library = crate::funct_groups::library::parse_library(
+            "[[groups]]\nname = \"Carbonyl\"\nsmarts = \"C=O\"\n\n[[groups]]\nname = \"Aldehyde\"\nsmarts = \"[CH1]=O\"\nparents = [\"Carbonyl\"]\nconsumes = true",
+            crate::funct_groups::library::LibraryFormat::Toml).unwrap();
+        let mut res_getter = GetRes::from_funct_groups(library).unwrap();
+        assert_eq!(res_getter.get_resolved_res(&smiles("CC=O")).result, vec![false, true]);
+        assert_eq!(res_getter.get_resolved_res(&smiles("CC(C)=O")).result, vec![true, false]);
+    }
 }
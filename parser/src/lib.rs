@@ -10,12 +10,85 @@ mod parser_common;
 mod constants;
 
 use molecule::molecule::*;
+use molecule::element::Element;
 use molecule::parser::*;
+use molecule::error::{MolParseError, SmilesError, SmartsError, KekulizeError};
+use funct_groups::smarts::parse_smarts;
+use funct_groups::library::{LibraryError, LibraryFormat};
+use molecule::smiles::parse_smiles;
+use molecule::sdf::SdfRecord;
+use molecule::writer::{mol_to_string, write_mol, append_sdf, write_sdf};
+use molecule::formula::{element_counts, parse_formula};
 use get_res::{GetRes,};
 
-use spectra::{spectrum, parser::parse_jdx};
+use spectra::{spectrum, parser::{parse_jdx, parse_jdx_with, parse_jdx_str, JcampOptions}, error::{JcampError, ConversionError}};
+use spectra::spectrum::{Xunits, Yunits};
+
+use std::collections::HashMap;
 
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::exceptions::{PyIOError, PyValueError};
+
+// Raises mol parsing errors as python exceptions instead of panicking across the ffi boundary
+impl From<MolParseError> for PyErr {
+    fn from(err : MolParseError) -> PyErr {
+        match err {
+            MolParseError::Io(_) => PyIOError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+// Raises SMILES parsing errors as python ValueErrors
+impl From<SmilesError> for PyErr {
+    fn from(err : SmilesError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+// Raises SMARTS parsing errors as python ValueErrors
+impl From<SmartsError> for PyErr {
+    fn from(err : SmartsError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+// Raises JCAMP-DX parsing errors as python IOErrors when the file can't be read, and ValueErrors
+// when it isn't a spectrum that can be read
+impl From<JcampError> for PyErr {
+    fn from(err : JcampError) -> PyErr {
+        match err {
+            JcampError::Io(_) => PyIOError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+// Raises unit conversion errors as python ValueErrors
+impl From<ConversionError> for PyErr {
+    fn from(err : ConversionError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+// Raises functional group library errors as python IOErrors when the file can't be read, and
+// ValueErrors when it doesn't hold a valid library
+impl From<LibraryError> for PyErr {
+    fn from(err : LibraryError) -> PyErr {
+        match err {
+            LibraryError::Io(_) => PyIOError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+// Raises kekulization errors as python ValueErrors
+impl From<KekulizeError> for PyErr {
+    fn from(err : KekulizeError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
 
 // Object for parsing functional groups from .mol files
 #[pyclass]
@@ -32,59 +105,449 @@ impl ParseGroups {
     fn new() -> Self {
        ParseGroups{get_res : GetRes::new()}
     }
+    // Uses the functional groups of a .toml or .json library file instead of the built in ones
+    #[staticmethod]
+    fn from_file(file_path : &str) -> PyResult<Self> {
+        Ok(ParseGroups{get_res : GetRes::from_file(file_path)?})
+    }
+    // Uses the functional groups of the contents of a library file, format is "toml" or "json"
+    #[staticmethod]
+    fn from_string(contents : &str, format : &str) -> PyResult<Self> {
+        let format = LibraryFormat::parse(format)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown library format {:?}, expected \"toml\" or \"json\"", format)))?;
+        Ok(ParseGroups{get_res : GetRes::from_string(contents, format)?})
+    }
     fn get_funct_result(&mut self, file_path :&str) -> PyResult<Vec<bool>> {
-       Ok(self.get_res.get_res_from_file(file_path).result)
+       Ok(self.get_res.get_res_from_file(file_path)?.result)
+    }
+
+    // Same as get_funct_result, but takes the contents of a .mol file instead of a path
+    fn get_funct_result_from_string(&mut self, contents : &str) -> PyResult<Vec<bool>> {
+        Ok(self.get_res.get_res_from_string(contents)?.result)
+    }
+
+    // Gets the result of every record in an .sdf file along with its data items and error
+    // Records that fail to parse have None in place of their result and the error message in
+    // place of None, their data items are kept
+    #[allow(clippy::type_complexity)]
+    fn get_funct_results_from_sdf(&mut self, file_path : &str) -> PyResult<Vec<(Option<Vec<bool>>, HashMap<String, String>, Option<String>)>> {
+        let mut out = Vec::new();
+        for record in self.get_res.get_res_from_sdf(file_path)? {
+            match record {
+                Ok((res, data)) => out.push((Some(res), data, None)),
+                Err(e) => out.push((None, e.data, Some(e.error.to_string()))),
+            }
+        }
+        Ok(out)
+    }
+
+    // Same as get_funct_result, but takes a Molecule that has already been built
+    fn get_funct_result_from_molecule(&mut self, molecule : &PyMolecule) -> Vec<bool> {
+        self.get_res.get_res(&molecule.molecule).result
+    }
+
+    // The number of times each functional group is found in a .mol file, in the same order as
+    // get_funct_groups. With unique, symmetric matches of the same atoms are counted once
+    fn get_funct_counts(&mut self, file_path : &str, unique : bool) -> PyResult<Vec<usize>> {
+        Ok(self.get_res.get_matches_from_file(file_path, unique)?.counts())
+    }
+
+    // Every match of every functional group in a .mol file, each match is a list of the molecule
+    // atom of each atom of the group's SMARTS
+    fn get_funct_matches(&mut self, file_path : &str, unique : bool) -> PyResult<Vec<Vec<Vec<Option<usize>>>>> {
+        Ok(self.get_res.get_matches_from_file(file_path, unique)?.matches)
+    }
+
+    fn get_funct_counts_from_string(&mut self, contents : &str, unique : bool) -> PyResult<Vec<usize>> {
+        Ok(self.get_res.get_matches_from_string(contents, unique)?.counts())
+    }
+
+    fn get_funct_matches_from_string(&mut self, contents : &str, unique : bool) -> PyResult<Vec<Vec<Vec<Option<usize>>>>> {
+        Ok(self.get_res.get_matches_from_string(contents, unique)?.matches)
+    }
+
+    fn get_funct_counts_from_molecule(&mut self, molecule : &PyMolecule, unique : bool) -> Vec<usize> {
+        self.get_res.get_matches(&molecule.molecule, unique).counts()
+    }
+
+    fn get_funct_matches_from_molecule(&mut self, molecule : &PyMolecule, unique : bool) -> Vec<Vec<Vec<Option<usize>>>> {
+        self.get_res.get_matches(&molecule.molecule, unique).matches
+    }
+
+    // Same as get_funct_result, but a group that is only found on atoms taken by a more specific
+    // group isn't reported, so an acid is a Carboxyl and not also a Carbonyl and a Hydroxyl
+    fn get_resolved_funct_result(&mut self, file_path : &str) -> PyResult<Vec<bool>> {
+        Ok(self.get_res.get_resolved_res_from_file(file_path)?.result)
+    }
+
+    fn get_resolved_funct_result_from_string(&mut self, contents : &str) -> PyResult<Vec<bool>> {
+        Ok(self.get_res.get_resolved_res_from_string(contents)?.result)
+    }
+
+    fn get_resolved_funct_result_from_molecule(&mut self, molecule : &PyMolecule) -> Vec<bool> {
+        self.get_res.get_resolved_res(&molecule.molecule).result
+    }
+
+    // The resolved counts of each functional group in a Molecule
+    fn get_resolved_funct_counts_from_molecule(&mut self, molecule : &PyMolecule, unique : bool) -> Vec<usize> {
+        self.get_res.get_resolved_matches(&molecule.molecule, unique).counts()
+    }
+
+    // The index in get_funct_groups of the most specific group on each atom of a Molecule, None
+    // for atoms in no group
+    fn get_atom_groups_from_molecule(&mut self, molecule : &PyMolecule) -> Vec<Option<usize>> {
+        self.get_res.get_atom_groups(&molecule.molecule)
     }
 
     fn get_funct_result_and_print(&mut self, file_path : &str) -> PyResult<Vec<bool>> {
-        Ok(self.get_res.get_res_from_file_and_print(file_path).result)
+        Ok(self.get_res.get_res_from_file_and_print(file_path)?.result)
     }
 
     fn get_funct_groups(&mut self) -> PyResult<Vec<String>> {
         Ok(self.get_res.get_funct_groups())
     }
 
+    // The SMARTS pattern of each functional group, in the same order as get_funct_groups, None
+    // for groups given as a list of atoms
+    fn get_funct_group_smarts(&mut self) -> Vec<Option<String>> {
+        self.get_res.get_funct_group_smarts()
+    }
+
+    fn get_funct_group_categories(&mut self) -> Vec<Option<String>> {
+        self.get_res.get_funct_group_categories()
+    }
+
+    fn get_funct_group_descriptions(&mut self) -> Vec<Option<String>> {
+        self.get_res.get_funct_group_descriptions()
+    }
+
     fn get_matrix(&self, file_path : &str) -> PyResult<Vec<Vec<i32>>> {
-        Ok(self.get_res.get_matrix(file_path))
+        Ok(self.get_res.get_matrix(file_path)?)
+    }
+
+    fn get_matrix_from_string(&self, contents : &str) -> PyResult<Vec<Vec<i32>>> {
+        Ok(self.get_res.get_matrix_from_string(contents)?)
+    }
+
+    // Returns the (n, 3) x, y, z coordinates of the atoms, in the same order as get_matrix
+    fn get_coordinates(&self, file_path : &str) -> PyResult<Vec<Vec<f32>>> {
+        Ok(self.get_res.get_coordinates(file_path)?)
+    }
+
+    fn get_coordinates_from_string(&self, contents : &str) -> PyResult<Vec<Vec<f32>>> {
+        Ok(self.get_res.get_coordinates_from_string(contents)?)
+    }
+}
+
+// A molecule built from a SMILES string or a .mol file, which can be passed to ParseGroups
+#[pyclass(name = "Molecule")]
+struct PyMolecule {
+    molecule : Molecule,
+}
+#[pymethods]
+impl PyMolecule {
+    #[staticmethod]
+    fn from_smiles(smiles : &str) -> PyResult<Self> {
+        Ok(PyMolecule{molecule : parse_smiles(smiles)?})
+    }
+    #[staticmethod]
+    fn from_mol_file(file_path : &str) -> PyResult<Self> {
+        Ok(PyMolecule{molecule : parse_mol(file_path)?})
+    }
+    #[staticmethod]
+    fn from_mol_string(contents : &str) -> PyResult<Self> {
+        Ok(PyMolecule{molecule : parse_mol_str(contents)?})
+    }
+    // The contents of a .mol file of the molecule, V2000 unless it has more than 999 atoms or bonds
+    fn to_mol_string(&self) -> String {
+        mol_to_string(&self.molecule)
+    }
+    // Writes the molecule to a .mol file, replacing the file if it exists
+    fn write_mol(&self, file_path : &str) -> PyResult<()> {
+        Ok(write_mol(&self.molecule, file_path)?)
+    }
+    // Adds the molecule and its data items as a record at the end of an .sdf file, creating the
+    // file if it doesn't exist
+    fn append_sdf(&self, file_path : &str, data : HashMap<String, String>) -> PyResult<()> {
+        Ok(append_sdf(file_path)?.write_record(&self.molecule, &data)?)
+    }
+    // Writes a list of (Molecule, data items) as the records of an .sdf file, replacing the file
+    // if it exists
+    #[staticmethod]
+    fn write_sdf(records : Vec<(PyRef<PyMolecule>, HashMap<String, String>)>, file_path : &str) -> PyResult<()> {
+        let records : Vec<SdfRecord> = records.into_iter()
+            .map(|(x, data)| SdfRecord { molecule : x.molecule.clone(), data })
+            .collect();
+        Ok(write_sdf(&records, file_path)?)
+    }
+    // The canonical SMILES is the same for every .mol file of the same compound
+    fn to_smiles(&self, canonical : bool) -> String {
+        self.molecule.to_smiles(canonical)
+    }
+    // The element symbols of the atoms, query atoms have their .mol file symbol ("R", "A", "L", ...)
+    fn get_atoms(&self) -> Vec<String> {
+        self.molecule.atoms.iter().map(|x| x.to_string()).collect()
+    }
+    fn get_matrix(&self) -> Vec<Vec<i32>> {
+        self.molecule.get_matrix()
+    }
+    fn get_charges(&self) -> Vec<i32> {
+        self.molecule.charges.clone()
+    }
+    fn get_isotopes(&self) -> Vec<i32> {
+        self.molecule.isotopes.clone()
+    }
+    // The molecular formula in Hill order, counting implicit hydrogens
+    fn formula(&self) -> String {
+        self.molecule.formula()
+    }
+    fn average_mass(&self) -> f64 {
+        self.molecule.average_mass()
+    }
+    fn monoisotopic_mass(&self) -> f64 {
+        self.molecule.monoisotopic_mass()
+    }
+    // Checks the molecule against a formula such as the ##MOLFORM= of a .jdx file, "C10 H13 N O"
+    // Returns False for formulas that can't be read
+    fn matches_formula(&self, formula : &str) -> bool {
+        parse_formula(formula) == Some(element_counts(&self.molecule))
+    }
+    // The hydrogen count of each bracket atom, None for atoms written without brackets
+    fn get_hydrogens(&self) -> Vec<Option<i32>> {
+        self.molecule.hydrogens.clone()
+    }
+    // The number of implicit hydrogens on every atom
+    fn get_implicit_hydrogens(&self) -> Vec<i32> {
+        (0..self.molecule.atoms.len()).map(|x| self.molecule.implicit_hydrogens(x)).collect()
+    }
+    // The number of hydrogens on every atom, implicit or drawn as atoms
+    fn get_total_hydrogens(&self) -> Vec<i32> {
+        (0..self.molecule.atoms.len()).map(|x| self.molecule.total_hydrogens(x)).collect()
+    }
+    // The atoms of each ring in the smallest set of smallest rings, in order around the ring
+    fn get_rings(&self) -> Vec<Vec<usize>> {
+        self.molecule.rings().rings
+    }
+    fn ring_count(&self) -> usize {
+        self.molecule.rings().ring_count()
+    }
+    fn is_atom_in_ring(&self, atom : usize) -> bool {
+        self.molecule.rings().is_atom_in_ring(atom)
+    }
+    fn is_bond_in_ring(&self, x : usize, y : usize) -> bool {
+        self.molecule.rings().is_bond_in_ring(x, y)
+    }
+    // The sizes of the rings each atom is in, an empty list for atoms not in a ring
+    fn get_atom_ring_sizes(&self) -> Vec<Vec<usize>> {
+        let rings = self.molecule.rings();
+        (0..self.molecule.atoms.len()).map(|x| rings.atom_ring_sizes(x)).collect()
+    }
+    // The number of rings each atom is in
+    fn get_atom_ring_counts(&self) -> Vec<usize> {
+        self.molecule.rings().atom_ring_counts()
+    }
+    fn get_bond_ring_sizes(&self, x : usize, y : usize) -> Vec<usize> {
+        self.molecule.rings().bond_ring_sizes(x, y)
+    }
+    // The atoms of each fused ring system, rings that share a bond are in the same system
+    fn get_ring_systems(&self) -> Vec<Vec<usize>> {
+        self.molecule.rings().ring_system_atoms()
+    }
+    // Whether the pattern is a substructure of this molecule, with the functional group
+    // matching rules: "R" atoms in the pattern match a carbon or a hydrogen
+    fn has_substructure(&self, pattern : &PyMolecule) -> bool {
+        funct_groups::recognizer::is_subgraph(&self.molecule, &pattern.molecule)
+    }
+    // Every match of the pattern, each a list of the atom of this molecule matched by each
+    // pattern atom, None where an "R" is an implicit hydrogen
+    // With unique, matches of the same atoms that only differ by symmetry are returned once
+    fn find_substructure(&self, pattern : &PyMolecule, unique : bool) -> Vec<Vec<Option<usize>>> {
+        funct_groups::recognizer::find_matches(&self.molecule, &pattern.molecule, unique)
+    }
+    // Whether the SMARTS pattern is found in this molecule, raises ValueError if it doesn't parse
+    fn has_smarts(&self, smarts : &str) -> PyResult<bool> {
+        Ok(funct_groups::recognizer::has_query_match(&self.molecule, &parse_smarts(smarts)?))
+    }
+    // Every match of the SMARTS pattern, each a list of the atom of this molecule matched by each
+    // pattern atom. With unique, matches of the same atoms are returned once
+    fn find_smarts(&self, smarts : &str, unique : bool) -> PyResult<Vec<Vec<Option<usize>>>> {
+        Ok(funct_groups::recognizer::find_query_matches(&self.molecule, &parse_smarts(smarts)?, unique))
+    }
+    // Whether each atom is in a ring that is aromatic by Hückel's rule
+    fn get_aromatic_atoms(&self) -> Vec<bool> {
+        self.molecule.aromatic_atoms()
+    }
+    // Sets the bonds of aromatic rings to the aromatic bond type, 4
+    fn aromatize(&mut self) {
+        self.molecule.aromatize()
+    }
+    // Replaces aromatic bonds with alternating single and double bonds, raises a ValueError if
+    // there is no Kekulé structure
+    fn kekulize(&mut self) -> PyResult<()> {
+        Ok(self.molecule.kekulize()?)
+    }
+    fn bond_order(&self, x : usize, y : usize) -> f64 {
+        self.molecule.bond_order(x, y)
+    }
+    // Turns every implicit hydrogen into a hydrogen atom
+    fn add_explicit_hydrogens(&mut self) {
+        self.molecule.add_explicit_hydrogens()
+    }
+    // Turns hydrogen atoms back into implicit hydrogens where they don't carry anything else
+    fn remove_explicit_hydrogens(&mut self) {
+        self.molecule.remove_explicit_hydrogens();
     }
 }
 
 // Object for parsing and transforming 2d data from .jdx spectra
 #[pyclass]
 struct Spectrum {
-    spectrum : Option<spectrum::Spectrum>,
+    spectrum : spectrum::Spectrum,
 }
 #[pymethods]
 impl Spectrum {
+    // Raises an IOError if the file can't be read, and a ValueError if it isn't a valid spectrum
     #[new]
-    fn new(filepath : &str) -> Self {
-        match parse_jdx(filepath) {
-            Ok(x) => {
-                return Spectrum{spectrum : Some(x)}
-            }, Err(_) => {
-                return Spectrum{spectrum : None}
-            }
-        }
+    fn new(filepath : &str) -> PyResult<Self> {
+        Ok(Spectrum{spectrum : parse_jdx(filepath)?})
+    }
+    // Builds a Spectrum from the contents of a .jdx file instead of a path
+    #[staticmethod]
+    fn from_string(contents : &str) -> PyResult<Self> {
+        Ok(Spectrum{spectrum : parse_jdx_str(contents)?})
+    }
+    // Same as the constructor, x_tolerance is how far the x values of the XYDATA lines and the
+    // header can be from each other as a fraction of the point spacing, and with
+    // x_mismatch_is_error x values further off raise a ValueError instead of a warning
+    #[staticmethod]
+    fn with_options(filepath : &str, x_tolerance : f64, x_mismatch_is_error : bool) -> PyResult<Self> {
+        let options = JcampOptions { x_tolerance, x_mismatch_is_error };
+        Ok(Spectrum{spectrum : parse_jdx_with(filepath, &options)?})
     }
+    // Problems found reading the file that didn't stop it being read
+    fn warnings(&self) -> Vec<String> {
+        self.spectrum.warnings().to_vec()
+    }
+    // A Spectrum that can't be parsed raises when it is built, so this is always true
     fn is_valid(&self) -> bool {
-        if self.spectrum.is_none()  {
-            return false
-        } else {
-            return true
-        }
+        true
     }
     // Returns a new tranformed Spectrum
     fn transform(&self, first_x : f32, last_x : f32, npoints : i32) -> Spectrum {
-        Spectrum{spectrum : Some(self.spectrum.as_ref().unwrap().transform(first_x, last_x, npoints))}
+        Spectrum{spectrum : self.spectrum.transform(first_x, last_x, npoints)}
     }
     fn get_x_values(&self) -> Vec<f32> {
-        self.spectrum.as_ref().unwrap().get_x_values()
+        self.spectrum.get_x_values()
     }
     fn get_y_values(&self) -> Vec<f32> {
-        self.spectrum.as_ref().unwrap().get_y_values()
+        self.spectrum.get_y_values()
     }
     fn to_string(&self) -> String {
-        self.spectrum.as_ref().unwrap().to_string()
+        self.spectrum.to_string()
+    }
+    // The units of get_x_values, ex : "1/CM"
+    fn get_xunits(&self) -> String {
+        self.spectrum.xunits().label().to_string()
+    }
+    // Returns a new Spectrum with its x values in other units, ex : "1/CM", raises a ValueError for
+    // unknown units or units this spectrum can't be converted to
+    fn convert_x(&self, target : &str) -> PyResult<Spectrum> {
+        let target = Xunits::parse(target).ok_or_else(|| PyValueError::new_err(format!("unknown x units {:?}", target)))?;
+        Ok(Spectrum{spectrum : self.spectrum.convert_x(target)?})
+    }
+    // The units of get_y_values, ex : "ABSORBANCE"
+    fn get_yunits(&self) -> String {
+        self.spectrum.yunits().label().to_string()
+    }
+    // Returns a new Spectrum with its y values in other units, ex : "TRANSMITTANCE", raises a
+    // ValueError for unknown units or units this spectrum can't be converted to
+    fn convert_y(&self, target : &str) -> PyResult<Spectrum> {
+        let target = Yunits::parse(target).ok_or_else(|| PyValueError::new_err(format!("unknown y units {:?}", target)))?;
+        Ok(Spectrum{spectrum : self.spectrum.convert_y(target)?})
+    }
+    // Every label of the jcamp header and its value, in the order they were written
+    fn metadata<'py>(&self, py : Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        for entry in self.spectrum.header().entries() {
+            dict.set_item(&entry.label, &entry.value)?;
+        }
+        Ok(dict)
+    }
+    // The value of one label, found whatever its case, spaces and dashes
+    fn get_metadata(&self, label : &str) -> Option<String> {
+        self.spectrum.header().get(label).map(|x| x.to_string())
+    }
+    fn title(&self) -> String {
+        self.spectrum.title().to_string()
+    }
+    fn data_type(&self) -> String {
+        self.spectrum.data_type().to_string()
+    }
+    fn state(&self) -> String {
+        self.spectrum.state().to_string()
+    }
+    fn cas_registry_no(&self) -> Option<String> {
+        self.spectrum.cas_registry_no().map(|x| x.to_string())
+    }
+    fn molform(&self) -> Option<String> {
+        self.spectrum.molform().map(|x| x.to_string())
+    }
+    fn origin(&self) -> Option<String> {
+        self.spectrum.origin().map(|x| x.to_string())
+    }
+    fn resolution(&self) -> Option<f32> {
+        self.spectrum.resolution()
+    }
+    fn spectrometer(&self) -> Option<String> {
+        self.spectrum.spectrometer().map(|x| x.to_string())
+    }
+    fn sampling_procedure(&self) -> Option<String> {
+        self.spectrum.sampling_procedure().map(|x| x.to_string())
+    }
+}
+
+// An element of the periodic table
+#[pyclass(name = "Element")]
+#[derive(Clone)]
+struct PyElement {
+    element : Element,
+}
+#[pymethods]
+impl PyElement {
+    // Symbols are case sensitive, "Co" is cobalt
+    #[staticmethod]
+    fn from_symbol(symbol : &str) -> PyResult<Self> {
+        Element::from_symbol(symbol).map(|element| PyElement{element})
+            .ok_or_else(|| PyValueError::new_err(format!("unknown element symbol {:?}", symbol)))
+    }
+    #[staticmethod]
+    fn from_atomic_number(number : u32) -> PyResult<Self> {
+        Element::from_atomic_number(number).map(|element| PyElement{element})
+            .ok_or_else(|| PyValueError::new_err(format!("no element has the atomic number {}", number)))
+    }
+    // Every element in order of atomic number
+    #[staticmethod]
+    fn all() -> Vec<PyElement> {
+        Element::all().iter().map(|x| PyElement{element : *x}).collect()
+    }
+    fn symbol(&self) -> &'static str {
+        self.element.symbol()
+    }
+    fn atomic_number(&self) -> u32 {
+        self.element.atomic_number()
+    }
+    fn average_mass(&self) -> f64 {
+        self.element.average_mass()
+    }
+    fn monoisotopic_mass(&self) -> f64 {
+        self.element.monoisotopic_mass()
+    }
+    fn default_valences(&self) -> Vec<i32> {
+        self.element.default_valences().to_vec()
+    }
+    // The Pauling electronegativity, None where it isn't known
+    fn electronegativity(&self) -> Option<f64> {
+        self.element.electronegativity()
     }
 }
 
@@ -92,7 +555,9 @@ impl Spectrum {
 #[pymodule]
 fn mol_swatter(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<ParseGroups>()?;
+    m.add_class::<PyMolecule>()?;
     m.add_class::<Spectrum>()?;
+    m.add_class::<PyElement>()?;
     Ok(())
 }
 
@@ -102,8 +567,8 @@ mod tests {
     use super::*;
     #[test]
     fn test_imports() {
-        let molecule : Molecule = Molecule::new(vec!["a", "b", "c", "d"]);
-        println!("{}", molecule.to_string());
-        parse_mol("src/molecule/test_files/Pentanoic acid.mol");
+        let molecule : Molecule = Molecule::new(vec!["C", "O", "N", "S"]);
+        println!("{}", molecule);
+        parse_mol("src/molecule/test_files/Pentanoic acid.mol").unwrap();
     }
 }
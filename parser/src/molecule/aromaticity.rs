@@ -0,0 +1,370 @@
+/**
+ * Aromaticity and kekulization
+ *
+ * A ring is aromatic by Hückel's rule when every atom in it is sp2 and it holds 4n + 2 pi
+ * electrons. An atom with a double bond inside the rings gives 1 electron, a lone pair that isn't
+ * in a double bond gives 2 (pyrrole nitrogen, furan oxygen, a carbanion), an empty p orbital or a
+ * double bond to an oxygen, nitrogen or sulfur outside the ring gives 0. Rings that aren't
+ * aromatic on their own are tried together with the rings they are fused to, for azulene.
+ *
+ * Aromatic rings are stored with bond type 4, as in the .mol bond block, so both Kekulé
+ * structures of a ring end up as the same molecule.
+ **/
+
+use crate::molecule::molecule::Molecule;
+use crate::molecule::element::Element;
+use crate::molecule::error::KekulizeError;
+use crate::molecule::rings::RingInfo;
+use crate::molecule::valence::{bond_order_sum, charged_valences, implicit_hydrogens};
+
+// The bond type used for aromatic bonds, as in the .mol bond block
+static AROMATIC_BOND : i32 = 4;
+
+// Elements that can be in an aromatic ring
+static AROMATIC_ELEMENTS : [Element; 9] = [
+    Element::B, Element::C, Element::N, Element::O, Element::P,
+    Element::S, Element::Se, Element::As, Element::Te,
+];
+
+// The bonds an atom uses up when every aromatic bond is counted as single, including its
+// implicit hydrogens
+fn used_valence(molecule : &Molecule, atom : usize) -> i32 {
+    bond_order_sum(molecule, atom) + implicit_hydrogens(molecule, atom)
+}
+
+// Whether an atom with aromatic bonds needs one of them to be a double bond in a Kekulé structure
+// The carbons of benzene and the nitrogen of pyridine do, the nitrogen of pyrrole doesn't
+fn needs_double_bond(molecule : &Molecule, atom : usize) -> bool {
+    let element = match molecule.atoms[atom].element() {
+        Some(x) => x,
+        None => return false,
+    };
+    let used = used_valence(molecule, atom);
+    charged_valences(element, molecule.charges[atom]).iter()
+        .find(|x| **x >= used)
+        .is_some_and(|x| *x == used + 1)
+}
+
+// The number of pi electrons an atom gives to a ring, None if it can't be in an aromatic ring
+fn pi_electrons(molecule : &Molecule, atom : usize, rings : &RingInfo) -> Option<i32> {
+    let element = molecule.atoms[atom].element()?;
+    if !AROMATIC_ELEMENTS.contains(&element) {
+        return None
+    }
+    let charge = molecule.charges[atom];
+    let bonds : Vec<(usize, i32)> = molecule.neighbours(atom).collect();
+    let lone_pair = !matches!(element, Element::B | Element::C) || charge < 0;
+
+    if bonds.iter().any(|(_, bond)| *bond == AROMATIC_BOND) {
+        if needs_double_bond(molecule, atom) {
+            return Some(1)
+        }
+        return Some(if lone_pair { 2 } else { 0 })
+    }
+
+    if bonds.iter().any(|(_, bond)| *bond == 3) {
+        return None
+    }
+    let doubles : Vec<usize> = bonds.iter().filter(|(_, bond)| *bond == 2).map(|(x, _)| *x).collect();
+    match doubles.len() {
+        0 => {
+            if !lone_pair {
+                // a carbocation or a boron has an empty p orbital, anything else is sp3
+                return if element == Element::B || charge > 0 { Some(0) } else { None }
+            }
+            // the lone pair is only free if the atom isn't using it for a bond
+            let lowest = *charged_valences(element, charge).first()?;
+            if used_valence(molecule, atom) <= lowest { Some(2) } else { None }
+        },
+        1 => {
+            let other = doubles[0];
+            if rings.is_bond_in_ring(atom, other) {
+                Some(1)
+            } else if molecule.atoms[other].element().is_some_and(|x| matches!(x, Element::O | Element::N | Element::S)) {
+                // the electrons of an exocyclic carbonyl stay on the oxygen, as in 2-pyridone
+                Some(0)
+            } else {
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+// Whether a ring, given as its atoms, follows Hückel's rule
+fn is_huckel(atoms : &[usize], electrons : &[Option<i32>]) -> bool {
+    let mut sum = 0;
+    for atom in atoms {
+        match electrons[*atom] {
+            Some(x) => sum += x,
+            None => return false,
+        }
+    }
+    sum % 4 == 2
+}
+
+// The aromatic rings of a molecule, each given as the atoms of one or two fused rings of the
+// smallest set of smallest rings
+pub fn aromatic_rings(molecule : &Molecule) -> Vec<Vec<usize>> {
+    let rings = molecule.rings();
+    let electrons : Vec<Option<i32>> = (0..molecule.atoms.len())
+        .map(|x| pi_electrons(molecule, x, &rings))
+        .collect();
+
+    let mut aromatic = vec![false; rings.rings.len()];
+    let mut out = Vec::new();
+    for (i, ring) in rings.rings.iter().enumerate() {
+        if is_huckel(ring, &electrons) {
+            aromatic[i] = true;
+            out.push(ring.clone());
+        }
+    }
+    // two fused rings that aren't aromatic on their own can be aromatic around the outside
+    for system in rings.ring_systems() {
+        for (a, i) in system.iter().enumerate() {
+            for j in &system[a + 1..] {
+                if aromatic[*i] && aromatic[*j] {
+                    continue
+                }
+                let (x, y) = (&rings.rings[*i], &rings.rings[*j]);
+                if !(0..x.len()).any(|k| y.contains(&x[k]) && y.contains(&x[(k + 1) % x.len()])) {
+                    continue
+                }
+                let mut atoms = x.clone();
+                atoms.extend(y.iter().filter(|z| !x.contains(z)));
+                if is_huckel(&atoms, &electrons) {
+                    aromatic[*i] = true;
+                    aromatic[*j] = true;
+                    out.push(x.clone());
+                    out.push(y.clone());
+                }
+            }
+        }
+    }
+    out.dedup();
+    out
+}
+
+// Whether each atom is in an aromatic ring
+pub fn aromatic_atoms(molecule : &Molecule) -> Vec<bool> {
+    let mut out = vec![false; molecule.atoms.len()];
+    for ring in aromatic_rings(molecule) {
+        for atom in ring {
+            out[atom] = true;
+        }
+    }
+    out
+}
+
+// The aromatic nitrogens that need a hydrogen for their ring to have a Kekulé structure
+// Aromatic bonds from a .mol file don't say which nitrogens carry a hydrogen, and by valence alone
+// the nitrogen of pyrrole has none. Every double bond pairs two atoms, so a ring system with an
+// odd number of atoms needing one has no Kekulé structure, and the first nitrogen with two bonds
+// and no hydrogen count in one of its five membered rings is given the hydrogen
+pub(crate) fn pyrrole_hydrogens(molecule : &Molecule) -> Vec<usize> {
+    let rings = molecule.rings();
+    let mut seen = vec![false; molecule.atoms.len()];
+    let mut out = Vec::new();
+    for start in 0..molecule.atoms.len() {
+        if seen[start] || !molecule.neighbours(start).any(|(_, bond)| bond == AROMATIC_BOND) {
+            continue
+        }
+        // the atoms joined to start by aromatic bonds
+        seen[start] = true;
+        let mut system = vec![start];
+        let mut i = 0;
+        while i < system.len() {
+            for (other, bond) in molecule.neighbours(system[i]) {
+                if bond == AROMATIC_BOND && !seen[other] {
+                    seen[other] = true;
+                    system.push(other);
+                }
+            }
+            i += 1;
+        }
+        if system.iter().filter(|x| needs_double_bond(molecule, **x)).count() % 2 == 0 {
+            continue
+        }
+        let nitrogen = system.iter().copied().find(|x| molecule.atoms[*x].is_element(Element::N)
+            && molecule.hydrogens[*x].is_none() && molecule.charges[*x] == 0 && molecule.degree(*x) == 2
+            && needs_double_bond(molecule, *x) && rings.atom_ring_sizes(*x).contains(&5));
+        out.extend(nitrogen);
+    }
+    out
+}
+
+// Changes bonds while keeping every atom's hydrogen count, atoms whose hydrogens would change
+// with the new bonds get their old count as a fixed count
+fn set_bonds_keeping_hydrogens(molecule : &mut Molecule, bonds : &[(usize, usize, i32)]) {
+    let before : Vec<i32> = (0..molecule.atoms.len()).map(|x| implicit_hydrogens(molecule, x)).collect();
+    for (x, y, bond_type) in bonds {
+        molecule.set_bond(*x, *y, *bond_type);
+    }
+    for (atom, hydrogens) in before.into_iter().enumerate() {
+        if implicit_hydrogens(molecule, atom) != hydrogens {
+            molecule.hydrogens[atom] = Some(hydrogens);
+        }
+    }
+}
+
+// Sets every bond of every aromatic ring to the aromatic bond type
+pub fn aromatize(molecule : &mut Molecule) {
+    let mut bonds = Vec::new();
+    for ring in aromatic_rings(molecule) {
+        for i in 0..ring.len() {
+            bonds.push((ring[i], ring[(i + 1) % ring.len()], AROMATIC_BOND));
+        }
+    }
+    set_bonds_keeping_hydrogens(molecule, &bonds);
+}
+
+// Pairs every atom in need of a double bond with a neighbour that needs one too, backtracking
+// from the atom with the fewest choices
+fn match_double_bonds(partners : &[Vec<usize>], matched : &mut [Option<usize>]) -> bool {
+    let next = (0..partners.len())
+        .filter(|x| !partners[*x].is_empty() && matched[*x].is_none())
+        .min_by_key(|x| partners[*x].iter().filter(|y| matched[**y].is_none()).count());
+    let atom = match next {
+        Some(x) => x,
+        None => return true,
+    };
+    for partner in partners[atom].clone() {
+        if matched[partner].is_some() {
+            continue
+        }
+        matched[atom] = Some(partner);
+        matched[partner] = Some(atom);
+        if match_double_bonds(partners, matched) {
+            return true
+        }
+        matched[atom] = None;
+        matched[partner] = None;
+    }
+    false
+}
+
+// Replaces aromatic bonds with alternating single and double bonds
+// The molecule is left as it was if there is no Kekulé structure
+pub fn kekulize(molecule : &mut Molecule) -> Result<(), KekulizeError> {
+    let aromatic_bonds : Vec<(usize, usize)> = molecule.get_bonds().into_iter()
+        .filter(|(_, _, bond_type)| *bond_type == AROMATIC_BOND)
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    if aromatic_bonds.is_empty() {
+        return Ok(())
+    }
+    let needs_double : Vec<bool> = (0..molecule.atoms.len()).map(|x| needs_double_bond(molecule, x)).collect();
+    let mut partners : Vec<Vec<usize>> = vec![Vec::new(); molecule.atoms.len()];
+    for (x, y) in &aromatic_bonds {
+        if needs_double[*x] && needs_double[*y] {
+            partners[*x].push(*y);
+            partners[*y].push(*x);
+        }
+    }
+    let lonely : Vec<usize> = (0..molecule.atoms.len())
+        .filter(|x| needs_double[*x] && partners[*x].is_empty() && molecule.neighbours(*x).any(|(_, bond)| bond == AROMATIC_BOND))
+        .collect();
+    let mut matched = vec![None; molecule.atoms.len()];
+    if !lonely.is_empty() || !match_double_bonds(&partners, &mut matched) {
+        let mut atoms = lonely;
+        if atoms.is_empty() {
+            atoms = (0..molecule.atoms.len()).filter(|x| !partners[*x].is_empty()).collect();
+        }
+        return Err(KekulizeError { atoms })
+    }
+
+    let bonds : Vec<(usize, usize, i32)> = aromatic_bonds.iter()
+        .map(|(x, y)| (*x, *y, if matched[*x] == Some(*y) { 2 } else { 1 }))
+        .collect();
+    set_bonds_keeping_hydrogens(molecule, &bonds);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::parse_mol;
+    use crate::molecule::smiles::parse_smiles;
+
+    fn is_aromatic(smiles : &str) -> Vec<bool> {
+        aromatic_atoms(&parse_smiles(smiles).unwrap())
+    }
+
+    #[test]
+    fn test_aromatic_atoms() {
+        // both Kekulé structures of benzene, and the aromatic SMILES
+        assert_eq!(is_aromatic("C1=CC=CC=C1"), vec![true; 6]);
+        assert_eq!(is_aromatic("C=1C=CC=CC=1"), vec![true; 6]);
+        assert_eq!(is_aromatic("c1ccccc1"), vec![true; 6]);
+        // pyridine, pyrrole, furan, thiophene and 2-pyridone
+        assert_eq!(is_aromatic("C1=CC=NC=C1"), vec![true; 6]);
+        assert_eq!(is_aromatic("C1=CNC=C1"), vec![true; 5]);
+        assert_eq!(is_aromatic("C1=COC=C1"), vec![true; 5]);
+        assert_eq!(is_aromatic("c1ccsc1"), vec![true; 5]);
+        assert_eq!(is_aromatic("O=C1NC=CC=C1"), vec![false, true, true, true, true, true, true]);
+        // the cyclopentadienyl anion and tropylium cation
+        assert_eq!(is_aromatic("[CH-]1C=CC=C1"), vec![true; 5]);
+        assert_eq!(is_aromatic("[CH+]1C=CC=CC=C1"), vec![true; 7]);
+        // azulene is only aromatic around the outside
+        assert_eq!(is_aromatic("C1=CC2=CC=CC=CC2=C1"), vec![true; 10]);
+        // naphthalene, with a fused carbon in a double bond of the other ring
+        assert_eq!(is_aromatic("C1=CC=C2C=CC=CC2=C1"), vec![true; 10]);
+
+        // cyclooctatetraene, cyclopentadiene, cyclohexene and toluene's methyl
+        assert_eq!(is_aromatic("C1=CC=CC=CC=C1"), vec![false; 8]);
+        assert_eq!(is_aromatic("C1=CCC=C1"), vec![false; 5]);
+        assert_eq!(is_aromatic("C1=CCCCC1"), vec![false; 6]);
+        assert_eq!(is_aromatic("CC1=CC=CC=C1"), vec![false, true, true, true, true, true, true]);
+    }
+
+    #[test]
+    fn test_aromatize() {
+        let mut kekule_a = parse_smiles("OC(=O)C1=CC=CC=C1").unwrap();
+        let mut kekule_b = parse_smiles("OC(=O)C1C=CC=CC=1").unwrap();
+        kekule_a.aromatize();
+        kekule_b.aromatize();
+        assert_eq!(kekule_a.get_matrix(), kekule_b.get_matrix());
+        assert_eq!(kekule_a.get(3, 4), AROMATIC_BOND);
+        assert_eq!(kekule_a.formula(), "C7H6O2");
+
+        // the pyrrole nitrogen keeps its hydrogen
+        let mut pyrrole = parse_smiles("C1=CNC=C1").unwrap();
+        pyrrole.aromatize();
+        assert_eq!(pyrrole.formula(), "C4H5N");
+        assert_eq!(pyrrole.implicit_hydrogens(2), 1);
+    }
+
+    #[test]
+    fn test_kekulize() {
+        for smiles in ["c1ccccc1", "c1ccncc1", "c1cc[nH]c1", "c1ccoc1", "c1ccc2ccccc2c1", "O=c1[nH]cccc1", "Cc1ccccc1O"].iter() {
+            let aromatic = parse_smiles(smiles).unwrap();
+            let mut kekule = aromatic.clone();
+            kekule.kekulize().unwrap();
+            assert!(!kekule.get_matrix().iter().any(|x| x.contains(&AROMATIC_BOND)), "{}", smiles);
+            assert_eq!(kekule.formula(), aromatic.formula(), "{}", smiles);
+            // every atom that needed a double bond got exactly one
+            for atom in 0..kekule.atoms.len() {
+                let doubles = kekule.get_matrix()[atom].iter().filter(|x| **x == 2).count();
+                assert!(doubles <= 1, "{}", smiles);
+            }
+            // and going back gives the aromatic bonds again
+            kekule.aromatize();
+            assert_eq!(kekule.get_matrix(), aromatic.get_matrix(), "{}", smiles);
+        }
+        // a pyrrole nitrogen without its hydrogen can't be kekulized
+        let mut bad = parse_smiles("c1cccc1").unwrap();
+        assert!(bad.kekulize().is_err());
+        assert_eq!(bad.get(0, 1), AROMATIC_BOND);
+    }
+
+    #[test]
+    fn test_mol_files() {
+        let mut benzoic = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap();
+        let formula = benzoic.formula();
+        benzoic.aromatize();
+        assert_eq!(aromatic_atoms(&benzoic).iter().filter(|x| **x).count(), 6);
+        assert_eq!(benzoic.formula(), formula);
+    }
+}
@@ -0,0 +1,387 @@
+/**
+ * The periodic table
+ *
+ * Every element has its atomic number, symbol, average mass (the standard atomic weight, or the
+ * mass number of the longest lived isotope for elements without one), monoisotopic mass (the
+ * exact mass of the most abundant or longest lived isotope), the valences it usually takes in
+ * organic molecules (empty for metals, which don't have a default) and its Pauling
+ * electronegativity (None where it isn't known).
+ **/
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Element {
+    H = 1,
+    He = 2,
+    Li = 3,
+    Be = 4,
+    B = 5,
+    C = 6,
+    N = 7,
+    O = 8,
+    F = 9,
+    Ne = 10,
+    Na = 11,
+    Mg = 12,
+    Al = 13,
+    Si = 14,
+    P = 15,
+    S = 16,
+    Cl = 17,
+    Ar = 18,
+    K = 19,
+    Ca = 20,
+    Sc = 21,
+    Ti = 22,
+    V = 23,
+    Cr = 24,
+    Mn = 25,
+    Fe = 26,
+    Co = 27,
+    Ni = 28,
+    Cu = 29,
+    Zn = 30,
+    Ga = 31,
+    Ge = 32,
+    As = 33,
+    Se = 34,
+    Br = 35,
+    Kr = 36,
+    Rb = 37,
+    Sr = 38,
+    Y = 39,
+    Zr = 40,
+    Nb = 41,
+    Mo = 42,
+    Tc = 43,
+    Ru = 44,
+    Rh = 45,
+    Pd = 46,
+    Ag = 47,
+    Cd = 48,
+    In = 49,
+    Sn = 50,
+    Sb = 51,
+    Te = 52,
+    I = 53,
+    Xe = 54,
+    Cs = 55,
+    Ba = 56,
+    La = 57,
+    Ce = 58,
+    Pr = 59,
+    Nd = 60,
+    Pm = 61,
+    Sm = 62,
+    Eu = 63,
+    Gd = 64,
+    Tb = 65,
+    Dy = 66,
+    Ho = 67,
+    Er = 68,
+    Tm = 69,
+    Yb = 70,
+    Lu = 71,
+    Hf = 72,
+    Ta = 73,
+    W = 74,
+    Re = 75,
+    Os = 76,
+    Ir = 77,
+    Pt = 78,
+    Au = 79,
+    Hg = 80,
+    Tl = 81,
+    Pb = 82,
+    Bi = 83,
+    Po = 84,
+    At = 85,
+    Rn = 86,
+    Fr = 87,
+    Ra = 88,
+    Ac = 89,
+    Th = 90,
+    Pa = 91,
+    U = 92,
+    Np = 93,
+    Pu = 94,
+    Am = 95,
+    Cm = 96,
+    Bk = 97,
+    Cf = 98,
+    Es = 99,
+    Fm = 100,
+    Md = 101,
+    No = 102,
+    Lr = 103,
+    Rf = 104,
+    Db = 105,
+    Sg = 106,
+    Bh = 107,
+    Hs = 108,
+    Mt = 109,
+    Ds = 110,
+    Rg = 111,
+    Cn = 112,
+    Nh = 113,
+    Fl = 114,
+    Mc = 115,
+    Lv = 116,
+    Ts = 117,
+    Og = 118,
+}
+
+struct ElementData {
+    symbol : &'static str,
+    average_mass : f64,
+    monoisotopic_mass : f64,
+    valences : &'static [i32],
+    electronegativity : Option<f64>,
+}
+
+// Every element in order of atomic number
+static ALL_ELEMENTS : [Element; 118] = [
+    Element::H, Element::He, Element::Li, Element::Be, Element::B, Element::C, Element::N,
+    Element::O, Element::F, Element::Ne, Element::Na, Element::Mg, Element::Al, Element::Si,
+    Element::P, Element::S, Element::Cl, Element::Ar, Element::K, Element::Ca, Element::Sc,
+    Element::Ti, Element::V, Element::Cr, Element::Mn, Element::Fe, Element::Co, Element::Ni,
+    Element::Cu, Element::Zn, Element::Ga, Element::Ge, Element::As, Element::Se, Element::Br,
+    Element::Kr, Element::Rb, Element::Sr, Element::Y, Element::Zr, Element::Nb, Element::Mo,
+    Element::Tc, Element::Ru, Element::Rh, Element::Pd, Element::Ag, Element::Cd, Element::In,
+    Element::Sn, Element::Sb, Element::Te, Element::I, Element::Xe, Element::Cs, Element::Ba,
+    Element::La, Element::Ce, Element::Pr, Element::Nd, Element::Pm, Element::Sm, Element::Eu,
+    Element::Gd, Element::Tb, Element::Dy, Element::Ho, Element::Er, Element::Tm, Element::Yb,
+    Element::Lu, Element::Hf, Element::Ta, Element::W, Element::Re, Element::Os, Element::Ir,
+    Element::Pt, Element::Au, Element::Hg, Element::Tl, Element::Pb, Element::Bi, Element::Po,
+    Element::At, Element::Rn, Element::Fr, Element::Ra, Element::Ac, Element::Th, Element::Pa,
+    Element::U, Element::Np, Element::Pu, Element::Am, Element::Cm, Element::Bk, Element::Cf,
+    Element::Es, Element::Fm, Element::Md, Element::No, Element::Lr, Element::Rf, Element::Db,
+    Element::Sg, Element::Bh, Element::Hs, Element::Mt, Element::Ds, Element::Rg, Element::Cn,
+    Element::Nh, Element::Fl, Element::Mc, Element::Lv, Element::Ts, Element::Og,
+];
+
+// The data for every element, in the same order as ALL_ELEMENTS
+static ELEMENT_DATA : [ElementData; 118] = [
+    ElementData { symbol : "H", average_mass : 1.008, monoisotopic_mass : 1.00782503207, valences : &[1], electronegativity : Some(2.20) },
+    ElementData { symbol : "He", average_mass : 4.002602, monoisotopic_mass : 4.00260325415, valences : &[0], electronegativity : None },
+    ElementData { symbol : "Li", average_mass : 6.94, monoisotopic_mass : 7.0160034366, valences : &[1], electronegativity : Some(0.98) },
+    ElementData { symbol : "Be", average_mass : 9.0121831, monoisotopic_mass : 9.012183065, valences : &[2], electronegativity : Some(1.57) },
+    ElementData { symbol : "B", average_mass : 10.81, monoisotopic_mass : 11.00930536, valences : &[3], electronegativity : Some(2.04) },
+    ElementData { symbol : "C", average_mass : 12.011, monoisotopic_mass : 12.0, valences : &[4], electronegativity : Some(2.55) },
+    ElementData { symbol : "N", average_mass : 14.007, monoisotopic_mass : 14.0030740048, valences : &[3, 5], electronegativity : Some(3.04) },
+    ElementData { symbol : "O", average_mass : 15.999, monoisotopic_mass : 15.99491461956, valences : &[2], electronegativity : Some(3.44) },
+    ElementData { symbol : "F", average_mass : 18.998403163, monoisotopic_mass : 18.99840316273, valences : &[1], electronegativity : Some(3.98) },
+    ElementData { symbol : "Ne", average_mass : 20.1797, monoisotopic_mass : 19.9924401762, valences : &[0], electronegativity : None },
+    ElementData { symbol : "Na", average_mass : 22.98976928, monoisotopic_mass : 22.989769282, valences : &[1], electronegativity : Some(0.93) },
+    ElementData { symbol : "Mg", average_mass : 24.305, monoisotopic_mass : 23.985041697, valences : &[2], electronegativity : Some(1.31) },
+    ElementData { symbol : "Al", average_mass : 26.9815385, monoisotopic_mass : 26.98153853, valences : &[3], electronegativity : Some(1.61) },
+    ElementData { symbol : "Si", average_mass : 28.085, monoisotopic_mass : 27.97692653465, valences : &[4], electronegativity : Some(1.90) },
+    ElementData { symbol : "P", average_mass : 30.973761998, monoisotopic_mass : 30.97376199842, valences : &[3, 5], electronegativity : Some(2.19) },
+    ElementData { symbol : "S", average_mass : 32.06, monoisotopic_mass : 31.9720711744, valences : &[2, 4, 6], electronegativity : Some(2.58) },
+    ElementData { symbol : "Cl", average_mass : 35.45, monoisotopic_mass : 34.968852682, valences : &[1, 3, 5, 7], electronegativity : Some(3.16) },
+    ElementData { symbol : "Ar", average_mass : 39.948, monoisotopic_mass : 39.9623831237, valences : &[0], electronegativity : None },
+    ElementData { symbol : "K", average_mass : 39.0983, monoisotopic_mass : 38.9637064864, valences : &[1], electronegativity : Some(0.82) },
+    ElementData { symbol : "Ca", average_mass : 40.078, monoisotopic_mass : 39.962590863, valences : &[2], electronegativity : Some(1.00) },
+    ElementData { symbol : "Sc", average_mass : 44.955908, monoisotopic_mass : 44.95590828, valences : &[], electronegativity : Some(1.36) },
+    ElementData { symbol : "Ti", average_mass : 47.867, monoisotopic_mass : 47.94794198, valences : &[], electronegativity : Some(1.54) },
+    ElementData { symbol : "V", average_mass : 50.9415, monoisotopic_mass : 50.94395704, valences : &[], electronegativity : Some(1.63) },
+    ElementData { symbol : "Cr", average_mass : 51.9961, monoisotopic_mass : 51.94050623, valences : &[], electronegativity : Some(1.66) },
+    ElementData { symbol : "Mn", average_mass : 54.938044, monoisotopic_mass : 54.93804391, valences : &[], electronegativity : Some(1.55) },
+    ElementData { symbol : "Fe", average_mass : 55.845, monoisotopic_mass : 55.93493633, valences : &[], electronegativity : Some(1.83) },
+    ElementData { symbol : "Co", average_mass : 58.933194, monoisotopic_mass : 58.93319429, valences : &[], electronegativity : Some(1.88) },
+    ElementData { symbol : "Ni", average_mass : 58.6934, monoisotopic_mass : 57.93534241, valences : &[], electronegativity : Some(1.91) },
+    ElementData { symbol : "Cu", average_mass : 63.546, monoisotopic_mass : 62.92959772, valences : &[], electronegativity : Some(1.90) },
+    ElementData { symbol : "Zn", average_mass : 65.38, monoisotopic_mass : 63.92914201, valences : &[], electronegativity : Some(1.65) },
+    ElementData { symbol : "Ga", average_mass : 69.723, monoisotopic_mass : 68.9255735, valences : &[3], electronegativity : Some(1.81) },
+    ElementData { symbol : "Ge", average_mass : 72.630, monoisotopic_mass : 73.921177761, valences : &[4], electronegativity : Some(2.01) },
+    ElementData { symbol : "As", average_mass : 74.921595, monoisotopic_mass : 74.92159457, valences : &[3, 5], electronegativity : Some(2.18) },
+    ElementData { symbol : "Se", average_mass : 78.971, monoisotopic_mass : 79.9165218, valences : &[2, 4, 6], electronegativity : Some(2.55) },
+    ElementData { symbol : "Br", average_mass : 79.904, monoisotopic_mass : 78.9183376, valences : &[1, 3, 5, 7], electronegativity : Some(2.96) },
+    ElementData { symbol : "Kr", average_mass : 83.798, monoisotopic_mass : 83.9114977282, valences : &[0], electronegativity : Some(3.00) },
+    ElementData { symbol : "Rb", average_mass : 85.4678, monoisotopic_mass : 84.9117897379, valences : &[1], electronegativity : Some(0.82) },
+    ElementData { symbol : "Sr", average_mass : 87.62, monoisotopic_mass : 87.9056125, valences : &[2], electronegativity : Some(0.95) },
+    ElementData { symbol : "Y", average_mass : 88.90584, monoisotopic_mass : 88.9058403, valences : &[], electronegativity : Some(1.22) },
+    ElementData { symbol : "Zr", average_mass : 91.224, monoisotopic_mass : 89.9046977, valences : &[], electronegativity : Some(1.33) },
+    ElementData { symbol : "Nb", average_mass : 92.90637, monoisotopic_mass : 92.906373, valences : &[], electronegativity : Some(1.6) },
+    ElementData { symbol : "Mo", average_mass : 95.95, monoisotopic_mass : 97.90540482, valences : &[], electronegativity : Some(2.16) },
+    ElementData { symbol : "Tc", average_mass : 98.0, monoisotopic_mass : 97.9072124, valences : &[], electronegativity : Some(1.9) },
+    ElementData { symbol : "Ru", average_mass : 101.07, monoisotopic_mass : 101.9043441, valences : &[], electronegativity : Some(2.2) },
+    ElementData { symbol : "Rh", average_mass : 102.90550, monoisotopic_mass : 102.905498, valences : &[], electronegativity : Some(2.28) },
+    ElementData { symbol : "Pd", average_mass : 106.42, monoisotopic_mass : 105.9034804, valences : &[], electronegativity : Some(2.20) },
+    ElementData { symbol : "Ag", average_mass : 107.8682, monoisotopic_mass : 106.9050916, valences : &[], electronegativity : Some(1.93) },
+    ElementData { symbol : "Cd", average_mass : 112.414, monoisotopic_mass : 113.90336509, valences : &[], electronegativity : Some(1.69) },
+    ElementData { symbol : "In", average_mass : 114.818, monoisotopic_mass : 114.903878776, valences : &[3], electronegativity : Some(1.78) },
+    ElementData { symbol : "Sn", average_mass : 118.710, monoisotopic_mass : 119.90220163, valences : &[2, 4], electronegativity : Some(1.96) },
+    ElementData { symbol : "Sb", average_mass : 121.760, monoisotopic_mass : 120.903812, valences : &[3, 5], electronegativity : Some(2.05) },
+    ElementData { symbol : "Te", average_mass : 127.60, monoisotopic_mass : 129.906222748, valences : &[2, 4, 6], electronegativity : Some(2.1) },
+    ElementData { symbol : "I", average_mass : 126.90447, monoisotopic_mass : 126.9044719, valences : &[1, 3, 5, 7], electronegativity : Some(2.66) },
+    ElementData { symbol : "Xe", average_mass : 131.293, monoisotopic_mass : 131.9041550856, valences : &[0], electronegativity : Some(2.6) },
+    ElementData { symbol : "Cs", average_mass : 132.90545196, monoisotopic_mass : 132.905451961, valences : &[1], electronegativity : Some(0.79) },
+    ElementData { symbol : "Ba", average_mass : 137.327, monoisotopic_mass : 137.905247, valences : &[2], electronegativity : Some(0.89) },
+    ElementData { symbol : "La", average_mass : 138.90547, monoisotopic_mass : 138.9063563, valences : &[], electronegativity : Some(1.10) },
+    ElementData { symbol : "Ce", average_mass : 140.116, monoisotopic_mass : 139.9054431, valences : &[], electronegativity : Some(1.12) },
+    ElementData { symbol : "Pr", average_mass : 140.90766, monoisotopic_mass : 140.9076576, valences : &[], electronegativity : Some(1.13) },
+    ElementData { symbol : "Nd", average_mass : 144.242, monoisotopic_mass : 141.907729, valences : &[], electronegativity : Some(1.14) },
+    ElementData { symbol : "Pm", average_mass : 145.0, monoisotopic_mass : 144.9127559, valences : &[], electronegativity : None },
+    ElementData { symbol : "Sm", average_mass : 150.36, monoisotopic_mass : 151.9197397, valences : &[], electronegativity : Some(1.17) },
+    ElementData { symbol : "Eu", average_mass : 151.964, monoisotopic_mass : 152.921238, valences : &[], electronegativity : None },
+    ElementData { symbol : "Gd", average_mass : 157.25, monoisotopic_mass : 157.9241123, valences : &[], electronegativity : Some(1.20) },
+    ElementData { symbol : "Tb", average_mass : 158.92535, monoisotopic_mass : 158.9253547, valences : &[], electronegativity : None },
+    ElementData { symbol : "Dy", average_mass : 162.500, monoisotopic_mass : 163.9291819, valences : &[], electronegativity : Some(1.22) },
+    ElementData { symbol : "Ho", average_mass : 164.93033, monoisotopic_mass : 164.9303288, valences : &[], electronegativity : Some(1.23) },
+    ElementData { symbol : "Er", average_mass : 167.259, monoisotopic_mass : 165.9302995, valences : &[], electronegativity : Some(1.24) },
+    ElementData { symbol : "Tm", average_mass : 168.93422, monoisotopic_mass : 168.9342179, valences : &[], electronegativity : Some(1.25) },
+    ElementData { symbol : "Yb", average_mass : 173.045, monoisotopic_mass : 173.9388664, valences : &[], electronegativity : None },
+    ElementData { symbol : "Lu", average_mass : 174.9668, monoisotopic_mass : 174.9407752, valences : &[], electronegativity : Some(1.27) },
+    ElementData { symbol : "Hf", average_mass : 178.49, monoisotopic_mass : 179.946557, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Ta", average_mass : 180.94788, monoisotopic_mass : 180.9479958, valences : &[], electronegativity : Some(1.5) },
+    ElementData { symbol : "W", average_mass : 183.84, monoisotopic_mass : 183.95093092, valences : &[], electronegativity : Some(2.36) },
+    ElementData { symbol : "Re", average_mass : 186.207, monoisotopic_mass : 186.9557501, valences : &[], electronegativity : Some(1.9) },
+    ElementData { symbol : "Os", average_mass : 190.23, monoisotopic_mass : 191.961477, valences : &[], electronegativity : Some(2.2) },
+    ElementData { symbol : "Ir", average_mass : 192.217, monoisotopic_mass : 192.9629216, valences : &[], electronegativity : Some(2.20) },
+    ElementData { symbol : "Pt", average_mass : 195.084, monoisotopic_mass : 194.9647917, valences : &[], electronegativity : Some(2.28) },
+    ElementData { symbol : "Au", average_mass : 196.966569, monoisotopic_mass : 196.96656879, valences : &[], electronegativity : Some(2.54) },
+    ElementData { symbol : "Hg", average_mass : 200.592, monoisotopic_mass : 201.9706434, valences : &[], electronegativity : Some(2.00) },
+    ElementData { symbol : "Tl", average_mass : 204.38, monoisotopic_mass : 204.9744278, valences : &[1, 3], electronegativity : Some(1.62) },
+    ElementData { symbol : "Pb", average_mass : 207.2, monoisotopic_mass : 207.9766525, valences : &[2, 4], electronegativity : Some(2.33) },
+    ElementData { symbol : "Bi", average_mass : 208.98040, monoisotopic_mass : 208.9803991, valences : &[3, 5], electronegativity : Some(2.02) },
+    ElementData { symbol : "Po", average_mass : 209.0, monoisotopic_mass : 208.9824308, valences : &[2, 4, 6], electronegativity : Some(2.0) },
+    ElementData { symbol : "At", average_mass : 210.0, monoisotopic_mass : 209.9871479, valences : &[1, 3, 5, 7], electronegativity : Some(2.2) },
+    ElementData { symbol : "Rn", average_mass : 222.0, monoisotopic_mass : 222.0175782, valences : &[0], electronegativity : Some(2.2) },
+    ElementData { symbol : "Fr", average_mass : 223.0, monoisotopic_mass : 223.019736, valences : &[1], electronegativity : Some(0.7) },
+    ElementData { symbol : "Ra", average_mass : 226.0, monoisotopic_mass : 226.0254103, valences : &[2], electronegativity : Some(0.9) },
+    ElementData { symbol : "Ac", average_mass : 227.0, monoisotopic_mass : 227.0277523, valences : &[], electronegativity : Some(1.1) },
+    ElementData { symbol : "Th", average_mass : 232.0377, monoisotopic_mass : 232.0380558, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Pa", average_mass : 231.03588, monoisotopic_mass : 231.0358842, valences : &[], electronegativity : Some(1.5) },
+    ElementData { symbol : "U", average_mass : 238.02891, monoisotopic_mass : 238.0507884, valences : &[], electronegativity : Some(1.38) },
+    ElementData { symbol : "Np", average_mass : 237.0, monoisotopic_mass : 237.0481736, valences : &[], electronegativity : Some(1.36) },
+    ElementData { symbol : "Pu", average_mass : 244.0, monoisotopic_mass : 244.0642053, valences : &[], electronegativity : Some(1.28) },
+    ElementData { symbol : "Am", average_mass : 243.0, monoisotopic_mass : 243.0613813, valences : &[], electronegativity : Some(1.13) },
+    ElementData { symbol : "Cm", average_mass : 247.0, monoisotopic_mass : 247.0703541, valences : &[], electronegativity : Some(1.28) },
+    ElementData { symbol : "Bk", average_mass : 247.0, monoisotopic_mass : 247.0703073, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Cf", average_mass : 251.0, monoisotopic_mass : 251.0795886, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Es", average_mass : 252.0, monoisotopic_mass : 252.08298, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Fm", average_mass : 257.0, monoisotopic_mass : 257.0951061, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Md", average_mass : 258.0, monoisotopic_mass : 258.0984315, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "No", average_mass : 259.0, monoisotopic_mass : 259.10103, valences : &[], electronegativity : Some(1.3) },
+    ElementData { symbol : "Lr", average_mass : 262.0, monoisotopic_mass : 262.10961, valences : &[], electronegativity : None },
+    ElementData { symbol : "Rf", average_mass : 267.0, monoisotopic_mass : 267.12179, valences : &[], electronegativity : None },
+    ElementData { symbol : "Db", average_mass : 268.0, monoisotopic_mass : 268.12567, valences : &[], electronegativity : None },
+    ElementData { symbol : "Sg", average_mass : 269.0, monoisotopic_mass : 269.12863, valences : &[], electronegativity : None },
+    ElementData { symbol : "Bh", average_mass : 270.0, monoisotopic_mass : 270.13336, valences : &[], electronegativity : None },
+    ElementData { symbol : "Hs", average_mass : 269.0, monoisotopic_mass : 269.13375, valences : &[], electronegativity : None },
+    ElementData { symbol : "Mt", average_mass : 278.0, monoisotopic_mass : 278.15631, valences : &[], electronegativity : None },
+    ElementData { symbol : "Ds", average_mass : 281.0, monoisotopic_mass : 281.16451, valences : &[], electronegativity : None },
+    ElementData { symbol : "Rg", average_mass : 282.0, monoisotopic_mass : 282.16912, valences : &[], electronegativity : None },
+    ElementData { symbol : "Cn", average_mass : 285.0, monoisotopic_mass : 285.17712, valences : &[], electronegativity : None },
+    ElementData { symbol : "Nh", average_mass : 286.0, monoisotopic_mass : 286.18221, valences : &[], electronegativity : None },
+    ElementData { symbol : "Fl", average_mass : 289.0, monoisotopic_mass : 289.19042, valences : &[], electronegativity : None },
+    ElementData { symbol : "Mc", average_mass : 290.0, monoisotopic_mass : 290.19598, valences : &[], electronegativity : None },
+    ElementData { symbol : "Lv", average_mass : 293.0, monoisotopic_mass : 293.20449, valences : &[], electronegativity : None },
+    ElementData { symbol : "Ts", average_mass : 294.0, monoisotopic_mass : 294.21046, valences : &[], electronegativity : None },
+    ElementData { symbol : "Og", average_mass : 294.0, monoisotopic_mass : 294.21392, valences : &[], electronegativity : None },
+];
+
+impl Element {
+    // Returns the element with the symbol, symbols are case sensitive ("Co" is cobalt, "CO" is
+    // nothing)
+    pub fn from_symbol(symbol : &str) -> Option<Element> {
+        ELEMENT_DATA.iter().position(|x| x.symbol == symbol).map(|i| ALL_ELEMENTS[i])
+    }
+
+    // Returns the element with the atomic number, from 1 to 118
+    pub fn from_atomic_number(number : u32) -> Option<Element> {
+        if number == 0 {
+            return None
+        }
+        ALL_ELEMENTS.get(number as usize - 1).copied()
+    }
+
+    // Returns every element in order of atomic number
+    pub fn all() -> &'static [Element] {
+        &ALL_ELEMENTS
+    }
+
+    fn data(&self) -> &'static ElementData {
+        &ELEMENT_DATA[self.atomic_number() as usize - 1]
+    }
+
+    pub fn atomic_number(&self) -> u32 {
+        *self as u32
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.data().symbol
+    }
+
+    pub fn average_mass(&self) -> f64 {
+        self.data().average_mass
+    }
+
+    pub fn monoisotopic_mass(&self) -> f64 {
+        self.data().monoisotopic_mass
+    }
+
+    // The usual valences from lowest to highest, "N" is [3, 5]
+    pub fn default_valences(&self) -> &'static [i32] {
+        self.data().valences
+    }
+
+    pub fn electronegativity(&self) -> Option<f64> {
+        self.data().electronegativity
+    }
+
+    // F, Cl, Br, I, At and Ts, the elements an "X" query atom stands for
+    pub fn is_halogen(&self) -> bool {
+        matches!(self, Element::F | Element::Cl | Element::Br | Element::I | Element::At | Element::Ts)
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.symbol())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols() {
+        for (i, element) in Element::all().iter().enumerate() {
+            assert_eq!(element.atomic_number() as usize, i + 1);
+            assert_eq!(Element::from_symbol(element.symbol()), Some(*element));
+            assert_eq!(Element::from_atomic_number(i as u32 + 1), Some(*element));
+        }
+        assert_eq!(Element::from_symbol("Co"), Some(Element::Co));
+        assert_eq!(Element::from_symbol("CO"), None);
+        assert_eq!(Element::from_symbol("Xx"), None);
+        assert_eq!(Element::from_symbol("R"), None);
+        assert_eq!(Element::from_atomic_number(0), None);
+        assert_eq!(Element::from_atomic_number(119), None);
+        assert_eq!(Element::Og.atomic_number(), 118);
+    }
+
+    #[test]
+    fn test_element_data() {
+        assert_eq!(Element::C.average_mass(), 12.011);
+        assert_eq!(Element::C.monoisotopic_mass(), 12.0);
+        assert!((Element::Cl.average_mass() - 35.45).abs() < 1e-9);
+        assert!((Element::Cl.monoisotopic_mass() - 34.968852682).abs() < 1e-9);
+        assert_eq!(Element::N.default_valences(), &[3, 5]);
+        assert_eq!(Element::Fe.default_valences(), &[] as &[i32]);
+        assert_eq!(Element::F.electronegativity(), Some(3.98));
+        assert_eq!(Element::He.electronegativity(), None);
+        assert!(Element::Br.is_halogen());
+        assert!(!Element::O.is_halogen());
+
+        // the average and monoisotopic masses are never far apart
+        for element in Element::all() {
+            assert!(element.average_mass() > 0.0 && element.monoisotopic_mass() > 0.0);
+            assert!((element.average_mass() - element.monoisotopic_mass()).abs() < 3.0, "{}", element);
+        }
+    }
+}
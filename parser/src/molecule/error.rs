@@ -0,0 +1,180 @@
+/**
+ * Error types returned by the .mol, SMILES and SMARTS parsers
+ *
+ * Every .mol variant that comes from the contents of the file carries the 1-based line number
+ * and the text of the offending line, so a bad file in a batch can be tracked down.
+ **/
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum MolParseError {
+    // The file could not be read
+    Io(std::io::Error),
+    // The file ended before the 3 header lines and the counts line
+    TruncatedHeader { line : usize, text : String },
+    // The counts line (line 4) does not hold a valid atom and bond count
+    BadCountsLine { line : usize, text : String },
+    // An atom line is missing or does not have coordinates and an element symbol
+    BadAtomLine { line : usize, text : String },
+    // An atom line or atom list has a symbol that isn't an element or a query atom
+    UnknownElement { line : usize, text : String },
+    // A bond line is missing, is not made of integers, or bonds an atom to itself
+    BadBondLine { line : usize, text : String },
+    // A bond line describes a bond that was already set
+    DuplicateBond { line : usize, text : String },
+    // A bond or property line refers to an atom that is not in the atom block
+    AtomIndexOutOfRange { line : usize, text : String },
+    // A line in the properties block ("M  CHG", "M  ISO", ...) doesn't match its format
+    BadPropertyLine { line : usize, text : String },
+}
+
+impl fmt::Display for MolParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MolParseError::Io(e) => write!(f, "could not read mol file: {}", e),
+            MolParseError::TruncatedHeader { line, text } =>
+                write!(f, "line {}: mol file ended inside the header: {:?}", line, text),
+            MolParseError::BadCountsLine { line, text } =>
+                write!(f, "line {}: bad counts line: {:?}", line, text),
+            MolParseError::BadAtomLine { line, text } =>
+                write!(f, "line {}: bad atom line: {:?}", line, text),
+            MolParseError::UnknownElement { line, text } =>
+                write!(f, "line {}: unknown element symbol: {:?}", line, text),
+            MolParseError::BadBondLine { line, text } =>
+                write!(f, "line {}: bad bond line: {:?}", line, text),
+            MolParseError::DuplicateBond { line, text } =>
+                write!(f, "line {}: bond has already been set: {:?}", line, text),
+            MolParseError::AtomIndexOutOfRange { line, text } =>
+                write!(f, "line {}: refers to an atom that does not exist: {:?}", line, text),
+            MolParseError::BadPropertyLine { line, text } =>
+                write!(f, "line {}: bad property line: {:?}", line, text),
+        }
+    }
+}
+
+impl std::error::Error for MolParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MolParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MolParseError {
+    fn from(e : std::io::Error) -> Self {
+        MolParseError::Io(e)
+    }
+}
+
+// Error type returned by the SMILES parser
+// Every variant carries the 0-based character position in the SMILES string where it was found
+#[derive(Debug, PartialEq)]
+pub enum SmilesError {
+    // A character that can't start an atom, bond, branch or ring closure
+    UnexpectedCharacter { position : usize, character : char },
+    // A bracket atom that isn't closed or doesn't read as [isotope symbol chirality hcount charge class]
+    BadBracketAtom { position : usize },
+    // A bond, branch or ring closure with no atom before it, or a bond with no atom after it
+    MissingAtom { position : usize },
+    // A ")" with no open branch, an empty branch, or a "(" that is never closed
+    UnbalancedBranch { position : usize },
+    // A ring closure number that is opened but never closed
+    UnclosedRing { position : usize, ring : u32 },
+    // The two ends of a ring closure ask for different bonds, join an atom to itself, or join
+    // two atoms that are already bonded
+    BadRingClosure { position : usize },
+    // A bracket atom with a symbol that isn't an element
+    UnknownElement { position : usize, symbol : String },
+    // A "$" quadruple bond, which has no bond type in a Molecule
+    UnsupportedBond { position : usize },
+}
+
+impl fmt::Display for SmilesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SmilesError::UnexpectedCharacter { position, character } =>
+                write!(f, "position {}: unexpected character {:?} in SMILES", position, character),
+            SmilesError::BadBracketAtom { position } =>
+                write!(f, "position {}: bad bracket atom", position),
+            SmilesError::MissingAtom { position } =>
+                write!(f, "position {}: bond, branch or ring closure is missing an atom", position),
+            SmilesError::UnknownElement { position, symbol } =>
+                write!(f, "position {}: unknown element {:?}", position, symbol),
+            SmilesError::UnbalancedBranch { position } =>
+                write!(f, "position {}: unbalanced or empty branch", position),
+            SmilesError::UnclosedRing { position, ring } =>
+                write!(f, "position {}: ring {} is never closed", position, ring),
+            SmilesError::BadRingClosure { position } =>
+                write!(f, "position {}: bad ring closure", position),
+            SmilesError::UnsupportedBond { position } =>
+                write!(f, "position {}: quadruple bonds are not supported", position),
+        }
+    }
+}
+
+impl std::error::Error for SmilesError {}
+
+// Error type returned by the SMARTS parser
+// Every variant carries the 0-based character position in the SMARTS string where it was found
+#[derive(Debug, PartialEq)]
+pub enum SmartsError {
+    // A character that can't start an atom, bond, branch or ring closure
+    UnexpectedCharacter { position : usize, character : char },
+    // An atom or bond expression that is empty, isn't closed, or has an operator with nothing on
+    // one side of it
+    BadExpression { position : usize },
+    // A primitive inside an atom or bond expression that isn't known
+    UnknownPrimitive { position : usize, character : char },
+    // A bracket atom with a symbol or atomic number that isn't an element
+    UnknownElement { position : usize, symbol : String },
+    // A bond, branch or ring closure with no atom before it, or a bond with no atom after it
+    MissingAtom { position : usize },
+    // A ")" with no open branch, an empty branch, or a "(" that is never closed
+    UnbalancedBranch { position : usize },
+    // A ring closure number that is opened but never closed
+    UnclosedRing { position : usize, ring : u32 },
+    // A ring closure that joins an atom to itself or two atoms that are already bonded
+    BadRingClosure { position : usize },
+}
+
+impl fmt::Display for SmartsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SmartsError::UnexpectedCharacter { position, character } =>
+                write!(f, "position {}: unexpected character {:?} in SMARTS", position, character),
+            SmartsError::BadExpression { position } =>
+                write!(f, "position {}: bad atom or bond expression", position),
+            SmartsError::UnknownPrimitive { position, character } =>
+                write!(f, "position {}: unknown primitive {:?}", position, character),
+            SmartsError::UnknownElement { position, symbol } =>
+                write!(f, "position {}: unknown element {:?}", position, symbol),
+            SmartsError::MissingAtom { position } =>
+                write!(f, "position {}: bond, branch or ring closure is missing an atom", position),
+            SmartsError::UnbalancedBranch { position } =>
+                write!(f, "position {}: unbalanced or empty branch", position),
+            SmartsError::UnclosedRing { position, ring } =>
+                write!(f, "position {}: ring {} is never closed", position, ring),
+            SmartsError::BadRingClosure { position } =>
+                write!(f, "position {}: bad ring closure", position),
+        }
+    }
+}
+
+impl std::error::Error for SmartsError {}
+
+// Error type returned when aromatic bonds can't be given alternating single and double bonds
+// Carries the atoms that were left without a double bond
+#[derive(Debug, PartialEq)]
+pub struct KekulizeError {
+    pub atoms : Vec<usize>,
+}
+
+impl fmt::Display for KekulizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't kekulize, atoms {:?} can't all get a double bond", self.atoms)
+    }
+}
+
+impl std::error::Error for KekulizeError {}
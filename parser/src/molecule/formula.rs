@@ -0,0 +1,175 @@
+/**
+ * Molecular formulas and masses
+ *
+ * Formulas are written in Hill order (carbon, hydrogen, then the rest alphabetically, or all
+ * alphabetically if there is no carbon) and count the implicit hydrogens. Query atoms have no
+ * element, so they are left out of the formula and the masses.
+ **/
+
+use std::collections::BTreeMap;
+
+use crate::molecule::molecule::Molecule;
+use crate::molecule::element::Element;
+use crate::molecule::valence::implicit_hydrogens;
+
+// The mass difference between neighbouring isotopes, 13C - 12C
+static NEUTRON_MASS_STEP : f64 = 1.0033548;
+
+// Counts the atoms of each element, including implicit hydrogens
+pub fn element_counts(molecule : &Molecule) -> BTreeMap<Element, u32> {
+    let mut counts : BTreeMap<Element, u32> = BTreeMap::new();
+    for i in 0..molecule.atoms.len() {
+        if let Some(element) = molecule.atoms[i].element() {
+            *counts.entry(element).or_insert(0) += 1;
+        }
+        let hydrogens = implicit_hydrogens(molecule, i);
+        if hydrogens > 0 {
+            *counts.entry(Element::H).or_insert(0) += hydrogens as u32;
+        }
+    }
+    counts
+}
+
+// Writes element counts in Hill order, "C7H6O2"
+pub fn hill_formula(counts : &BTreeMap<Element, u32>) -> String {
+    let mut elements : Vec<(&str, u32)> = counts.iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(element, count)| (element.symbol(), *count))
+        .collect();
+    let has_carbon = counts.get(&Element::C).is_some_and(|x| *x > 0);
+    elements.sort_by_key(|(symbol, _)| {
+        match *symbol {
+            "C" if has_carbon => (0, *symbol),
+            "H" if has_carbon => (1, *symbol),
+            _ => (2, *symbol),
+        }
+    });
+    let mut out = String::new();
+    for (symbol, count) in elements {
+        out += symbol;
+        if count > 1 {
+            out += &count.to_string();
+        }
+    }
+    out
+}
+
+// Reads a formula like "C7H6O2", or the spaced out form used in JCAMP-DX ##MOLFORM= headers,
+// "C 10 H 13 N O" or "C10 H13 N O"
+// Returns None if the formula has an unknown element or anything that isn't an element and count
+pub fn parse_formula(formula : &str) -> Option<BTreeMap<Element, u32>> {
+    let chars : Vec<char> = formula.chars().filter(|x| !x.is_whitespace()).collect();
+    let mut counts : BTreeMap<Element, u32> = BTreeMap::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_uppercase() {
+            return None
+        }
+        let mut symbol = chars[i].to_string();
+        i += 1;
+        if i < chars.len() && chars[i].is_ascii_lowercase() {
+            symbol.push(chars[i]);
+            i += 1;
+        }
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let count = if start == i { 1 } else { chars[start..i].iter().collect::<String>().parse().ok()? };
+        *counts.entry(Element::from_symbol(&symbol)?).or_insert(0) += count;
+    }
+    Some(counts)
+}
+
+// The mass of a single atom, using the isotope if one is set
+fn atom_mass(molecule : &Molecule, atom : usize, element : Element, average : bool) -> f64 {
+    let isotope = molecule.isotopes[atom];
+    let monoisotopic = element.monoisotopic_mass();
+    if isotope == 0 {
+        return if average { element.average_mass() } else { monoisotopic }
+    }
+    // there is no table of every isotope, so labelled atoms are estimated from the most
+    // abundant isotope, which is within a few thousandths of the real mass
+    monoisotopic + (isotope as f64 - monoisotopic.round()) * NEUTRON_MASS_STEP
+}
+
+fn molecule_mass(molecule : &Molecule, average : bool) -> f64 {
+    let mut mass = 0.0;
+    let hydrogen = if average { Element::H.average_mass() } else { Element::H.monoisotopic_mass() };
+    for i in 0..molecule.atoms.len() {
+        if let Some(element) = molecule.atoms[i].element() {
+            mass += atom_mass(molecule, i, element, average);
+        }
+        mass += implicit_hydrogens(molecule, i) as f64 * hydrogen;
+    }
+    mass
+}
+
+// The molecular weight from the standard atomic weights, in g/mol
+pub fn average_mass(molecule : &Molecule) -> f64 {
+    molecule_mass(molecule, true)
+}
+
+// The exact mass of the molecule made of only the most abundant isotope of each element
+pub fn monoisotopic_mass(molecule : &Molecule) -> f64 {
+    molecule_mass(molecule, false)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::parse_mol;
+    use crate::molecule::smiles::parse_smiles;
+
+    #[test]
+    fn test_hill_formula() {
+        let formula = |x : &str| hill_formula(&element_counts(&parse_smiles(x).unwrap()));
+        assert_eq!(formula("OC(=O)c1ccccc1"), "C7H6O2");
+        assert_eq!(formula("O"), "H2O");
+        assert_eq!(formula("[Na+].[Cl-]"), "ClNa");
+        assert_eq!(formula("C(Cl)(Cl)(Cl)Br"), "CBrCl3");
+        assert_eq!(formula("[2H]C([2H])([2H])[2H]"), "CH4");
+    }
+
+    #[test]
+    fn test_parse_formula() {
+        let benzoic = parse_formula("C7H6O2").unwrap();
+        assert_eq!(parse_formula("C 7 H 6 O 2"), Some(benzoic.clone()));
+        assert_eq!(parse_formula("C7 H6 O2"), Some(benzoic));
+        assert_eq!(parse_formula("Na Cl").unwrap().len(), 2);
+        assert_eq!(parse_formula("[ C H 2 C H( C 6 H 5 )] n"), None);
+        assert_eq!(parse_formula("C2 Xx"), None);
+    }
+
+    #[test]
+    fn test_masses() {
+        let water = parse_smiles("O").unwrap();
+        assert!((average_mass(&water) - 18.015).abs() < 1e-3);
+        assert!((monoisotopic_mass(&water) - 18.010565).abs() < 1e-6);
+
+        let benzoic = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap();
+        assert!((average_mass(&benzoic) - 122.123).abs() < 1e-2);
+        assert!((monoisotopic_mass(&benzoic) - 122.036779).abs() < 1e-5);
+
+        // heavy water is close to its real mass of 20.0231
+        let heavy_water = parse_smiles("[2H]O[2H]").unwrap();
+        assert!((monoisotopic_mass(&heavy_water) - 20.0231).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_formulas_match_jdx() {
+        // the mol files against the ##MOLFORM= of the spectrum of the same compound
+        let pairs = [
+            ("Benzeneacetamide, N,N-dimethyl-", "C 10 H 13 N O"),
+            ("Benzeneacetamide, «alpha»-amino-", "C8 H10 N2 O"),
+            ("Benzeneacetamide, «alpha»-ethyl-", "C10 H13 N O"),
+            ("Methane", "C H4"),
+        ];
+        for (name, molform) in pairs.iter() {
+            let molecule = parse_mol(&(TEST_DIR.to_owned() + name + ".mol")).unwrap();
+            assert_eq!(Some(element_counts(&molecule)), parse_formula(molform), "{}", name);
+        }
+    }
+}
@@ -1,5 +1,16 @@
 // Calls molecule into the scope
 pub mod molecule;
+pub mod element;
 pub mod parser;
+pub mod error;
+pub mod sdf;
+pub mod v3000;
+pub mod writer;
+pub mod smiles;
+pub mod smiles_writer;
+pub mod valence;
+pub mod formula;
+pub mod rings;
+pub mod aromaticity;
 
 extern crate nom;
\ No newline at end of file
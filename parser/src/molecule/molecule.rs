@@ -1,91 +1,486 @@
 // Structs related to instatiating and modifying molecules, represented by 
-// a symmetrical edge matrix representing bonds between atoms.
+// an adjacency list of the bonds between atoms.
+
+use crate::molecule::element::Element;
+
+#[derive(Clone)]
 pub struct Molecule {
-    // the edge matrix
-    matrix : Matrix,
+    // the name from the first line of the mol block, empty for molecules made in code
+    pub name : String,
+    // the bonds of each atom
+    bonds : Bonds,
     // the vector key storing the atom types
-    pub atoms : Vec<String>,
+    pub atoms : Vec<Atom>,
+    // the formal charge of each atom
+    pub charges : Vec<i32>,
+    // the isotope mass number of each atom, 0 means the natural abundance mix
+    pub isotopes : Vec<i32>,
+    // the radical state of each atom, using the molfile values:
+    // 0 none, 1 singlet, 2 doublet, 3 triplet
+    pub radicals : Vec<i32>,
+    // the number of hydrogens on each atom that aren't atoms in the molecule, as written in a
+    // SMILES bracket atom. None when the hydrogens are left to the atom's valence
+    pub hydrogens : Vec<Option<i32>>,
+    // the single bonds written with "/" or "\" in SMILES, as (from atom, to atom, direction)
+    pub bond_directions : Vec<(usize, usize, BondDirection)>,
+    // the x, y, z position of each atom, z is 0 for 2d drawings
+    coordinates : Vec<[f32; 3]>,
+}
+
+// An atom in a Molecule, either an element or a query atom that stands for more than one element
+#[derive(Debug, PartialEq, Clone)]
+pub enum Atom {
+    Element(Element),
+    Query(QueryAtom),
+}
+
+// Atoms in functional group patterns and query .mol files that match more than one element
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryAtom {
+    // "R" in the functional groups, a carbon, a hydrogen, or no atom at all
+    R,
+    // "A" in a .mol file or "*" in SMILES, any atom
+    Any,
+    // "Q" in a .mol file, any atom that isn't carbon or hydrogen
+    Heteroatom,
+    // "X", any halogen
+    Halogen,
+    // "L" in a .mol file, one of the elements of an atom list ("M  ALS")
+    List(AtomList),
+}
+
+// A query atom that matches any of the elements, or any element that isn't one of them if
+// exclude is set
+#[derive(Debug, PartialEq, Clone)]
+pub struct AtomList {
+    pub exclude : bool,
+    pub elements : Vec<Element>,
+}
+
+impl Atom {
+    // Reads an element symbol, or one of the query atom symbols "R", "A", "*", "Q", "X" and "L"
+    // "L" is an empty atom list until the "M  ALS" line for it is read
+    pub fn from_symbol(symbol : &str) -> Option<Atom> {
+        match symbol {
+            "R" => Some(Atom::Query(QueryAtom::R)),
+            "A" | "*" => Some(Atom::Query(QueryAtom::Any)),
+            "Q" => Some(Atom::Query(QueryAtom::Heteroatom)),
+            "X" => Some(Atom::Query(QueryAtom::Halogen)),
+            "L" => Some(Atom::Query(QueryAtom::List(AtomList { exclude : false, elements : Vec::new() }))),
+            _ => Element::from_symbol(symbol).map(Atom::Element),
+        }
+    }
+
+    // Returns the element, None for query atoms
+    pub fn element(&self) -> Option<Element> {
+        match self {
+            Atom::Element(x) => Some(*x),
+            Atom::Query(_) => None,
+        }
+    }
+
+    pub fn is_element(&self, element : Element) -> bool {
+        *self == Atom::Element(element)
+    }
+
+    // The symbol as it is written in a .mol file
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Atom::Element(x) => x.symbol(),
+            Atom::Query(QueryAtom::R) => "R",
+            Atom::Query(QueryAtom::Any) => "A",
+            Atom::Query(QueryAtom::Heteroatom) => "Q",
+            Atom::Query(QueryAtom::Halogen) => "X",
+            Atom::Query(QueryAtom::List(_)) => "L",
+        }
+    }
+
+    // Returns true if this atom, as part of a pattern, matches the atom of a molecule
+    // Query atoms in the molecule are only matched by the same query atom
+    pub fn matches(&self, atom : &Atom) -> bool {
+        let element = match atom {
+            Atom::Element(x) => *x,
+            Atom::Query(_) => return self == atom,
+        };
+        match self {
+            Atom::Element(x) => *x == element,
+            Atom::Query(QueryAtom::R) => element == Element::C || element == Element::H,
+            Atom::Query(QueryAtom::Any) => true,
+            Atom::Query(QueryAtom::Heteroatom) => element != Element::C && element != Element::H,
+            Atom::Query(QueryAtom::Halogen) => element.is_halogen(),
+            Atom::Query(QueryAtom::List(list)) => list.elements.contains(&element) != list.exclude,
+        }
+    }
+}
+
+impl From<Element> for Atom {
+    fn from(element : Element) -> Atom {
+        Atom::Element(element)
+    }
+}
+
+impl std::fmt::Display for Atom {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.pad(self.symbol())
+    }
+}
+
+// The direction of a single bond next to a double bond, "/" is Up and "\" is Down
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BondDirection {
+    Up,
+    Down,
 }
 
 
 impl Molecule {
-    // Instantiate a new Molecule
+    // Instantiate a new Molecule from element and query atom symbols
+    // Panics on a symbol that isn't either, use from_atoms for input that has not been checked
     pub fn new(atoms : Vec<&str>) -> Molecule {
         let atoms = {
-            let mut out:Vec<String> = Vec::new();
-            for element in atoms {
-                out.push(String::from(element));
+            let mut out : Vec<Atom> = Vec::new();
+            for symbol in atoms {
+                match Atom::from_symbol(symbol) {
+                    Some(atom) => out.push(atom),
+                    None => panic!("unknown element {:?}!", symbol),
+                }
             }
             out
         };
+        Molecule::from_atoms(atoms)
+    }
 
+    // Instantiate a new Molecule with no bonds
+    pub fn from_atoms(atoms : Vec<Atom>) -> Molecule {
         Molecule {
-            matrix : Matrix::new(atoms.len() as usize),
+            name : String::new(),
+            bonds : Bonds::new(atoms.len()),
+            charges : vec![0; atoms.len()],
+            isotopes : vec![0; atoms.len()],
+            radicals : vec![0; atoms.len()],
+            hydrogens : vec![None; atoms.len()],
+            bond_directions : Vec::new(),
+            coordinates : vec![[0.0; 3]; atoms.len()],
             atoms
         }
     }
 
     // Add a single bond between two atoms in the molecule
-    // returns a happy string if the new bond was valid and recorded,
-    // returns an error string if the new bond was invalid, (in an occupied location)
+    // Panics if the bond is invalid, use try_add_bond for input that has not been checked
     pub fn add_bond(&mut self, x : usize, y : usize, z : i32) {
-        if self.matrix.get(x as usize, y as usize) != 0 {
-            panic!("{}", "bond has already been set!")
-        } else {
-            self.matrix.set(x, y, z);
+        if let Err(e) = self.try_add_bond(x, y, z) {
+            panic!("{}", e)
         }
     }
 
-    pub fn to_string(&self) -> String {
-        let mut out = String::new();
-        for a in &self.atoms {
-            out += &a.to_string();
+    // Add a single bond between two atoms in the molecule
+    // returns Ok if the new bond was valid and recorded,
+    // returns a BondError if the new bond was invalid (in an occupied location, between an atom
+    // and itself, or to an atom that doesn't exist)
+    pub fn try_add_bond(&mut self, x : usize, y : usize, z : i32) -> Result<(), BondError> {
+        if x >= self.atoms.len() || y >= self.atoms.len() {
+            return Err(BondError::OutOfRange)
         }
-        out += "\n";
-        out += &self.matrix.to_string();
-        out
+        if x == y {
+            return Err(BondError::SameAtom)
+        }
+        if self.bonds.get(x, y) != 0 {
+            return Err(BondError::AlreadySet)
+        }
+        self.bonds.set(x, y, z);
+        Ok(())
+    }
+
+    // Changes the type of a bond between two atoms, a bond type of 0 removes the bond
+    pub fn set_bond(&mut self, x : usize, y : usize, z : i32) {
+        self.bonds.set(x, y, z);
+    }
+
+    // Adds an atom with no bonds to the end of the molecule, returns its index
+    pub fn add_atom(&mut self, atom : Atom) -> usize {
+        self.atoms.push(atom);
+        self.charges.push(0);
+        self.isotopes.push(0);
+        self.radicals.push(0);
+        self.hydrogens.push(None);
+        self.coordinates.push([0.0; 3]);
+        self.bonds.grow(self.atoms.len());
+        self.atoms.len() - 1
+    }
+
+    // Removes atoms and their bonds, the remaining atoms keep their order
+    pub fn remove_atoms(&mut self, remove : &[usize]) {
+        let mut removed = vec![false; self.atoms.len()];
+        for x in remove {
+            if let Some(x) = removed.get_mut(*x) {
+                *x = true;
+            }
+        }
+        let keep : Vec<usize> = (0..self.atoms.len()).filter(|x| !removed[*x]).collect();
+        let mut new_index : Vec<Option<usize>> = vec![None; self.atoms.len()];
+        for (new, old) in keep.iter().enumerate() {
+            new_index[*old] = Some(new);
+        }
+
+        self.atoms = keep.iter().map(|x| self.atoms[*x].clone()).collect();
+        self.charges = keep.iter().map(|x| self.charges[*x]).collect();
+        self.isotopes = keep.iter().map(|x| self.isotopes[*x]).collect();
+        self.radicals = keep.iter().map(|x| self.radicals[*x]).collect();
+        self.hydrogens = keep.iter().map(|x| self.hydrogens[*x]).collect();
+        self.coordinates = keep.iter().map(|x| self.coordinates[*x]).collect();
+        self.bonds.keep(&keep);
+        self.bond_directions = self.bond_directions.iter()
+            .filter_map(|(x, y, direction)| Some((new_index[*x]?, new_index[*y]?, *direction)))
+            .collect();
+    }
+
+    // The number of hydrogens on an atom that are atoms in the molecule
+    pub fn explicit_hydrogens(&self, atom : usize) -> i32 {
+        self.neighbours(atom).filter(|(x, _)| self.atoms[*x].is_element(Element::H)).count() as i32
+    }
+
+    // The number of hydrogens on an atom, whether or not they are atoms in the molecule
+    pub fn total_hydrogens(&self, atom : usize) -> i32 {
+        self.implicit_hydrogens(atom) + self.explicit_hydrogens(atom)
+    }
+
+    // Turns every implicit hydrogen into an atom bonded to its parent
+    pub fn add_explicit_hydrogens(&mut self) {
+        crate::molecule::valence::add_explicit_hydrogens(self)
     }
 
-    // Get a bond from the matrix
+    // Turns hydrogen atoms back into implicit hydrogens on their parent, hydrogens that are
+    // needed to describe the molecule (isotopes, charges, bridges, H2) are kept
+    // Returns the index each remaining atom had before
+    pub fn remove_explicit_hydrogens(&mut self) -> Vec<usize> {
+        crate::molecule::valence::remove_explicit_hydrogens(self)
+    }
+
+    // Get the bond type between two atoms, 0 if they aren't bonded
     pub fn get(&self, x : usize, y : usize) -> i32 {
-        self.matrix.get(x as usize, y as usize)
+        self.bonds.get(x, y)
+    }
+
+    // Returns the (neighbour, bond type) of every bond of an atom, in order of the neighbours
+    pub fn neighbours(&self, atom : usize) -> impl Iterator<Item = (usize, i32)> + '_ {
+        self.bonds.neighbours(atom).iter().copied()
+    }
+
+    // The number of atoms bonded to an atom
+    pub fn degree(&self, atom : usize) -> usize {
+        self.bonds.neighbours(atom).len()
+    }
+
+    // Returns the bonds as a dense matrix of bond types, 0 where atoms aren't bonded
+    // The matrix is built on every call, use neighbours to walk the bonds
+    pub fn get_matrix(&self) -> Vec<Vec<i32>> {
+        self.bonds.to_matrix()
+    }
+
+    // Returns every bond as (atom1, atom2, bond type) with atom1 < atom2
+    pub fn get_bonds(&self) -> Vec<(usize, usize, i32)> {
+        let mut out : Vec<(usize, usize, i32)> = Vec::new();
+        for x in 0..self.atoms.len() {
+            for (y, bond) in self.neighbours(x) {
+                if y > x {
+                    out.push((x, y, bond));
+                }
+            }
+        }
+        out
+    }
+
+    // Writes the molecule as a SMILES string
+    // The canonical string is the same for every atom order, so it can be used as a key
+    pub fn to_smiles(&self, canonical : bool) -> String {
+        crate::molecule::smiles_writer::write_smiles(self, canonical)
+    }
+
+    // The number of hydrogens on an atom that aren't atoms in the molecule, from the valence of
+    // the atom, or the hydrogen count of a SMILES bracket atom
+    pub fn implicit_hydrogens(&self, atom : usize) -> i32 {
+        crate::molecule::valence::implicit_hydrogens(self, atom)
     }
 
-    // Returns the matrix
-    pub fn get_matrix(&self) -> &Vec<Vec<i32>> {
-        &self.matrix.matrix
+    // The molecular formula in Hill order, counting implicit hydrogens, "C7H6O2"
+    pub fn formula(&self) -> String {
+        let formula = crate::molecule::formula::element_counts(self);
+        crate::molecule::formula::hill_formula(&formula)
+    }
+
+    // The molecular weight in g/mol, counting implicit hydrogens
+    pub fn average_mass(&self) -> f64 {
+        crate::molecule::formula::average_mass(self)
+    }
+
+    // The exact mass of the most abundant isotopes, counting implicit hydrogens
+    pub fn monoisotopic_mass(&self) -> f64 {
+        crate::molecule::formula::monoisotopic_mass(self)
+    }
+
+    // The smallest set of smallest rings, with ring membership and fused ring systems
+    pub fn rings(&self) -> crate::molecule::rings::RingInfo {
+        crate::molecule::rings::find_rings(self)
+    }
+
+    // The order of a bond, 1.5 for aromatic bonds and 1 for the query bond types
+    pub fn bond_order(&self, x : usize, y : usize) -> f64 {
+        match self.get(x, y) {
+            0 => 0.0,
+            bond @ 1..=3 => bond as f64,
+            4 => 1.5,
+            _ => 1.0,
+        }
+    }
+
+    // Whether each atom is in a ring that is aromatic by Hückel's rule
+    pub fn aromatic_atoms(&self) -> Vec<bool> {
+        crate::molecule::aromaticity::aromatic_atoms(self)
+    }
+
+    // Sets the bonds of aromatic rings to the aromatic bond type, so every Kekulé structure of
+    // the molecule has the same bonds
+    pub fn aromatize(&mut self) {
+        crate::molecule::aromaticity::aromatize(self)
+    }
+
+    // Replaces aromatic bonds with alternating single and double bonds
+    pub fn kekulize(&mut self) -> Result<(), crate::molecule::error::KekulizeError> {
+        crate::molecule::aromaticity::kekulize(self)
+    }
+
+    // Returns the x, y, z coordinates of every atom
+    pub fn get_coordinates(&self) -> &Vec<[f32; 3]> {
+        &self.coordinates
+    }
+
+    // Returns the x, y, z coordinates of a single atom
+    pub fn get_coordinate(&self, atom : usize) -> [f32; 3] {
+        self.coordinates[atom]
+    }
+
+    // Moves a single atom to x, y, z
+    pub fn set_coordinate(&mut self, atom : usize, coordinate : [f32; 3]) {
+        self.coordinates[atom] = coordinate;
     }
 }
 
-struct Matrix {
-    matrix : Vec<Vec<i32>>,
+// The atom symbols on one line, then the adjacency matrix
+impl std::fmt::Display for Molecule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for a in &self.atoms {
+            write!(f, "{}", a)?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.bonds)
+    }
+}
+
+// The reasons a bond can't be added to a Molecule
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BondError {
+    // one of the atoms is not in the molecule
+    OutOfRange,
+    // a bond can't be described between an atom and itself
+    SameAtom,
+    // there is already a bond between the two atoms
+    AlreadySet,
 }
-impl Matrix {
-    // initializes an empty 2d array. 
-    pub fn new(size : usize) -> Matrix{
-        Matrix {matrix : vec![vec![0; size as usize]; size] }
+impl std::fmt::Display for BondError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BondError::OutOfRange => write!(f, "bond atom is out of range!"),
+            BondError::SameAtom => write!(f, "bond can't join an atom to itself!"),
+            BondError::AlreadySet => write!(f, "bond has already been set!"),
+        }
+    }
+}
+
+// The bonds of a molecule as an adjacency list, every atom has the (neighbour, bond type) of each
+// of its bonds, sorted by neighbour. Molecules only have a few bonds per atom, so this takes
+// memory in proportion to the bonds instead of the square of the atoms
+#[derive(Clone)]
+struct Bonds {
+    neighbours : Vec<Vec<(usize, i32)>>,
+}
+impl Bonds {
+    // initializes atoms with no bonds
+    pub fn new(size : usize) -> Bonds {
+        Bonds { neighbours : vec![Vec::new(); size] }
+    }
+
+    // sets the bond between two atoms on both of their lists, 0 removes the bond
+    pub fn set(&mut self, x : usize, y : usize, amount : i32) {
+        self.set_one(x, y, amount);
+        self.set_one(y, x, amount);
+    }
+
+    fn set_one(&mut self, x : usize, y : usize, amount : i32) {
+        let list = &mut self.neighbours[x];
+        match list.binary_search_by_key(&y, |(atom, _)| *atom) {
+            Ok(i) if amount == 0 => { list.remove(i); },
+            Ok(i) => list[i].1 = amount,
+            Err(i) if amount != 0 => list.insert(i, (y, amount)),
+            Err(_) => (),
+        }
+    }
+
+    pub fn get(&self, x : usize, y : usize) -> i32 {
+        let list = &self.neighbours[x];
+        match list.binary_search_by_key(&y, |(atom, _)| *atom) {
+            Ok(i) => list[i].1,
+            Err(_) => 0,
+        }
+    }
+
+    pub fn neighbours(&self, x : usize) -> &[(usize, i32)] {
+        &self.neighbours[x]
+    }
+
+    // adds atoms with no bonds up to the new size
+    pub fn grow(&mut self, size : usize) {
+        self.neighbours.resize(size, Vec::new());
     }
 
-    // set a spot in the matrix (add a new bond between atoms)
-    // since this represents a unidirectional graph, the matrix has to be symmetrical
-    pub fn set(&mut self, x: usize, y: usize, amount: i32 ) {
-        self.matrix[x][y] = amount;
-        self.matrix[y][x] = amount;
+    // keeps only the atoms in keep, in that order, and the bonds between them
+    pub fn keep(&mut self, keep : &[usize]) {
+        let mut new_index = vec![None; self.neighbours.len()];
+        for (new, old) in keep.iter().enumerate() {
+            new_index[*old] = Some(new);
+        }
+        self.neighbours = keep.iter()
+            .map(|x| self.neighbours[*x].iter()
+                .filter_map(|(y, bond)| Some((new_index[*y]?, *bond)))
+                .collect())
+            .collect();
+        // the order of keep could be different from the old order
+        for list in self.neighbours.iter_mut() {
+            list.sort_unstable();
+        }
     }
 
-    pub fn get(&self, x: usize, y : usize) -> i32 {
-        self.matrix[x][y]
+    // the dense matrix of bond types, 0 where there is no bond
+    pub fn to_matrix(&self) -> Vec<Vec<i32>> {
+        let mut matrix = vec![vec![0; self.neighbours.len()]; self.neighbours.len()];
+        for (x, list) in self.neighbours.iter().enumerate() {
+            for (y, bond) in list {
+                matrix[x][*y] = *bond;
+            }
+        }
+        matrix
     }
+}
 
-    pub fn to_string(&self) -> String {
-        let mut out = String::new();
-        for x in 0..self.matrix.len() {
-            for y in 0..self.matrix[x].len() {
-                out += &self.matrix[x][y].to_string();
+// The adjacency matrix, a row of bond types for every atom
+impl std::fmt::Display for Bonds {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for row in self.to_matrix() {
+            for bond in row {
+                write!(f, "{}", bond)?;
             }
-            out += "\n"
+            writeln!(f)?;
         }
-        out
+        Ok(())
     }
 }
 
@@ -101,36 +496,125 @@ mod tests {
         let mut my_mol = Molecule::new(vec![
             "C", "O", "C", "K"]);
 
-        println!("{}", my_mol.to_string());
+        println!("{}", my_mol);
 
         // adds a double bond between 'C' and 'O'
         let _result = my_mol.add_bond(1, 2, 2);
 
         // attempting to add another bond will result in an error
-        //let _result = my_mol.add_bond(1, 2, 1);
+        assert_eq!(my_mol.try_add_bond(1, 2, 1), Err(BondError::AlreadySet));
+        assert_eq!(my_mol.try_add_bond(2, 2, 1), Err(BondError::SameAtom));
+        assert_eq!(my_mol.try_add_bond(1, 4, 1), Err(BondError::OutOfRange));
     }
+    #[test]
+    fn test_atoms() {
+        let my_mol = Molecule::new(vec!["C", "Cl", "R", "X", "*"]);
+        assert_eq!(my_mol.atoms[1], Atom::Element(Element::Cl));
+        assert_eq!(my_mol.atoms[2], Atom::Query(QueryAtom::R));
+        assert_eq!(my_mol.atoms[4], Atom::Query(QueryAtom::Any));
+        assert_eq!(Atom::from_symbol("Xx"), None);
+        assert_eq!(my_mol.to_string().lines().next(), Some("CClRXA"));
+
+        let chlorine = Atom::Element(Element::Cl);
+        assert!(my_mol.atoms[1].matches(&chlorine));
+        assert!(my_mol.atoms[3].matches(&chlorine));
+        assert!(!my_mol.atoms[3].matches(&Atom::Element(Element::O)));
+        assert!(my_mol.atoms[2].matches(&Atom::Element(Element::H)));
+        assert!(!my_mol.atoms[2].matches(&chlorine));
+        assert!(Atom::Query(QueryAtom::Heteroatom).matches(&chlorine));
+        let list = Atom::Query(QueryAtom::List(AtomList { exclude : true, elements : vec![Element::O, Element::S] }));
+        assert!(list.matches(&chlorine));
+        assert!(!list.matches(&Atom::Element(Element::S)));
+        // an element never matches a query atom in the molecule
+        assert!(!chlorine.matches(&Atom::Query(QueryAtom::Halogen)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unknown_element() {
+        Molecule::new(vec!["C", "Xx"]);
+    }
+
     #[test]
     fn test_add_bond() {
         let mut my_mol = Molecule::new(vec![
             "C", "O", "C","K"]);
-        println!("{}", my_mol.to_string());
+        println!("{}", my_mol);
         my_mol.add_bond(1, 2, 1);
-        println!("{}", my_mol.to_string());
+        println!("{}", my_mol);
         my_mol.add_bond(3, 2, 1);
-        println!("{}", my_mol.to_string());
+        println!("{}", my_mol);
         my_mol.add_bond(3, 1, 2);
-        println!("{}", my_mol.to_string());
+        println!("{}", my_mol);
+        assert_eq!(my_mol.get_bonds(), vec![(1, 2, 1), (1, 3, 2), (2, 3, 1)]);
+    }
+    #[test]
+    fn test_coordinates() {
+        let mut my_mol = Molecule::new(vec!["C", "O"]);
+        assert_eq!(my_mol.get_coordinate(1), [0.0, 0.0, 0.0]);
+        my_mol.set_coordinate(1, [1.2, -0.5, 0.0]);
+        assert_eq!(my_mol.get_coordinates(), &vec![[0.0, 0.0, 0.0], [1.2, -0.5, 0.0]]);
+    }
+    #[test]
+    fn test_add_and_remove_atoms() {
+        let mut my_mol = Molecule::new(vec!["C", "O", "N"]);
+        my_mol.add_bond(0, 1, 1);
+        my_mol.add_bond(1, 2, 2);
+        my_mol.bond_directions.push((1, 2, BondDirection::Up));
+        let cl = my_mol.add_atom(Atom::Element(Element::Cl));
+        assert_eq!(cl, 3);
+        my_mol.add_bond(2, 3, 1);
+        my_mol.charges[2] = 1;
+
+        my_mol.remove_atoms(&[0]);
+        assert_eq!(my_mol.atoms, vec![Atom::Element(Element::O), Atom::Element(Element::N), Atom::Element(Element::Cl)]);
+        assert_eq!(my_mol.get_bonds(), vec![(0, 1, 2), (1, 2, 1)]);
+        assert_eq!(my_mol.charges, vec![0, 1, 0]);
+        assert_eq!(my_mol.bond_directions, vec![(0, 1, BondDirection::Up)]);
+        assert_eq!(my_mol.get_coordinates().len(), 3);
     }
     #[test]
-    fn test_matrix_methods() {
-        let mut matrix = Matrix::new(10);
-        println!("{}", matrix.to_string());
+    fn test_bonds_methods() {
+        let mut bonds = Bonds::new(10);
+        println!("{}", bonds);
 
-        matrix.set(1,5,1);
+        bonds.set(1,5,1);
+        bonds.set(1,3,2);
 
-        println!("{}", matrix.to_string());
+        println!("{}", bonds);
 
-        assert_eq!(matrix.matrix[1][5], 1);
-        assert_eq!(matrix.matrix[5][1], 1);
+        assert_eq!(bonds.get(1, 5), 1);
+        assert_eq!(bonds.get(5, 1), 1);
+        assert_eq!(bonds.neighbours(1), &[(3, 2), (5, 1)]);
+        assert_eq!(bonds.to_matrix()[3][1], 2);
+
+        bonds.set(1, 3, 0);
+        assert_eq!(bonds.get(3, 1), 0);
+        assert_eq!(bonds.neighbours(3), &[]);
+        bonds.keep(&[5, 1]);
+        assert_eq!(bonds.neighbours(0), &[(1, 1)]);
+    }
+    #[test]
+    fn test_large_molecule() {
+        // a dense matrix of this many atoms would take 160 GB
+        let size = 200000;
+        let mut my_mol = Molecule::from_atoms(vec![Atom::Element(Element::C); size]);
+        for i in 1..size {
+            my_mol.add_bond(i - 1, i, 1);
+        }
+        assert_eq!(my_mol.get_bonds().len(), size - 1);
+        assert_eq!(my_mol.implicit_hydrogens(size / 2), 2);
+        assert_eq!(my_mol.rings().ring_count(), 0);
+    }
+    #[test]
+    fn test_neighbours() {
+        let mut my_mol = Molecule::new(vec!["C", "O", "C", "N"]);
+        my_mol.add_bond(0, 1, 1);
+        my_mol.add_bond(1, 2, 1);
+        my_mol.add_bond(3, 1, 2);
+        assert_eq!(my_mol.neighbours(1).collect::<Vec<(usize, i32)>>(), vec![(0, 1), (2, 1), (3, 2)]);
+        assert_eq!(my_mol.degree(1), 3);
+        assert_eq!(my_mol.degree(3), 1);
+        assert_eq!(my_mol.get_matrix()[1], vec![1, 0, 1, 2]);
     }
 }
@@ -3,9 +3,14 @@
  */
 
 
+use std::io::Read;
+
 use nom::IResult;
 
 use crate::molecule::molecule::*;
+use crate::molecule::element::Element;
+use crate::molecule::error::MolParseError;
+use crate::molecule::v3000::parse_v3000_ctab;
 
 use crate::parser_common::common::*;
 
@@ -20,98 +25,367 @@ macro_rules! debug_println {
     }
 }
 
-// parses the first 4 new lines from i
-// returns the number of atoms found on line 4
-fn parse_header(i : &str) -> (&str, i32) {
-    let mut out = (i, "");
-    for _x in 0..3 {
-        out= not_newline(out.0).unwrap();
+// Steps through the lines of a .mol file, keeping count of the line number so that errors can
+// point to the offending line
+pub(crate) struct MolLines<'a> {
+    lines : std::str::Lines<'a>,
+    // the 1-based number of the last line returned by next_line
+    pub(crate) line_no : usize,
+    // the last line returned by next_line
+    pub(crate) last_line : &'a str,
+}
+
+impl<'a> MolLines<'a> {
+    pub(crate) fn new(i : &'a str) -> MolLines<'a> {
+        MolLines { lines : i.lines(), line_no : 0, last_line : "" }
+    }
+
+    // returns the next line, or None if the end of the file has been reached
+    pub(crate) fn next_line(&mut self) -> Option<&'a str> {
+        let line = self.lines.next()?;
+        self.line_no += 1;
+        self.last_line = line;
+        Some(line)
+    }
+
+    // true if there is nothing but whitespace left
+    pub(crate) fn is_blank_to_end(&self) -> bool {
+        self.lines.clone().all(|line| line.trim().is_empty())
+    }
+}
+
+// Reads a fixed width integer column from a line.
+// The counts and bond lines are fixed width, so for big molecules the numbers can run into each
+// other (" 12100" is atoms 12 and 100), which means they can't be split on whitespace.
+fn fixed_width_i32(line : &str, from : usize, to : usize) -> Option<i32> {
+    let field = line.get(from..to.min(line.len()))?;
+    field.trim().parse::<i32>().ok()
+}
+
+// The version of the connection table, from the tag at the end of the counts line
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum MolVersion {
+    V2000,
+    V3000,
+}
+
+// parses the first 4 lines of the mol block
+// returns the name on the first line, the number of atoms and the number of bonds found on the
+// counts line (line 4), and the version of the rest of the block.
+// V3000 blocks keep their counts inside the connection table, so their counts are always 0
+fn parse_header<'a>(lines : &mut MolLines<'a>) -> Result<(&'a str, usize, usize, MolVersion), MolParseError> {
+    // the name, program and comment lines
+    let mut name = "";
+    for x in 0..3 {
+        match lines.next_line() {
+            Some(line) => if x == 0 { name = line.trim() },
+            None => return Err(MolParseError::TruncatedHeader { line : lines.line_no + 1, text : String::new() }),
+        }
+    }
+    let counts = match lines.next_line() {
+        Some(x) => x,
+        None => return Err(MolParseError::TruncatedHeader { line : lines.line_no + 1, text : String::new() }),
+    };
+    debug_println!("{}", counts);
+
+    // Files without a version tag are from before V3000 existed
+    if counts.contains("V3000") {
+        return Ok((name, 0, 0, MolVersion::V3000))
+    }
+
+    let bad_counts = || MolParseError::BadCountsLine { line : lines.line_no, text : counts.to_string() };
+    let num_atoms = fixed_width_i32(counts, 0, 3).ok_or_else(bad_counts)?;
+    let num_bonds = fixed_width_i32(counts, 3, 6).ok_or_else(bad_counts)?;
+    if num_atoms < 0 || num_bonds < 0 {
+        return Err(bad_counts())
+    }
+
+    Ok((name, num_atoms as usize, num_bonds as usize, MolVersion::V2000))
+}
+
+// A single line of the atom block
+struct ParsedAtom<'a> {
+    element : &'a str,
+    coordinate : [f32; 3],
+    // the legacy charge column
+    charge : i32,
+}
+
+// parses a single line of the atom block
+fn parse_atom(i : &str) -> IResult<&str, ParsedAtom<'_>> {
+    // This section parses the 3d coordinates up the element character
+    let (i, x) = parse_f32(i)?;
+    let (i, y) = parse_f32(i)?;
+    let (i, z) = parse_f32(i)?;
+    // for the remaining whitespace before the element
+    let i = whitespace(i)?.0;
+    // takes the element char
+    let (i, element) = not_whitespace(i)?;
+    let mut atom = ParsedAtom { element, coordinate : [x, y, z], charge : 0 };
+    // the mass difference and charge columns are optional in hand written files
+    if i.trim().is_empty() {
+        return Ok((i, atom))
     }
-    // gets the number of atoms
-    let num_atoms : i32 = parse_i32(out.0).unwrap().1;
-    // finished parsing the header
-    out = not_newline(out.0).unwrap();
-    
-    (out.0, num_atoms)
+    // The mass difference is relative to the periodic table mass, "M  ISO" is what gets used
+    let (i, _mass_difference) = parse_i32(i)?;
+    if i.trim().is_empty() {
+        return Ok((i, atom))
+    }
+    let (i, charge) = parse_i32(i)?;
+    atom.charge = charge;
+    Ok((i, atom))
 }
 
-// parses the atom section, returning the list of atoms as a vector
-fn parse_atom_list(i : &str, num_atoms : i32) -> IResult<&str, Vec<&str>> {
-    let mut trimmed = i;
-    let mut atoms : Vec<&str> = Vec::new();
+// Converts the charge column of the atom block into a (charge, radical) pair
+// The column is 0 for uncharged, 1 to 3 for +3 to +1, 4 for a doublet radical, 5 to 7 for -1 to -3
+fn legacy_charge(code : i32) -> Option<(i32, i32)> {
+    match code {
+        0 => Some((0, 0)),
+        1..=3 => Some((4 - code, 0)),
+        4 => Some((0, 2)),
+        5..=7 => Some((4 - code, 0)),
+        _ => None,
+    }
+}
+
+// Deuterium and tritium can be written as "D" and "T", they are read as hydrogen with an isotope
+pub(crate) fn hydrogen_isotope(symbol : &str) -> Option<i32> {
+    match symbol {
+        "D" => Some(2),
+        "T" => Some(3),
+        _ => None,
+    }
+}
+
+// parses the atom section into a new Molecule, with the coordinates, and the charge and radical
+// from the legacy charge column of each atom
+fn parse_atom_list(lines : &mut MolLines, num_atoms : usize) -> Result<Molecule, MolParseError> {
+    let mut atoms : Vec<ParsedAtom> = Vec::new();
+    let mut elements : Vec<Atom> = Vec::new();
 
     for _i in 0..num_atoms {
-        // This section parses the 3d coordinates up the element character
-        // For each 3d coordinate
-        for _x in 0..3 {
-            trimmed = whitespace(trimmed).unwrap().0;
-            trimmed = not_whitespace(trimmed).unwrap().0;
+        let line = match lines.next_line() {
+            Some(x) => x,
+            None => return Err(MolParseError::BadAtomLine { line : lines.line_no + 1, text : String::new() }),
+        };
+        debug_println!("{}", line);
+
+        let bad_atom = || MolParseError::BadAtomLine { line : lines.line_no, text : line.to_string() };
+        let atom = parse_atom(line).map_err(|_| bad_atom())?.1;
+        if legacy_charge(atom.charge).is_none() {
+            return Err(bad_atom())
         }
-        // for the remaining whitespace before the element
-        trimmed = whitespace(trimmed).unwrap().0;
+        let element = match hydrogen_isotope(atom.element) {
+            Some(_) => Atom::Element(Element::H),
+            None => Atom::from_symbol(atom.element).ok_or(
+                MolParseError::UnknownElement { line : lines.line_no, text : line.to_string() })?,
+        };
+        elements.push(element);
+        atoms.push(atom);
+    }
 
-        // takes the element char and adds it to the atoms vector
-        let to_add = not_whitespace(trimmed).unwrap().1;
-        atoms.push(to_add);
-        
-        // Goes to the next line
-        trimmed = not_newline(trimmed)?.0;
-        
-        debug_println!("{}", trimmed);
+    let mut molecule : Molecule = Molecule::from_atoms(elements);
+    for (i, atom) in atoms.iter().enumerate() {
+        if let Some(isotope) = hydrogen_isotope(atom.element) {
+            molecule.isotopes[i] = isotope;
+        }
+        let (charge, radical) = legacy_charge(atom.charge).unwrap();
+        molecule.charges[i] = charge;
+        molecule.radicals[i] = radical;
+        molecule.set_coordinate(i, atom.coordinate);
     }
-    
-    Ok((trimmed, atoms))
+    Ok(molecule)
 }
 
 // parses a single line;  single, double, and triple bonds from the mol file format
 // Does not parse the stereochemistry section
-fn parse_bonds(i : &str) -> IResult<&str, (i32, i32, i32)> {
-    let (i, atom1) = parse_i32(i)?;
-    let (i, atom2) = parse_i32(i)?;
-    let (i, bond_type) = parse_i32(i)?;
+fn parse_bond(i : &str) -> Option<(i32, i32, i32)> {
+    let atom1 = fixed_width_i32(i, 0, 3)?;
+    let atom2 = fixed_width_i32(i, 3, 6)?;
+    let bond_type = fixed_width_i32(i, 6, 9)?;
+    Some((atom1, atom2, bond_type))
+}
 
-    let i = not_newline(i)?.0;
+// parses the bond section, adding each bond to the molecule
+fn parse_bond_list(lines : &mut MolLines, num_bonds : usize, molecule : &mut Molecule) -> Result<(), MolParseError> {
+    for _i in 0..num_bonds {
+        let line = match lines.next_line() {
+            Some(x) => x,
+            None => return Err(MolParseError::BadBondLine { line : lines.line_no + 1, text : String::new() }),
+        };
+        debug_println!("{}", line);
 
-    // The two atoms must be different, (a bond can't be described between an atom and itself)
-    assert_ne!(atom1, atom2);
+        let (atom1, atom2, bond_type) = match parse_bond(line) {
+            Some(x) => x,
+            None => return Err(MolParseError::BadBondLine { line : lines.line_no, text : line.to_string() }),
+        };
+        // the data in the mol file starts from 1 instead of 0.
+        if atom1 < 1 || atom2 < 1 {
+            return Err(MolParseError::AtomIndexOutOfRange { line : lines.line_no, text : line.to_string() })
+        }
+        add_parsed_bond(molecule, (atom1 - 1) as usize, (atom2 - 1) as usize, bond_type, lines.line_no, line)?;
+    }
+    Ok(())
+}
 
-    Ok((i, (atom1, atom2, bond_type)))
+// Adds a bond read from line number line_no, turning a BondError into the matching MolParseError
+pub(crate) fn add_parsed_bond(molecule : &mut Molecule, x : usize, y : usize, bond_type : i32,
+    line_no : usize, line : &str) -> Result<(), MolParseError> {
+    match molecule.try_add_bond(x, y, bond_type) {
+        Ok(_) => Ok(()),
+        Err(BondError::OutOfRange) => {
+            Err(MolParseError::AtomIndexOutOfRange { line : line_no, text : line.to_string() })
+        },
+        // The two atoms must be different, (a bond can't be described between an atom and itself)
+        Err(BondError::SameAtom) => {
+            Err(MolParseError::BadBondLine { line : line_no, text : line.to_string() })
+        },
+        Err(BondError::AlreadySet) => {
+            Err(MolParseError::DuplicateBond { line : line_no, text : line.to_string() })
+        },
+    }
 }
 
-// Given a valid file path of a .mol file, contructs a Molecule struct from the data in the file
-// The main entry to parser
-pub fn parse_mol(file_path : &str) -> Molecule {
-    let contents = read_file_to_string(file_path).unwrap();
-    debug_println!("{}", contents);
+// Reads the atom number from a property line, checking that it is in the molecule
+// returns the 0-based index
+fn property_atom(atom : &str, molecule : &Molecule, line_no : usize, line : &str) -> Result<usize, MolParseError> {
+    match atom.trim().parse::<usize>() {
+        Ok(x) if x >= 1 && x <= molecule.atoms.len() => Ok(x - 1),
+        Ok(_) => Err(MolParseError::AtomIndexOutOfRange { line : line_no, text : line.to_string() }),
+        Err(_) => Err(MolParseError::BadPropertyLine { line : line_no, text : line.to_string() }),
+    }
+}
 
-    // removes the header, but gets the number of atoms and saves in num_atoms
-    let (result, num_atoms) = parse_header(&contents);
+// Parses the "atom value" pairs of an "M  CHG", "M  ISO" or "M  RAD" line
+// "M  CHG  2   1   1   3  -1" -> [(0, 1), (2, -1)]
+fn parse_property_pairs(molecule : &Molecule, line_no : usize, line : &str) -> Result<Vec<(usize, i32)>, MolParseError> {
+    let bad_property = || MolParseError::BadPropertyLine { line : line_no, text : line.to_string() };
+    let tokens : Vec<&str> = line.get(6..).ok_or_else(bad_property)?.split_whitespace().collect();
+    let count = tokens.first().and_then(|x| x.parse::<usize>().ok()).ok_or_else(bad_property)?;
+    if tokens.len() != 1 + 2 * count {
+        return Err(bad_property())
+    }
+    let mut out : Vec<(usize, i32)> = Vec::new();
+    for pair in tokens[1..].chunks(2) {
+        let atom = property_atom(pair[0], molecule, line_no, line)?;
+        let value = pair[1].parse::<i32>().map_err(|_| bad_property())?;
+        out.push((atom, value));
+    }
+    Ok(out)
+}
 
-    let (mut contents, atoms) = parse_atom_list(result, num_atoms).unwrap();
-    let mut molecule : Molecule = Molecule::new(atoms);
-    debug_println!("{}", molecule.to_string());
+// Parses an "M  ALS" line, "M  ALS aaannn e sss sss ..." where the element symbols are 4
+// characters wide
+fn parse_atom_list_property(molecule : &mut Molecule, line_no : usize, line : &str) -> Result<(), MolParseError> {
+    let bad_property = || MolParseError::BadPropertyLine { line : line_no, text : line.to_string() };
+    let atom = property_atom(line.get(7..10).ok_or_else(bad_property)?, molecule, line_no, line)?;
+    let count = fixed_width_i32(line, 10, 13).ok_or_else(bad_property)? as usize;
+    let exclude = match line.get(14..15) {
+        Some("T") => true,
+        Some("F") => false,
+        _ => return Err(bad_property()),
+    };
+    let mut elements : Vec<Element> = Vec::new();
+    for i in 0..count {
+        let element = line.get(16 + 4 * i..(20 + 4 * i).min(line.len())).ok_or_else(bad_property)?.trim();
+        if element.is_empty() {
+            return Err(bad_property())
+        }
+        elements.push(Element::from_symbol(element).ok_or(
+            MolParseError::UnknownElement { line : line_no, text : line.to_string() })?);
+    }
+    molecule.atoms[atom] = Atom::Query(QueryAtom::List(AtomList { exclude, elements }));
+    Ok(())
+}
 
-    loop {
-        // If the escape character is reached, 'M', then breaks the loop
-        // In some mol files, 'A' marks the beggining of a new section after the bond adjacency
-        // section
-        if is_next_char_x(contents, 'M') || is_next_char_x(contents, 'A') {
-            break;
+// parses the properties block after the bonds, up to and including the "M  END" line
+// Only the charge, isotope, radical and atom list properties are kept
+fn parse_properties_block(lines : &mut MolLines, molecule : &mut Molecule) -> Result<(), MolParseError> {
+    // The first "M  CHG" or "M  RAD" line replaces all of the charges and radicals from the atom block
+    let mut reset_charges = false;
+    while let Some(line) = lines.next_line() {
+        let line_no = lines.line_no;
+        // "$$$$" ends the record in an .sdf file, even if "M  END" is missing
+        if line.starts_with("M  END") || line.starts_with("$$$$") {
+            break
+        }
+        if line.starts_with("M  CHG") || line.starts_with("M  RAD") {
+            if !reset_charges {
+                molecule.charges.iter_mut().for_each(|x| *x = 0);
+                molecule.radicals.iter_mut().for_each(|x| *x = 0);
+                reset_charges = true;
+            }
+            for (atom, value) in parse_property_pairs(molecule, line_no, line)? {
+                if line.starts_with("M  CHG") {
+                    molecule.charges[atom] = value;
+                } else {
+                    molecule.radicals[atom] = value;
+                }
+            }
+        } else if line.starts_with("M  ISO") {
+            for (atom, value) in parse_property_pairs(molecule, line_no, line)? {
+                molecule.isotopes[atom] = value;
+            }
+        } else if line.starts_with("M  ALS") {
+            parse_atom_list_property(molecule, line_no, line)?;
+        } else if line.starts_with("A  ") || line.starts_with("G  ") {
+            // atom aliases and group abbreviations have their text on the next line
+            lines.next_line();
+        } else if line.starts_with("S  SKP") {
+            let skip = fixed_width_i32(line, 6, 9).ok_or(
+                MolParseError::BadPropertyLine { line : line_no, text : line.to_string() })?;
+            for _i in 0..skip {
+                lines.next_line();
+            }
         }
+    }
+    Ok(())
+}
+
+// Constructs a Molecule from the lines of a mol block, parses up to and including the "M  END" line
+pub(crate) fn parse_mol_block(lines : &mut MolLines) -> Result<Molecule, MolParseError> {
+    // removes the header, but gets the number of atoms and bonds
+    let (name, num_atoms, num_bonds, version) = parse_header(lines)?;
 
-        let result = parse_bonds(contents).unwrap();
-        contents = result.0;
+    let mut molecule = if version == MolVersion::V3000 {
+        parse_v3000_ctab(lines)?
+    } else {
+        let mut molecule = parse_atom_list(lines, num_atoms)?;
+        debug_println!("{}", molecule);
 
-        debug_println!("{}", contents);
-        
-        // the -1 are there because the data in the mol file starts from 1 instead of 0.
-        // the bond doesnt need to be set to -1 because the default is 0
-        molecule.add_bond((result.1.0 - 1) as usize, (result.1.1 - 1) as usize, result.1.2);
+        parse_bond_list(lines, num_bonds, &mut molecule)?;
+        molecule
+    };
+
+    molecule.name = name.to_string();
+
+    // V3000 tables that are missing "END CTAB" stop on the "M  END" line
+    if !lines.last_line.starts_with("M  END") {
+        parse_properties_block(lines, &mut molecule)?;
     }
 
-    debug_println!("{}", molecule.to_string());
+    debug_println!("{}", molecule);
+
+    Ok(molecule)
+}
 
-    molecule
+// Given the contents of a .mol file, contructs a Molecule struct from the data
+// The main entry to parser
+pub fn parse_mol_str(contents : &str) -> Result<Molecule, MolParseError> {
+    debug_println!("{}", contents);
+    parse_mol_block(&mut MolLines::new(contents))
+}
+
+// Reads all of the .mol data out of reader, then parses it with parse_mol_str
+pub fn parse_mol_reader(mut reader : impl Read) -> Result<Molecule, MolParseError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    parse_mol_str(&contents)
+}
+
+// Given a valid file path of a .mol file, contructs a Molecule struct from the data in the file
+pub fn parse_mol(file_path : &str) -> Result<Molecule, MolParseError> {
+    parse_mol_reader(std::fs::File::open(file_path)?)
 }
 
 
@@ -126,7 +400,8 @@ mod tests {
     #[test]
     fn test_mol_parse() {
         let molecule = get_mol("Pentanoic acid.mol");
-        println!("{}", molecule.to_string());
+        println!("{}", molecule);
+        assert_eq!(molecule.name, "PENTANOIC ACID, ID: C109524");
     }
     #[test]
     fn test_debug_print() {
@@ -135,16 +410,119 @@ mod tests {
     #[test]
     fn test_imports() {
         let molecule : Molecule = Molecule::new(vec!["C", "O", "C", "K"]);
-        println!("{}", molecule.to_string());
+        println!("{}", molecule);
     }
     #[test]
     fn test_tricky_mol() {
         let molecule = get_mol("Decaborane.mol");
-        println!("{}", molecule.to_string());
+        println!("{}", molecule);
         let molecule = get_mol("1,2-Benzenedicarboxylic acid, diisooctyl ester.mol");
-        println!("{}", molecule.to_string());
+        println!("{}", molecule);
+    }
+    #[test]
+    fn test_all_test_files() {
+        for entry in std::fs::read_dir(TEST_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().unwrap() == "mol" {
+                parse_mol(path.to_str().unwrap()).unwrap();
+            }
+        }
+    }
+    #[test]
+    fn test_missing_file() {
+        assert!(matches!(parse_mol("does not exist.mol"), Err(MolParseError::Io(_))));
+    }
+    #[test]
+    fn test_malformed_mol() {
+        let header = "name\nprogram\ncomment\n";
+        let parse = |s : &str| parse_mol_block(&mut MolLines::new(s));
+
+        assert!(matches!(parse("name\nprogram\n"),
+            Err(MolParseError::TruncatedHeader { line : 3, .. })));
+        assert!(matches!(parse(&(header.to_owned() + "  x  1  0\n")),
+            Err(MolParseError::BadCountsLine { line : 4, .. })));
+
+        let counts = "  2  1  0     0  0              1 V2000\n";
+        let atoms = "    0.0000    0.0000    0.0000 C   0  0\n    1.0000    0.0000    0.0000 O   0  0\n";
+        assert!(matches!(parse(&(header.to_owned() + counts + "    0.0000    zero    0.0000 C\n")),
+            Err(MolParseError::BadAtomLine { line : 5, .. })));
+        assert!(matches!(parse(&(header.to_owned() + counts + atoms + "  1  1  1  0\n")),
+            Err(MolParseError::BadBondLine { line : 7, .. })));
+        assert!(matches!(parse(&(header.to_owned() + counts + atoms + "  1  a  1  0\n")),
+            Err(MolParseError::BadBondLine { line : 7, .. })));
+        assert!(matches!(parse(&(header.to_owned() + counts + atoms + "M  END\n")),
+            Err(MolParseError::BadBondLine { line : 7, .. })));
+        assert!(matches!(parse(&(header.to_owned() + counts + atoms + "  1  3  1  0\n")),
+            Err(MolParseError::AtomIndexOutOfRange { line : 7, .. })));
+
+        let counts = "  2  2  0     0  0              1 V2000\n";
+        match parse(&(header.to_owned() + counts + atoms + "  1  2  1  0\n  2  1  2  0\n")) {
+            Err(MolParseError::DuplicateBond { line, text }) => {
+                assert_eq!(line, 8);
+                assert_eq!(text, "  2  1  2  0");
+            },
+            _ => panic!("expected a duplicate bond error"),
+        }
+    }
+    #[test]
+    fn test_parse_from_memory() {
+        let contents = read_file_to_string(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap();
+        let from_file = get_mol("Benzoic acid.mol");
+        let from_str = parse_mol_str(&contents).unwrap();
+        let from_reader = parse_mol_reader(contents.as_bytes()).unwrap();
+        assert_eq!(from_file.get_matrix(), from_str.get_matrix());
+        assert_eq!(from_file.atoms, from_reader.atoms);
+        assert_eq!(from_file.get_matrix(), from_reader.get_matrix());
+    }
+    #[test]
+    fn test_coordinates() {
+        let molecule = get_mol("Benzoic acid.mol");
+        assert_eq!(molecule.get_coordinates().len(), 9);
+        assert_eq!(molecule.get_coordinate(0), [2.0186, 1.7158, 0.0]);
+        assert_eq!(molecule.get_coordinate(8), [0.0, 0.0, 0.0]);
+    }
+    #[test]
+    fn test_properties_block() {
+        let contents = "nitromethane\n\n\n  4  3  0     0  0              1 V2000\n\
+            \x20   0.0000    0.0000    0.0000 C   0  0\n\
+            \x20   1.0000    0.0000    0.0000 N   0  3\n\
+            \x20   2.0000    0.0000    0.0000 O   0  0\n\
+            \x20   2.0000    1.0000    0.0000 O   0  5\n\
+            \x20 1  2  1  0\n  2  3  2  0\n  2  4  1  0\n";
+        // the legacy charge column alone
+        let molecule = parse_mol_str(&(contents.to_owned() + "M  END\n")).unwrap();
+        assert_eq!(molecule.charges, vec![0, 1, 0, -1]);
+
+        // "M  CHG" replaces the charge column
+        let properties = "M  CHG  1   2   1\nM  ISO  1   1  13\nM  RAD  1   3   2\nA    1\nM  CHG\nM  ALS   4  2 F O   S   \nM  END\n";
+        let molecule = parse_mol_str(&(contents.to_owned() + properties)).unwrap();
+        assert_eq!(molecule.charges, vec![0, 1, 0, 0]);
+        assert_eq!(molecule.isotopes, vec![13, 0, 0, 0]);
+        assert_eq!(molecule.radicals, vec![0, 0, 2, 0]);
+        assert_eq!(molecule.atoms[3], Atom::Query(QueryAtom::List(AtomList { exclude : false, elements : vec![Element::O, Element::S] })));
+
+        assert!(matches!(parse_mol_str(&(contents.to_owned() + "M  CHG  2   1   1\n")),
+            Err(MolParseError::BadPropertyLine { line : 12, .. })));
+        assert!(matches!(parse_mol_str(&(contents.to_owned() + "M  ISO  1   5  13\n")),
+            Err(MolParseError::AtomIndexOutOfRange { line : 12, .. })));
+        assert!(matches!(parse_mol_str(&(contents.to_owned() + "M  ALS   4  2 F O   Xx  \n")),
+            Err(MolParseError::UnknownElement { line : 12, .. })));
+    }
+    #[test]
+    fn test_element_symbols() {
+        let contents = "\n\n\n  3  0  0     0  0              1 V2000\n\
+            \x20   0.0000    0.0000    0.0000 C   0  0\n\
+            \x20   1.0000    0.0000    0.0000 D   0  0\n\
+            \x20   2.0000    0.0000    0.0000 Cl  0  0\n";
+        let molecule = parse_mol_str(&(contents.to_owned() + "M  END\n")).unwrap();
+        assert_eq!(molecule.atoms, vec![Atom::Element(Element::C), Atom::Element(Element::H), Atom::Element(Element::Cl)]);
+        assert_eq!(molecule.isotopes, vec![0, 2, 0]);
+
+        // unknown symbols are an error instead of a new kind of atom
+        let contents = contents.replace(" Cl ", " Cx ");
+        assert!(matches!(parse_mol_str(&contents), Err(MolParseError::UnknownElement { line : 7, .. })));
     }
     fn get_mol(file : &str) -> Molecule {
-        parse_mol(&(TEST_DIR.to_owned() + file))
+        parse_mol(&(TEST_DIR.to_owned() + file)).unwrap()
     }
 }
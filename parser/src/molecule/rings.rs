@@ -0,0 +1,403 @@
+/**
+ * Ring perception
+ *
+ * Finds the smallest set of smallest rings (SSSR), the fewest smallest rings that every other
+ * ring in the molecule can be made from. Candidate rings are built Horton's way, from every atom
+ * and every bond, a shortest path from the atom to each end of the bond closed by the bond. The
+ * candidates are taken from smallest to largest, keeping the ones whose bonds can't be made by
+ * adding up (exclusive or) the rings already kept. The candidates are made a batch of ring sizes
+ * at a time, stopping as soon as there are enough rings, so big molecules of small rings only
+ * search a few bonds out from each atom.
+ **/
+
+use std::collections::HashMap;
+
+use crate::molecule::molecule::Molecule;
+
+// The rings of a molecule, with the atoms of each ring in order around the ring
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingInfo {
+    pub rings : Vec<Vec<usize>>,
+    atom_count : usize,
+}
+
+impl RingInfo {
+    // The number of rings in the smallest set of smallest rings
+    pub fn ring_count(&self) -> usize {
+        self.rings.len()
+    }
+
+    // Whether the atom is part of any ring
+    pub fn is_atom_in_ring(&self, atom : usize) -> bool {
+        self.rings.iter().any(|x| x.contains(&atom))
+    }
+
+    // Whether the bond between two atoms is part of any ring
+    pub fn is_bond_in_ring(&self, x : usize, y : usize) -> bool {
+        self.rings.iter().any(|ring| ring_has_bond(ring, x, y))
+    }
+
+    // The sizes of the rings the atom is in, smallest first, empty for atoms not in a ring
+    pub fn atom_ring_sizes(&self, atom : usize) -> Vec<usize> {
+        self.rings.iter().filter(|x| x.contains(&atom)).map(|x| x.len()).collect()
+    }
+
+    // The sizes of the rings the bond is in, smallest first
+    pub fn bond_ring_sizes(&self, x : usize, y : usize) -> Vec<usize> {
+        self.rings.iter().filter(|ring| ring_has_bond(ring, x, y)).map(|x| x.len()).collect()
+    }
+
+    // The number of rings each atom is in
+    pub fn atom_ring_counts(&self) -> Vec<usize> {
+        let mut out = vec![0; self.atom_count];
+        for ring in &self.rings {
+            for atom in ring {
+                out[*atom] += 1;
+            }
+        }
+        out
+    }
+
+    // Groups rings that share a bond into fused ring systems, naphthalene is one system of two
+    // rings, biphenyl is two systems. Rings that only share an atom (spiro rings) are kept apart
+    // Returns the indexes into rings of each system
+    pub fn ring_systems(&self) -> Vec<Vec<usize>> {
+        let mut systems : Vec<Vec<usize>> = Vec::new();
+        for i in 0..self.rings.len() {
+            let fused : Vec<usize> = (0..systems.len())
+                .filter(|x| systems[*x].iter().any(|y| rings_share_bond(&self.rings[i], &self.rings[*y])))
+                .collect();
+            let mut system = vec![i];
+            // merges every system this ring joins, from the back so the indexes stay the same
+            for x in fused.into_iter().rev() {
+                system.extend(systems.remove(x));
+            }
+            system.sort_unstable();
+            systems.push(system);
+        }
+        systems.sort();
+        systems
+    }
+
+    // The atoms of each fused ring system, sorted
+    pub fn ring_system_atoms(&self) -> Vec<Vec<usize>> {
+        self.ring_systems().iter().map(|system| {
+            let mut atoms : Vec<usize> = system.iter().flat_map(|x| self.rings[*x].clone()).collect();
+            atoms.sort_unstable();
+            atoms.dedup();
+            atoms
+        }).collect()
+    }
+}
+
+fn ring_has_bond(ring : &[usize], x : usize, y : usize) -> bool {
+    (0..ring.len()).any(|i| {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        (a == x && b == y) || (a == y && b == x)
+    })
+}
+
+fn rings_share_bond(a : &[usize], b : &[usize]) -> bool {
+    (0..a.len()).any(|i| ring_has_bond(b, a[i], a[(i + 1) % a.len()]))
+}
+
+// A breadth first search tree from a root atom, going no further than a depth from it
+struct PathTree {
+    // the parent of every atom on the tree, None for the root and atoms that weren't reached
+    parents : Vec<Option<usize>>,
+    // the number of bonds from the root, None for atoms that weren't reached
+    distances : Vec<Option<usize>>,
+    // the atoms reached, in the order they were found, so only they have to be cleared for the
+    // next root
+    reached : Vec<usize>,
+}
+
+impl PathTree {
+    fn new(atom_count : usize) -> PathTree {
+        PathTree { parents : vec![None; atom_count], distances : vec![None; atom_count], reached : Vec::new() }
+    }
+
+    fn grow(&mut self, molecule : &Molecule, root : usize, depth : usize) {
+        for atom in self.reached.drain(..) {
+            self.parents[atom] = None;
+            self.distances[atom] = None;
+        }
+        self.distances[root] = Some(0);
+        self.reached.push(root);
+        let mut next = 0;
+        while next < self.reached.len() {
+            let atom = self.reached[next];
+            next += 1;
+            let distance = self.distances[atom].unwrap();
+            if distance == depth {
+                continue
+            }
+            for (neighbour, _) in molecule.neighbours(atom) {
+                if self.distances[neighbour].is_none() {
+                    self.distances[neighbour] = Some(distance + 1);
+                    self.parents[neighbour] = Some(atom);
+                    self.reached.push(neighbour);
+                }
+            }
+        }
+    }
+
+    // The path from an atom up the tree to the root, starting with the atom
+    fn path_to_root(&self, atom : usize) -> Vec<usize> {
+        let mut path = vec![atom];
+        let mut current = atom;
+        while let Some(parent) = self.parents[current] {
+            path.push(parent);
+            current = parent;
+        }
+        path
+    }
+}
+
+// The bonds of a ring, as sorted indexes into the molecule's bonds
+fn bond_row(ring : &[usize], bond_index : &HashMap<(usize, usize), usize>) -> Vec<usize> {
+    let mut row : Vec<usize> = (0..ring.len())
+        .map(|i| {
+            let (x, y) = (ring[i], ring[(i + 1) % ring.len()]);
+            bond_index[&(x.min(y), x.max(y))]
+        })
+        .collect();
+    row.sort_unstable();
+    row
+}
+
+// The bonds in one of two rows but not both (their sum, exclusive or), sorted
+fn add_rows(a : &[usize], b : &[usize]) -> Vec<usize> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            out.push(a[i]);
+            i += 1;
+        } else if b[j] < a[i] {
+            out.push(b[j]);
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    out
+}
+
+// Reduces a row against the rows already kept, each kept row has its own leading (lowest) bond
+// Returns the row if it is independent, None if it is a sum of kept rows
+fn reduce(mut row : Vec<usize>, basis : &HashMap<usize, Vec<usize>>) -> Option<Vec<usize>> {
+    loop {
+        match basis.get(row.first()?) {
+            Some(basis_row) => row = add_rows(&row, basis_row),
+            None => return Some(row),
+        }
+    }
+}
+
+// Finds the smallest set of smallest rings
+pub fn find_rings(molecule : &Molecule) -> RingInfo {
+    let atom_count = molecule.atoms.len();
+    let bonds = molecule.get_bonds();
+
+    // the number of rings is the number of bonds that aren't needed to connect every atom
+    let mut components = 0;
+    let mut seen = vec![false; atom_count];
+    for atom in 0..atom_count {
+        if seen[atom] {
+            continue
+        }
+        components += 1;
+        seen[atom] = true;
+        let mut stack = vec![atom];
+        while let Some(x) = stack.pop() {
+            for (y, _) in molecule.neighbours(x) {
+                if !seen[y] {
+                    seen[y] = true;
+                    stack.push(y);
+                }
+            }
+        }
+    }
+    let ring_count = bonds.len() + components - atom_count;
+    if ring_count == 0 {
+        return RingInfo { rings : Vec::new(), atom_count }
+    }
+    let bond_index : HashMap<(usize, usize), usize> = bonds.iter().enumerate()
+        .map(|(i, (x, y, _))| ((*x.min(y), *x.max(y)), i))
+        .collect();
+
+    let mut rings = Vec::new();
+    let mut basis : HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut tree = PathTree::new(atom_count);
+    // the candidate that last marked each atom, for checking that two paths only meet at the root
+    let mut marks : Vec<usize> = vec![0; atom_count];
+    let mut stamp = 0;
+    // this batch is the rings bigger than smaller and up to larger atoms
+    let (mut smaller, mut larger) = (0, 8);
+    while rings.len() < ring_count && smaller < atom_count {
+        let mut candidates : Vec<Vec<usize>> = Vec::new();
+        for root in 0..atom_count {
+            // the two paths of a ring are at most one bond different in length
+            tree.grow(molecule, root, larger / 2);
+            for x in tree.reached.iter().copied() {
+                for (y, _) in molecule.neighbours(x) {
+                    let (dx, dy) = match (tree.distances[x], tree.distances[y]) {
+                        (Some(dx), Some(dy)) if x < y => (dx, dy),
+                        _ => continue,
+                    };
+                    // bonds on the tree would make a path back on itself
+                    if tree.parents[x] == Some(y) || tree.parents[y] == Some(x) {
+                        continue
+                    }
+                    let size = dx + dy + 1;
+                    if size <= smaller || size > larger {
+                        continue
+                    }
+                    let to_x = tree.path_to_root(x);
+                    let to_y = tree.path_to_root(y);
+                    // the paths can only meet at the root, or the ring would touch itself
+                    stamp += 1;
+                    for atom in &to_x {
+                        marks[*atom] = stamp;
+                    }
+                    if to_y.iter().filter(|a| marks[**a] == stamp).count() != 1 {
+                        continue
+                    }
+                    let mut ring = to_x;
+                    ring.pop();
+                    ring.reverse();
+                    ring.extend(to_y);
+                    ring.rotate_right(1);
+                    candidates.push(ring);
+                }
+            }
+        }
+        // smallest first, then by atoms so the same molecule always gives the same rings
+        for ring in candidates.iter_mut() {
+            normalize_ring(ring);
+        }
+        candidates.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        candidates.dedup();
+
+        for ring in candidates {
+            if let Some(reduced) = reduce(bond_row(&ring, &bond_index), &basis) {
+                basis.insert(reduced[0], reduced);
+                rings.push(ring);
+                if rings.len() == ring_count {
+                    break
+                }
+            }
+        }
+        smaller = larger;
+        larger *= 2;
+    }
+    RingInfo { rings, atom_count }
+}
+
+// Starts a ring at its lowest atom and goes round towards the lower of that atom's neighbours
+fn normalize_ring(ring : &mut [usize]) {
+    let lowest = (0..ring.len()).min_by_key(|x| ring[*x]).unwrap_or(0);
+    ring.rotate_left(lowest);
+    if ring.len() > 2 && ring[ring.len() - 1] < ring[1] {
+        ring[1..].reverse();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::element::Element;
+    use crate::molecule::parser::parse_mol;
+    use crate::molecule::smiles::parse_smiles;
+
+    fn ring_sizes(smiles : &str) -> Vec<usize> {
+        find_rings(&parse_smiles(smiles).unwrap()).rings.iter().map(|x| x.len()).collect()
+    }
+
+    #[test]
+    fn test_ring_sizes() {
+        assert_eq!(ring_sizes("CCO"), Vec::<usize>::new());
+        assert_eq!(ring_sizes("c1ccccc1"), vec![6]);
+        // naphthalene, not the 10 ring around the outside
+        assert_eq!(ring_sizes("c1ccc2ccccc2c1"), vec![6, 6]);
+        // norbornane
+        assert_eq!(ring_sizes("C1CC2CCC1C2"), vec![5, 5]);
+        // cubane has 6 faces but only 5 are needed
+        assert_eq!(ring_sizes("C12C3C4C1C5C2C3C45"), vec![4, 4, 4, 4, 4]);
+        // spiro[4.5]decane
+        assert_eq!(ring_sizes("C1CCC2(C1)CCCCC2"), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_ring_membership() {
+        // toluene
+        let rings = find_rings(&parse_smiles("Cc1ccccc1").unwrap());
+        assert!(!rings.is_atom_in_ring(0));
+        assert!(rings.is_atom_in_ring(1));
+        assert!(!rings.is_bond_in_ring(0, 1));
+        assert!(rings.is_bond_in_ring(1, 2));
+        assert!(rings.is_bond_in_ring(6, 1));
+        assert_eq!(rings.atom_ring_sizes(3), vec![6]);
+        assert_eq!(rings.bond_ring_sizes(0, 1), Vec::<usize>::new());
+
+        // the atoms where naphthalene's rings fuse are in both
+        let rings = find_rings(&parse_smiles("c1ccc2ccccc2c1").unwrap());
+        assert_eq!(rings.atom_ring_counts(), vec![1, 1, 1, 2, 1, 1, 1, 1, 2, 1]);
+        assert_eq!(rings.bond_ring_sizes(3, 8), vec![6, 6]);
+    }
+
+    #[test]
+    fn test_ring_systems() {
+        // biphenyl is two systems, naphthalene is one
+        assert_eq!(find_rings(&parse_smiles("c1ccccc1-c1ccccc1").unwrap()).ring_systems().len(), 2);
+        let rings = find_rings(&parse_smiles("c1ccc2ccccc2c1").unwrap());
+        assert_eq!(rings.ring_systems(), vec![vec![0, 1]]);
+        assert_eq!(rings.ring_system_atoms(), vec![(0..10).collect::<Vec<usize>>()]);
+        // spiro rings only share an atom
+        assert_eq!(find_rings(&parse_smiles("C1CCC2(C1)CCCCC2").unwrap()).ring_systems().len(), 2);
+    }
+
+    #[test]
+    fn test_mol_files() {
+        let benzoic = find_rings(&parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap());
+        assert_eq!(benzoic.rings.iter().map(|x| x.len()).collect::<Vec<usize>>(), vec![6]);
+        let methane = find_rings(&parse_mol(&(TEST_DIR.to_owned() + "Methane.mol")).unwrap());
+        assert_eq!(methane.ring_count(), 0);
+        // every boron of decaborane is in a ring of the cage, the hydrogens aren't
+        let decaborane = parse_mol(&(TEST_DIR.to_owned() + "Decaborane.mol")).unwrap();
+        let rings = find_rings(&decaborane);
+        assert_eq!(rings.ring_count(), decaborane.get_bonds().len() + 1 - decaborane.atoms.len());
+        assert!((0..decaborane.atoms.len())
+            .filter(|x| decaborane.atoms[*x].is_element(Element::B))
+            .all(|x| rings.is_atom_in_ring(x)));
+        assert!(rings.rings.windows(2).all(|x| x[0].len() <= x[1].len()));
+    }
+
+    #[test]
+    fn test_large_molecule() {
+        // polystyrene, a benzene ring on every other carbon of a 1200 carbon chain
+        let units = 600;
+        let mut polystyrene = parse_smiles(&"CC(c1ccccc1)".repeat(units)).unwrap();
+        let rings = find_rings(&polystyrene);
+        assert_eq!(rings.ring_count(), units);
+        assert!(rings.rings.iter().all(|x| x.len() == 6));
+        assert_eq!(rings.ring_systems().len(), units);
+        polystyrene.kekulize().unwrap();
+        polystyrene.aromatize();
+        assert_eq!(polystyrene.get_bonds().iter().filter(|x| x.2 == 4).count(), 6 * units);
+
+        // a chain of 200 cyclopropanes closed into a ring of 404 atoms, which needs the search to
+        // go right round the molecule
+        let closed = parse_smiles(&format!("C1C{}CC1", "C2CC2".repeat(200))).unwrap();
+        let sizes : Vec<usize> = find_rings(&closed).rings.iter().map(|x| x.len()).collect();
+        assert_eq!(sizes.len(), 201);
+        assert!(sizes[..200].iter().all(|x| *x == 3));
+        assert_eq!(sizes[200], 404);
+    }
+}
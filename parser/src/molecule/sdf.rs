@@ -0,0 +1,213 @@
+/**
+ * Reads multi-record .sdf files
+ *
+ * An .sdf file is a list of mol blocks, each followed by optional data items and
+ * terminated by a "$$$$" line:
+ *
+ * <mol block>
+ * M  END
+ * > <PUBCHEM_COMPOUND_CID>
+ * 297
+ *
+ * $$$$
+ **/
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::molecule::molecule::Molecule;
+use crate::molecule::error::MolParseError;
+use crate::molecule::parser::{MolLines, parse_mol_block};
+use crate::parser_common::common::read_file_to_string;
+
+// Marks the end of a record
+pub(crate) static RECORD_END : &str = "$$$$";
+
+// A single record of an .sdf file, the molecule and its data items
+pub struct SdfRecord {
+    pub molecule : Molecule,
+    // maps the field name from "> <FIELD>" to its value, multi-line values are joined with "\n"
+    pub data : HashMap<String, String>,
+}
+
+// A record of an .sdf file whose mol block failed to parse, with the data items after it
+#[derive(Debug)]
+pub struct SdfRecordError {
+    pub error : MolParseError,
+    // the data items that could still be read after the mol block, empty if the error was at the
+    // end of the record
+    pub data : HashMap<String, String>,
+}
+
+impl fmt::Display for SdfRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for SdfRecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+// Iterates over the records of the contents of an .sdf file
+// A record that fails to parse is returned as an error along with its data items, and the
+// iterator goes on to the next record, so one bad record doesn't stop the rest of the file from
+// being read
+pub struct SdfRecords<'a> {
+    lines : MolLines<'a>,
+}
+
+impl<'a> SdfRecords<'a> {
+    pub fn new(contents : &'a str) -> SdfRecords<'a> {
+        SdfRecords { lines : MolLines::new(contents) }
+    }
+
+    // Parses the data items after the mol block, until the end of the record
+    fn parse_data_items(&mut self) -> HashMap<String, String> {
+        let mut data : HashMap<String, String> = HashMap::new();
+        let mut field : Option<String> = None;
+        let mut value : Vec<&str> = Vec::new();
+
+        while let Some(line) = self.lines.next_line() {
+            if line.starts_with(RECORD_END) {
+                break
+            }
+            match &field {
+                None => {
+                    // Anything between the data items that isn't a data header is skipped
+                    if line.starts_with('>') {
+                        field = Some(parse_data_header(line));
+                    }
+                },
+                Some(name) => {
+                    // A blank line ends the value of the data item
+                    if line.trim().is_empty() {
+                        data.insert(name.to_string(), value.join("\n"));
+                        field = None;
+                        value.clear();
+                    } else {
+                        value.push(line);
+                    }
+                }
+            }
+        }
+        // The last data item doesn't need a blank line before the end of the record
+        if let Some(name) = field {
+            data.insert(name, value.join("\n"));
+        }
+        data
+    }
+}
+
+impl<'a> Iterator for SdfRecords<'a> {
+    type Item = Result<SdfRecord, SdfRecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Trailing blank lines at the end of the file aren't a record
+        if self.lines.is_blank_to_end() {
+            return None
+        }
+        let molecule = match parse_mol_block(&mut self.lines) {
+            Ok(x) => x,
+            Err(error) => {
+                // The record might have already ended on the line that caused the error, if not
+                // the rest of the mol block is skipped over looking for data items
+                let data = if self.lines.last_line.starts_with(RECORD_END) {
+                    HashMap::new()
+                } else {
+                    self.parse_data_items()
+                };
+                return Some(Err(SdfRecordError { error, data }))
+            }
+        };
+        // The mol block can end the record if it doesn't have an "M  END" line
+        if self.lines.last_line.starts_with(RECORD_END) {
+            return Some(Ok(SdfRecord { molecule, data : HashMap::new() }))
+        }
+        let data = self.parse_data_items();
+        Some(Ok(SdfRecord { molecule, data }))
+    }
+}
+
+// Gets the field name from a data header line
+// "> <PUBCHEM_COMPOUND_CID> (1)" -> "PUBCHEM_COMPOUND_CID"
+// Old style headers without angle brackets ">  25  (MD-08974)" keep the rest of the line
+fn parse_data_header(line : &str) -> String {
+    let line = &line[1..];
+    if let Some(start) = line.find('<') {
+        if let Some(len) = line[start + 1..].find('>') {
+            return line[start + 1..start + 1 + len].to_string()
+        }
+    }
+    line.trim().to_string()
+}
+
+// Given the contents of an .sdf file, returns an iterator over its records
+pub fn parse_sdf_str(contents : &str) -> SdfRecords<'_> {
+    SdfRecords::new(contents)
+}
+
+// Given a valid file path of an .sdf file, parses every record in the file
+// Only returns an error for the whole file if the file can't be read, errors from single records
+// are kept in place of the record
+pub fn parse_sdf(file_path : &str) -> Result<Vec<Result<SdfRecord, SdfRecordError>>, MolParseError> {
+    let contents = read_file_to_string(file_path)?;
+    Ok(parse_sdf_str(&contents).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::parse_mol;
+
+    #[test]
+    fn test_parse_sdf() {
+        let records = parse_sdf(&(TEST_DIR.to_owned() + "Methane, Benzoic acid, Pentanoic acid.sdf")).unwrap();
+        assert_eq!(records.len(), 3);
+        let records : Vec<SdfRecord> = records.into_iter().map(|x| x.unwrap()).collect();
+
+        let methane = parse_mol(&(TEST_DIR.to_owned() + "Methane.mol")).unwrap();
+        assert_eq!(records[0].molecule.atoms, methane.atoms);
+        assert_eq!(records[0].molecule.get_matrix(), methane.get_matrix());
+        assert_eq!(records[0].data["PUBCHEM_COMPOUND_CID"], "297");
+        assert_eq!(records[0].data["NAME"], "Methane");
+
+        assert_eq!(records[1].molecule.atoms.len(), 9);
+        assert_eq!(records[1].data["SYNONYMS"], "benzoic acid\nbenzenecarboxylic acid");
+
+        assert_eq!(records[2].molecule.atoms.len(), 7);
+        assert!(records[2].data.is_empty());
+    }
+
+    #[test]
+    fn test_bad_record_is_skipped() {
+        let good = "good\n\n\n  1  0  0     0  0              1 V2000\n    0.0000    0.0000    0.0000 C   0  0\nM  END\n> <ID>\n1\n\n$$$$\n";
+        let bad = "bad\n\n\n  2  1  0     0  0              1 V2000\n    0.0000    0.0000    0.0000 C   0  0\n    0.0000    0.0000    0.0000 C   0  0\n  1  1  1  0\nM  END\n> <ID>\n2\n\n$$$$\n";
+        // The bond block ends early, the "$$$$" line is read as a bond
+        let short = "short\n\n\n  2  1  0     0  0              1 V2000\n    0.0000    0.0000    0.0000 C   0  0\n    0.0000    0.0000    0.0000 C   0  0\n$$$$\n";
+        let contents = good.to_owned() + bad + short + good + "\n\n";
+        let records : Vec<Result<SdfRecord, SdfRecordError>> = parse_sdf_str(&contents).collect();
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].as_ref().unwrap().data["ID"], "1");
+        let bad = records[1].as_ref().err().unwrap();
+        assert!(matches!(bad.error, MolParseError::BadBondLine { line : 17, .. }));
+        // the data items of a bad record are still read
+        assert_eq!(bad.data["ID"], "2");
+        let short = records[2].as_ref().err().unwrap();
+        assert!(matches!(short.error, MolParseError::BadBondLine { line : 29, .. }));
+        assert!(short.data.is_empty());
+        assert_eq!(records[3].as_ref().unwrap().data["ID"], "1");
+    }
+
+    #[test]
+    fn test_parse_data_header() {
+        assert_eq!(parse_data_header("> <PUBCHEM_COMPOUND_CID>"), "PUBCHEM_COMPOUND_CID");
+        assert_eq!(parse_data_header(">  25  <MELTING.POINT> (1)"), "MELTING.POINT");
+        assert_eq!(parse_data_header(">  25  (MD-08974)"), "25  (MD-08974)");
+    }
+}
@@ -0,0 +1,512 @@
+/**
+ * OpenSMILES parser, builds a Molecule from a SMILES string
+ *
+ * Aromatic atoms are written as lowercase symbols in SMILES, they are stored with their normal
+ * element symbol and joined by aromatic bonds (type 4, the same as a .mol file). Bracket atoms
+ * set the isotope, charge and hydrogen count of the atom. Chirality and atom classes are read
+ * but not kept, "/" and "\" bonds are kept in Molecule::bond_directions.
+ **/
+
+use std::collections::HashMap;
+
+use crate::molecule::molecule::{Molecule, Atom, QueryAtom, BondDirection, BondError};
+use crate::molecule::element::Element;
+use crate::molecule::error::SmilesError;
+
+// The bond type used for aromatic bonds, as in the .mol bond block
+static AROMATIC_BOND : i32 = 4;
+
+// Atoms that can be written without brackets
+static ORGANIC_SUBSET : [&str; 10] = ["Cl", "Br", "B", "C", "N", "O", "P", "S", "F", "I"];
+static AROMATIC_ORGANIC_SUBSET : [&str; 6] = ["b", "c", "n", "o", "p", "s"];
+// Aromatic atoms that are only allowed inside brackets
+static AROMATIC_BRACKET_ONLY : [&str; 3] = ["se", "as", "te"];
+
+// A bond symbol as it was written between two atoms
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum BondSymbol {
+    Single,
+    Double,
+    Triple,
+    Quadruple,
+    Aromatic,
+    Up,
+    Down,
+}
+
+impl BondSymbol {
+    fn from_char(c : char) -> Option<BondSymbol> {
+        match c {
+            '-' => Some(BondSymbol::Single),
+            '=' => Some(BondSymbol::Double),
+            '#' => Some(BondSymbol::Triple),
+            '$' => Some(BondSymbol::Quadruple),
+            ':' => Some(BondSymbol::Aromatic),
+            '/' => Some(BondSymbol::Up),
+            '\\' => Some(BondSymbol::Down),
+            _ => None,
+        }
+    }
+
+    fn direction(self) -> Option<BondDirection> {
+        match self {
+            BondSymbol::Up => Some(BondDirection::Up),
+            BondSymbol::Down => Some(BondDirection::Down),
+            _ => None,
+        }
+    }
+}
+
+// An atom read from the SMILES string, before the Molecule is built
+struct SmilesAtom {
+    atom : Atom,
+    aromatic : bool,
+    isotope : i32,
+    charge : i32,
+    hydrogens : Option<i32>,
+}
+
+impl SmilesAtom {
+    // Reads the symbol of the atom starting at position, "*" is any atom
+    fn new(symbol : &str, position : usize) -> Result<SmilesAtom, SmilesError> {
+        let atom = if symbol == "*" {
+            Atom::Query(QueryAtom::Any)
+        } else {
+            let element = Element::from_symbol(&capitalize(symbol))
+                .ok_or_else(|| SmilesError::UnknownElement { position, symbol : symbol.to_string() })?;
+            Atom::Element(element)
+        };
+        Ok(SmilesAtom {
+            atom,
+            aromatic : symbol.starts_with(|c : char| c.is_ascii_lowercase()),
+            isotope : 0,
+            charge : 0,
+            hydrogens : None,
+        })
+    }
+}
+
+// A bond read from the SMILES string, the bond symbol is None when it was left out
+struct SmilesBond {
+    from : usize,
+    to : usize,
+    symbol : Option<BondSymbol>,
+    position : usize,
+}
+
+// Turns an aromatic symbol into the element symbol, "se" -> "Se"
+fn capitalize(symbol : &str) -> String {
+    let mut chars = symbol.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+// Reads a run of digits starting at i, returns the number and the index after it
+fn read_number(chars : &[char], mut i : usize) -> (Option<u32>, usize) {
+    let start = i;
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i == start {
+        return (None, i)
+    }
+    let number : String = chars[start..i].iter().collect();
+    (number.parse().ok(), i)
+}
+
+// Reads an atom from the organic subset at i, returns the symbol and the index after it
+fn read_organic_atom(chars : &[char], i : usize) -> Option<(String, usize)> {
+    if chars[i] == '*' {
+        return Some(("*".to_string(), i + 1))
+    }
+    // two letter symbols have to be tried first so "Cl" isn't read as "C"
+    if i + 1 < chars.len() {
+        let pair : String = chars[i..i + 2].iter().collect();
+        if ORGANIC_SUBSET.contains(&pair.as_str()) {
+            return Some((pair, i + 2))
+        }
+    }
+    let single = chars[i].to_string();
+    if ORGANIC_SUBSET.contains(&single.as_str()) || AROMATIC_ORGANIC_SUBSET.contains(&single.as_str()) {
+        return Some((single, i + 1))
+    }
+    None
+}
+
+// Reads a bracket atom, i is the index of the "[", returns the atom and the index after the "]"
+// [isotope? symbol chirality? hcount? charge? class?]
+fn read_bracket_atom(chars : &[char], start : usize) -> Result<(SmilesAtom, usize), SmilesError> {
+    let bad = SmilesError::BadBracketAtom { position : start };
+    let at = |i : usize| chars.get(i).copied();
+
+    let (isotope, mut i) = read_number(chars, start + 1);
+
+    // element symbol
+    let symbol = match at(i) {
+        Some('*') => { i += 1; "*".to_string() },
+        Some(c) if c.is_ascii_uppercase() => {
+            i += 1;
+            match at(i) {
+                Some(l) if l.is_ascii_lowercase() => { i += 1; format!("{}{}", c, l) },
+                _ => c.to_string(),
+            }
+        },
+        Some(c) if c.is_ascii_lowercase() => {
+            let pair : String = chars[i..chars.len().min(i + 2)].iter().collect();
+            if AROMATIC_BRACKET_ONLY.contains(&pair.as_str()) {
+                i += 2;
+                pair
+            } else if AROMATIC_ORGANIC_SUBSET.contains(&c.to_string().as_str()) {
+                i += 1;
+                c.to_string()
+            } else {
+                return Err(bad)
+            }
+        },
+        _ => return Err(bad),
+    };
+    let mut atom = SmilesAtom::new(&symbol, start)?;
+    atom.isotope = isotope.unwrap_or(0) as i32;
+
+    // chirality, @, @@, or @TH1 style classes
+    if at(i) == Some('@') {
+        i += 1;
+        if at(i) == Some('@') {
+            i += 1;
+        } else if let (Some(a), Some(b)) = (at(i), at(i + 1)) {
+            if ["TH", "AL", "SP", "TB", "OH"].contains(&format!("{}{}", a, b).as_str()) {
+                let (number, next) = read_number(chars, i + 2);
+                if number.is_none() {
+                    return Err(bad)
+                }
+                i = next;
+            }
+        }
+    }
+
+    // hydrogen count, bracket atoms have no hydrogens unless they are written
+    atom.hydrogens = Some(0);
+    if at(i) == Some('H') {
+        let (count, next) = read_number(chars, i + 1);
+        atom.hydrogens = Some(count.unwrap_or(1) as i32);
+        i = next;
+    }
+
+    // charge, +, -, +2, or the older ++ style
+    if let Some(sign @ ('+' | '-')) = at(i) {
+        let unit = if sign == '+' { 1 } else { -1 };
+        i += 1;
+        let (count, next) = read_number(chars, i);
+        match count {
+            Some(count) => { atom.charge = unit * count as i32; i = next; },
+            None => {
+                atom.charge = unit;
+                while at(i) == Some(sign) {
+                    atom.charge += unit;
+                    i += 1;
+                }
+            },
+        }
+    }
+
+    // atom class
+    if at(i) == Some(':') {
+        let (class, next) = read_number(chars, i + 1);
+        if class.is_none() {
+            return Err(bad)
+        }
+        i = next;
+    }
+
+    if at(i) != Some(']') {
+        return Err(bad)
+    }
+    Ok((atom, i + 1))
+}
+
+// Parses a SMILES string into a Molecule
+pub fn parse_smiles(smiles : &str) -> Result<Molecule, SmilesError> {
+    let chars : Vec<char> = smiles.trim().chars().collect();
+
+    let mut atoms : Vec<SmilesAtom> = Vec::new();
+    let mut bonds : Vec<SmilesBond> = Vec::new();
+
+    // the atom the next atom is bonded to
+    let mut prev : Option<usize> = None;
+    // the bond symbol waiting for the next atom or ring closure
+    let mut pending : Option<(BondSymbol, usize)> = None;
+    // the atoms the open branches start from, and where the branch was opened
+    let mut branches : Vec<(usize, usize)> = Vec::new();
+    // the open ring closures, ring number -> (atom, bond symbol, position)
+    let mut rings : HashMap<u32, (usize, Option<BondSymbol>, usize)> = HashMap::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i;
+
+        // atoms
+        let atom = if c == '[' {
+            Some(read_bracket_atom(&chars, i)?)
+        } else {
+            match read_organic_atom(&chars, i) {
+                Some((symbol, next)) => Some((SmilesAtom::new(&symbol, i)?, next)),
+                None => None,
+            }
+        };
+        if let Some((atom, next)) = atom {
+            let index = atoms.len();
+            atoms.push(atom);
+            if let Some(from) = prev {
+                // errors in the bond point at the bond symbol if there is one
+                let (symbol, position) = match pending.take() {
+                    Some((symbol, position)) => (Some(symbol), position),
+                    None => (None, position),
+                };
+                bonds.push(SmilesBond { from, to : index, symbol, position });
+            } else if let Some((_, position)) = pending {
+                return Err(SmilesError::MissingAtom { position })
+            }
+            prev = Some(index);
+            i = next;
+            continue
+        }
+
+        match c {
+            '(' => {
+                let from = prev.ok_or(SmilesError::MissingAtom { position })?;
+                if pending.is_some() {
+                    return Err(SmilesError::UnbalancedBranch { position })
+                }
+                branches.push((from, position));
+            },
+            ')' => {
+                let (from, _) = branches.pop().ok_or(SmilesError::UnbalancedBranch { position })?;
+                if let Some((_, position)) = pending {
+                    return Err(SmilesError::MissingAtom { position })
+                }
+                if chars[i - 1] == '(' {
+                    return Err(SmilesError::UnbalancedBranch { position })
+                }
+                prev = Some(from);
+            },
+            '.' => {
+                if let Some((_, position)) = pending {
+                    return Err(SmilesError::MissingAtom { position })
+                }
+                prev = None;
+            },
+            '0'..='9' | '%' => {
+                let (ring, next) = if c == '%' {
+                    // %nn is always two digits
+                    let (ring, _) = read_number(&chars[..chars.len().min(i + 3)], i + 1);
+                    match ring {
+                        Some(ring) if ring >= 10 => (ring, i + 3),
+                        _ => return Err(SmilesError::UnexpectedCharacter { position, character : c }),
+                    }
+                } else {
+                    (c.to_digit(10).unwrap(), i + 1)
+                };
+                let atom = prev.ok_or(SmilesError::MissingAtom { position })?;
+                let symbol = pending.take().map(|x| x.0);
+                match rings.remove(&ring) {
+                    Some((open_atom, open_symbol, _)) => {
+                        // a "/" or "\" is read from the atom it is written next to
+                        let bond = match (open_symbol, symbol) {
+                            (Some(a), Some(b)) if a != b && (a.direction().is_none() || b.direction().is_none()) =>
+                                return Err(SmilesError::BadRingClosure { position }),
+                            (Some(a), None) => SmilesBond { from : open_atom, to : atom, symbol : Some(a), position },
+                            (_, b) => SmilesBond { from : atom, to : open_atom, symbol : b, position },
+                        };
+                        bonds.push(bond);
+                    },
+                    None => {
+                        rings.insert(ring, (atom, symbol, position));
+                    },
+                }
+                i = next;
+                continue
+            },
+            _ => match BondSymbol::from_char(c) {
+                Some(symbol) => {
+                    if prev.is_none() || pending.is_some() {
+                        return Err(SmilesError::MissingAtom { position })
+                    }
+                    pending = Some((symbol, position));
+                },
+                None => return Err(SmilesError::UnexpectedCharacter { position, character : c }),
+            },
+        }
+        i += 1;
+    }
+
+    if let Some((_, position)) = pending {
+        return Err(SmilesError::MissingAtom { position })
+    }
+    if let Some((_, position)) = branches.pop() {
+        return Err(SmilesError::UnbalancedBranch { position })
+    }
+    if let Some((ring, (_, _, position))) = rings.into_iter().min_by_key(|(_, x)| x.2) {
+        return Err(SmilesError::UnclosedRing { position, ring })
+    }
+
+    build_molecule(atoms, bonds)
+}
+
+// Builds the Molecule once every atom and bond has been read
+fn build_molecule(atoms : Vec<SmilesAtom>, bonds : Vec<SmilesBond>) -> Result<Molecule, SmilesError> {
+    let mut molecule = Molecule::from_atoms(atoms.iter().map(|x| x.atom.clone()).collect());
+    for (i, atom) in atoms.iter().enumerate() {
+        molecule.isotopes[i] = atom.isotope;
+        molecule.charges[i] = atom.charge;
+        molecule.hydrogens[i] = atom.hydrogens;
+    }
+
+    for bond in bonds {
+        let bond_type = match bond.symbol {
+            Some(BondSymbol::Double) => 2,
+            Some(BondSymbol::Triple) => 3,
+            Some(BondSymbol::Aromatic) => AROMATIC_BOND,
+            Some(BondSymbol::Quadruple) => return Err(SmilesError::UnsupportedBond { position : bond.position }),
+            Some(_) => 1,
+            // a bond that isn't written is aromatic between two aromatic atoms
+            None if atoms[bond.from].aromatic && atoms[bond.to].aromatic => AROMATIC_BOND,
+            None => 1,
+        };
+        match molecule.try_add_bond(bond.from, bond.to, bond_type) {
+            Ok(()) => (),
+            Err(BondError::SameAtom) | Err(BondError::AlreadySet) =>
+                return Err(SmilesError::BadRingClosure { position : bond.position }),
+            Err(BondError::OutOfRange) => unreachable!(),
+        }
+        if let Some(direction) = bond.symbol.and_then(|x| x.direction()) {
+            molecule.bond_directions.push((bond.from, bond.to, direction));
+        }
+    }
+    Ok(molecule)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(molecule : &Molecule) -> Vec<&str> {
+        molecule.atoms.iter().map(|x| x.symbol()).collect()
+    }
+
+    #[test]
+    fn test_chains_and_branches() {
+        // isobutanol
+        let molecule = parse_smiles("CC(C)CO").unwrap();
+        assert_eq!(symbols(&molecule), vec!["C", "C", "C", "C", "O"]);
+        assert_eq!(molecule.get_bonds(), vec![(0, 1, 1), (1, 2, 1), (1, 3, 1), (3, 4, 1)]);
+        assert_eq!(molecule.hydrogens, vec![None; 5]);
+
+        // acetic acid, with a nested branch and a double bond
+        let molecule = parse_smiles("CC(=O)O").unwrap();
+        assert_eq!(molecule.get_bonds(), vec![(0, 1, 1), (1, 2, 2), (1, 3, 1)]);
+
+        let molecule = parse_smiles("C(C(Cl)Br)#N").unwrap();
+        assert_eq!(symbols(&molecule), vec!["C", "C", "Cl", "Br", "N"]);
+        assert_eq!(molecule.get_bonds(), vec![(0, 1, 1), (0, 4, 3), (1, 2, 1), (1, 3, 1)]);
+    }
+
+    #[test]
+    fn test_rings_and_aromatics() {
+        let cyclohexane = parse_smiles("C1CCCCC1").unwrap();
+        assert_eq!(cyclohexane.get_bonds().len(), 6);
+        assert_eq!(cyclohexane.get(0, 5), 1);
+
+        // benzoic acid
+        let molecule = parse_smiles("c1ccccc1C(=O)O").unwrap();
+        assert_eq!(symbols(&molecule), vec!["C", "C", "C", "C", "C", "C", "C", "O", "O"]);
+        assert_eq!(molecule.get(0, 5), AROMATIC_BOND);
+        assert_eq!(molecule.get(0, 1), AROMATIC_BOND);
+        assert_eq!(molecule.get(5, 6), 1);
+
+        // ring bond symbols can go on either end, and %nn ring numbers
+        let molecule = parse_smiles("C=1CCCCC1").unwrap();
+        assert_eq!(molecule.get(0, 5), 2);
+        let molecule = parse_smiles("C%12CCCCC=%12").unwrap();
+        assert_eq!(molecule.get(0, 5), 2);
+
+        // ring numbers can be reused once closed, "." separates molecules
+        let molecule = parse_smiles("C1CC1.C1CC1").unwrap();
+        assert_eq!(molecule.get_bonds().len(), 6);
+        assert_eq!(molecule.get(2, 3), 0);
+
+        // selenophene, aromatic atoms outside the organic subset need brackets
+        let molecule = parse_smiles("c1cc[se]c1").unwrap();
+        assert_eq!(molecule.atoms[3], Atom::Element(Element::Se));
+        assert_eq!(molecule.get(2, 3), AROMATIC_BOND);
+    }
+
+    #[test]
+    fn test_bracket_atoms() {
+        let molecule = parse_smiles("[13CH4]").unwrap();
+        assert_eq!(symbols(&molecule), vec!["C"]);
+        assert_eq!(molecule.isotopes, vec![13]);
+        assert_eq!(molecule.hydrogens, vec![Some(4)]);
+
+        // sodium acetate
+        let molecule = parse_smiles("CC(=O)[O-].[Na+]").unwrap();
+        assert_eq!(molecule.charges, vec![0, 0, 0, -1, 1]);
+        assert_eq!(molecule.hydrogens, vec![None, None, None, Some(0), Some(0)]);
+
+        let molecule = parse_smiles("[Fe+++].[Cu-2].[NH4+].[O--]").unwrap();
+        assert_eq!(molecule.charges, vec![3, -2, 1, -2]);
+        assert_eq!(molecule.hydrogens, vec![Some(0), Some(0), Some(4), Some(0)]);
+
+        // chirality and atom classes are read over
+        let molecule = parse_smiles("N[C@@H](C)C(=O)O").unwrap();
+        assert_eq!(symbols(&molecule), vec!["N", "C", "C", "C", "O", "O"]);
+        assert_eq!(molecule.hydrogens[1], Some(1));
+        let molecule = parse_smiles("[C@TH2H](F)(Cl)[CH3:7]").unwrap();
+        assert_eq!(symbols(&molecule), vec!["C", "F", "Cl", "C"]);
+
+        // two letter elements, and pyrrole nitrogen with its hydrogen
+        let molecule = parse_smiles("[Sc][Hf]c1cc[nH]c1").unwrap();
+        assert_eq!(&symbols(&molecule)[..3], &["Sc", "Hf", "C"]);
+        assert_eq!(molecule.hydrogens[5], Some(1));
+        assert_eq!(molecule.get(4, 5), AROMATIC_BOND);
+    }
+
+    #[test]
+    fn test_bond_directions() {
+        // trans-difluoroethene
+        let molecule = parse_smiles("F/C=C/F").unwrap();
+        assert_eq!(molecule.get_bonds(), vec![(0, 1, 1), (1, 2, 2), (2, 3, 1)]);
+        assert_eq!(molecule.bond_directions, vec![(0, 1, BondDirection::Up), (2, 3, BondDirection::Up)]);
+
+        let molecule = parse_smiles("F\\C=C/F").unwrap();
+        assert_eq!(molecule.bond_directions, vec![(0, 1, BondDirection::Down), (2, 3, BondDirection::Up)]);
+    }
+
+    #[test]
+    fn test_bad_smiles() {
+        assert_eq!(parse_smiles("CC(C").err(), Some(SmilesError::UnbalancedBranch { position : 2 }));
+        assert_eq!(parse_smiles("CC)C").err(), Some(SmilesError::UnbalancedBranch { position : 2 }));
+        assert_eq!(parse_smiles("CC()C").err(), Some(SmilesError::UnbalancedBranch { position : 3 }));
+        assert_eq!(parse_smiles("C1CC").err(), Some(SmilesError::UnclosedRing { position : 1, ring : 1 }));
+        assert_eq!(parse_smiles("C=1CCC#1").err(), Some(SmilesError::BadRingClosure { position : 7 }));
+        assert_eq!(parse_smiles("C11").err(), Some(SmilesError::BadRingClosure { position : 2 }));
+        assert_eq!(parse_smiles("C12CCC12").err(), Some(SmilesError::BadRingClosure { position : 7 }));
+        assert_eq!(parse_smiles("CC=").err(), Some(SmilesError::MissingAtom { position : 2 }));
+        assert_eq!(parse_smiles("=CC").err(), Some(SmilesError::MissingAtom { position : 0 }));
+        assert_eq!(parse_smiles("C==C").err(), Some(SmilesError::MissingAtom { position : 2 }));
+        assert_eq!(parse_smiles("C[C").err(), Some(SmilesError::BadBracketAtom { position : 1 }));
+        assert_eq!(parse_smiles("C[xy]").err(), Some(SmilesError::BadBracketAtom { position : 1 }));
+        assert_eq!(parse_smiles("CXC").err(), Some(SmilesError::UnexpectedCharacter { position : 1, character : 'X' }));
+        assert_eq!(parse_smiles("C$C").err(), Some(SmilesError::UnsupportedBond { position : 1 }));
+        assert_eq!(parse_smiles("C[Xx]").err(), Some(SmilesError::UnknownElement { position : 1, symbol : "Xx".to_string() }));
+        assert_eq!(parse_smiles("C[R]").err(), Some(SmilesError::UnknownElement { position : 1, symbol : "R".to_string() }));
+    }
+
+    #[test]
+    fn test_empty_smiles() {
+        assert_eq!(parse_smiles("").unwrap().atoms.len(), 0);
+    }
+}
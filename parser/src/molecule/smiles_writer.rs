@@ -0,0 +1,464 @@
+/**
+ * Writes Molecules as SMILES strings
+ *
+ * The canonical form ranks the atoms from their structure alone (a Morgan / CANON style
+ * refinement of atom invariants over the bonds, breaking ties between equivalent atoms one at a
+ * time), then writes the depth first walk of the molecule in rank order. The same compound gives
+ * the same string no matter what order its atoms were in. Aromatic rings are found first, so every
+ * Kekulé structure of a compound gives the same string too.
+ **/
+
+use std::collections::{HashMap, HashSet};
+
+use crate::molecule::molecule::{Molecule, BondDirection};
+use crate::molecule::aromaticity::pyrrole_hydrogens;
+
+// The bond type used for aromatic bonds, as in the .mol bond block
+static AROMATIC_BOND : i32 = 4;
+
+static ORGANIC_SUBSET : [&str; 10] = ["B", "C", "N", "O", "P", "S", "F", "Cl", "Br", "I"];
+// Elements that have a lowercase aromatic form
+static AROMATIC_ELEMENTS : [&str; 9] = ["B", "C", "N", "O", "P", "S", "Se", "As", "Te"];
+
+// degree, element, isotope, charge, radical, hydrogen count
+type AtomInvariant = (usize, &'static str, i32, i32, i32, i32);
+
+// Returns (neighbour, bond type) for every atom bonded to the atom
+fn neighbours(molecule : &Molecule, atom : usize) -> Vec<(usize, i32)> {
+    molecule.neighbours(atom).collect()
+}
+
+// Ranks the keys from 0 up, equal keys get equal ranks
+fn rank_by<T : Ord + Clone>(keys : &[T]) -> Vec<usize> {
+    let mut sorted : Vec<T> = keys.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    keys.iter().map(|x| sorted.binary_search(x).unwrap()).collect()
+}
+
+fn count_ranks(ranks : &[usize]) -> usize {
+    ranks.iter().collect::<HashSet<_>>().len()
+}
+
+// Splits atoms with the same rank by the ranks of their neighbours, until no more splits happen
+fn refine(mut ranks : Vec<usize>, neighbours : &[Vec<(usize, i32)>]) -> Vec<usize> {
+    loop {
+        let keys : Vec<(usize, Vec<(usize, i32)>)> = neighbours.iter().enumerate()
+            .map(|(i, bonds)| {
+                let mut around : Vec<(usize, i32)> = bonds.iter().map(|(j, bond)| (ranks[*j], *bond)).collect();
+                around.sort();
+                (ranks[i], around)
+            })
+            .collect();
+        let refined = rank_by(&keys);
+        if count_ranks(&refined) == count_ranks(&ranks) {
+            return refined
+        }
+        ranks = refined;
+    }
+}
+
+// The hydrogens written on each atom that aren't atoms in the molecule, the hydrogen count of a
+// SMILES bracket atom or the implicit hydrogens from the valence, with the hydrogens of pyrrole
+// style nitrogens that a .mol file's aromatic bonds leave out
+fn written_hydrogens(molecule : &Molecule) -> Vec<i32> {
+    let mut hydrogens : Vec<i32> = (0..molecule.atoms.len()).map(|x| molecule.implicit_hydrogens(x)).collect();
+    for atom in pyrrole_hydrogens(molecule) {
+        hydrogens[atom] += 1;
+    }
+    hydrogens
+}
+
+// Gives every atom a distinct rank that only depends on the structure of the molecule
+pub fn canonical_ranks(molecule : &Molecule) -> Vec<usize> {
+    let n = molecule.atoms.len();
+    let neighbours : Vec<Vec<(usize, i32)>> = (0..n).map(|i| neighbours(molecule, i)).collect();
+    let hydrogens = written_hydrogens(molecule);
+
+    // the atom invariants, low degree atoms rank first so the walk starts at the end of a chain
+    let invariants : Vec<AtomInvariant> = (0..n)
+        .map(|i| (neighbours[i].len(), atom_symbol(molecule, i), molecule.isotopes[i],
+            molecule.charges[i], molecule.radicals[i], hydrogens[i]))
+        .collect();
+    let mut ranks = refine(rank_by(&invariants), &neighbours);
+
+    // atoms that still share a rank are symmetric, so picking any one of them to go first gives
+    // the same result
+    while count_ranks(&ranks) < n {
+        let mut counts : HashMap<usize, usize> = HashMap::new();
+        for rank in &ranks {
+            *counts.entry(*rank).or_insert(0) += 1;
+        }
+        let tied = (0..n).filter(|x| counts.get(x).copied().unwrap_or(0) > 1).min().unwrap();
+        let first = ranks.iter().position(|x| *x == tied).unwrap();
+        let split : Vec<(usize, bool)> = ranks.iter().enumerate()
+            .map(|(i, x)| (*x, *x == tied && i != first))
+            .collect();
+        ranks = refine(rank_by(&split), &neighbours);
+    }
+    ranks
+}
+
+// The spanning tree of the depth first walk, and the bonds that close rings
+struct Walk {
+    // the atoms in the order they are written
+    order : Vec<usize>,
+    children : Vec<Vec<usize>>,
+    // ring closing bonds by atom, with the atom at the other end
+    ring_bonds : Vec<Vec<usize>>,
+}
+
+fn walk(atom : usize, parent : Option<usize>, neighbours : &[Vec<(usize, i32)>], walk_state : &mut Walk,
+    visited : &mut Vec<bool>, seen_rings : &mut HashSet<(usize, usize)>) {
+
+    visited[atom] = true;
+    walk_state.order.push(atom);
+    for (next, _) in &neighbours[atom] {
+        let next = *next;
+        if Some(next) == parent {
+            continue
+        }
+        if visited[next] {
+            if seen_rings.insert((atom.min(next), atom.max(next))) {
+                walk_state.ring_bonds[atom].push(next);
+                walk_state.ring_bonds[next].push(atom);
+            }
+            continue
+        }
+        walk_state.children[atom].push(next);
+        walk(next, Some(atom), neighbours, walk_state, visited, seen_rings);
+    }
+}
+
+fn is_aromatic(molecule : &Molecule, atom : usize) -> bool {
+    AROMATIC_ELEMENTS.contains(&molecule.atoms[atom].symbol())
+        && molecule.neighbours(atom).any(|(_, bond)| bond == AROMATIC_BOND)
+}
+
+// The element symbol, every query atom is written as "*" so they are all ranked the same
+fn atom_symbol(molecule : &Molecule, atom : usize) -> &'static str {
+    molecule.atoms[atom].element().map_or("*", |x| x.symbol())
+}
+
+// The atom as it is written, with brackets if the organic subset can't describe it
+// hydrogens are the hydrogens on the atom that aren't atoms in the molecule. SMILES has no
+// radicals, an atom with one is written in brackets with its hydrogens, the methyl radical is [CH3]
+fn atom_text(molecule : &Molecule, atom : usize, hydrogens : i32) -> String {
+    let element = atom_symbol(molecule, atom);
+    if element == "*" {
+        return "*".to_string()
+    }
+    let aromatic = is_aromatic(molecule, atom);
+    let symbol = if aromatic { element.to_lowercase() } else { element.to_string() };
+
+    let charge = molecule.charges[atom];
+    let isotope = molecule.isotopes[atom];
+    // an aromatic nitrogen's hydrogen can't be worked out from its valence, pyrrole is c1cc[nH]c1
+    let organic = ORGANIC_SUBSET.contains(&element) && charge == 0 && isotope == 0
+        && molecule.radicals[atom] == 0 && molecule.hydrogens[atom].is_none()
+        && !(aromatic && element == "N" && hydrogens > 0);
+    if organic {
+        return symbol
+    }
+
+    let mut out = "[".to_string();
+    if isotope != 0 {
+        out += &isotope.to_string();
+    }
+    out += &symbol;
+    match hydrogens {
+        1 => out += "H",
+        h if h > 1 => out += &format!("H{}", h),
+        _ => (),
+    }
+    match charge {
+        0 => (),
+        1 => out += "+",
+        -1 => out += "-",
+        c if c > 0 => out += &format!("+{}", c),
+        c => out += &format!("-{}", -c),
+    }
+    out += "]";
+    out
+}
+
+// The bond as it is written between two atoms, from atom a to atom b
+fn bond_text(molecule : &Molecule, a : usize, b : usize) -> String {
+    for (from, to, direction) in &molecule.bond_directions {
+        let same_way = (*from, *to) == (a, b);
+        if same_way || (*from, *to) == (b, a) {
+            // "F/C=C/F" read backwards is "F\C=C\F"
+            return match (direction, same_way) {
+                (BondDirection::Up, true) | (BondDirection::Down, false) => "/".to_string(),
+                _ => "\\".to_string(),
+            }
+        }
+    }
+    match molecule.get(a, b) {
+        // a single bond between two aromatic atoms would be read as aromatic
+        1 if is_aromatic(molecule, a) && is_aromatic(molecule, b) => "-".to_string(),
+        2 => "=".to_string(),
+        3 => "#".to_string(),
+        x if x == AROMATIC_BOND && !(is_aromatic(molecule, a) && is_aromatic(molecule, b)) => ":".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn ring_number_text(ring : usize) -> String {
+    if ring < 10 { ring.to_string() } else { format!("%{}", ring) }
+}
+
+// Writes the atom and everything below it in the walk
+fn write_atom(molecule : &Molecule, atom : usize, walk_state : &Walk, hydrogens : &[i32], written : &mut Vec<bool>,
+    open_rings : &mut HashMap<(usize, usize), usize>, out : &mut String) {
+
+    written[atom] = true;
+    *out += &atom_text(molecule, atom, hydrogens[atom]);
+
+    for other in &walk_state.ring_bonds[atom] {
+        let other = *other;
+        if written[other] {
+            // close the ring, the bond symbol was written when it was opened
+            let ring = open_rings.remove(&(other, atom)).unwrap();
+            *out += &ring_number_text(ring);
+        } else {
+            // open the ring with the lowest free number
+            let ring = (1..).find(|x| !open_rings.values().any(|y| y == x)).unwrap();
+            open_rings.insert((atom, other), ring);
+            *out += &bond_text(molecule, atom, other);
+            *out += &ring_number_text(ring);
+        }
+    }
+
+    let children = &walk_state.children[atom];
+    for (i, child) in children.iter().enumerate() {
+        let branch = i + 1 < children.len();
+        if branch {
+            *out += "(";
+        }
+        *out += &bond_text(molecule, atom, *child);
+        write_atom(molecule, *child, walk_state, hydrogens, written, open_rings, out);
+        if branch {
+            *out += ")";
+        }
+    }
+}
+
+// Writes a Molecule as SMILES, disconnected parts are joined with "."
+// The canonical form is the same for every atom order, otherwise the atoms are walked in the
+// order they are in the Molecule
+pub fn write_smiles(molecule : &Molecule, canonical : bool) -> String {
+    // the canonical form is written from the aromatic form, so every Kekulé structure of a
+    // compound gives the same string
+    let aromatic;
+    let molecule = if canonical {
+        let mut copy = molecule.clone();
+        copy.aromatize();
+        aromatic = copy;
+        &aromatic
+    } else {
+        molecule
+    };
+    let n = molecule.atoms.len();
+    let ranks : Vec<usize> = if canonical { canonical_ranks(molecule) } else { (0..n).collect() };
+
+    // every atom visits its neighbours from the lowest rank up
+    let neighbours : Vec<Vec<(usize, i32)>> = (0..n)
+        .map(|i| {
+            let mut bonds = neighbours(molecule, i);
+            bonds.sort_by_key(|(j, _)| ranks[*j]);
+            bonds
+        })
+        .collect();
+    let mut by_rank : Vec<usize> = (0..n).collect();
+    by_rank.sort_by_key(|x| ranks[*x]);
+
+    let mut walk_state = Walk { order : Vec::new(), children : vec![Vec::new(); n], ring_bonds : vec![Vec::new(); n] };
+    let mut visited = vec![false; n];
+    let mut seen_rings : HashSet<(usize, usize)> = HashSet::new();
+    let mut roots : Vec<usize> = Vec::new();
+    for atom in by_rank {
+        if !visited[atom] {
+            roots.push(atom);
+            walk(atom, None, &neighbours, &mut walk_state, &mut visited, &mut seen_rings);
+        }
+    }
+
+    // ring bonds are written in the order of the atoms at their other end
+    let position : HashMap<usize, usize> = walk_state.order.iter().enumerate().map(|(i, x)| (*x, i)).collect();
+    for ring_bonds in walk_state.ring_bonds.iter_mut() {
+        ring_bonds.sort_by_key(|x| position[x]);
+    }
+
+    let hydrogens = written_hydrogens(molecule);
+    let mut written = vec![false; n];
+    let mut open_rings : HashMap<(usize, usize), usize> = HashMap::new();
+    let parts : Vec<String> = roots.iter()
+        .map(|root| {
+            let mut out = String::new();
+            write_atom(molecule, *root, &walk_state, &hydrogens, &mut written, &mut open_rings, &mut out);
+            out
+        })
+        .collect();
+    parts.join(".")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::{parse_mol, parse_mol_str};
+    use crate::molecule::smiles::parse_smiles;
+    use crate::molecule::writer::mol_to_string;
+
+    // Returns a copy of the molecule with its atoms in a different order
+    fn shuffle(molecule : &Molecule, order : &[usize]) -> Molecule {
+        let mut out = Molecule::from_atoms(order.iter().map(|x| molecule.atoms[*x].clone()).collect());
+        let new_index : HashMap<usize, usize> = order.iter().enumerate().map(|(i, x)| (*x, i)).collect();
+        for (x, y, bond) in molecule.get_bonds() {
+            out.add_bond(new_index[&x], new_index[&y], bond);
+        }
+        for (i, old) in order.iter().enumerate() {
+            out.charges[i] = molecule.charges[*old];
+            out.isotopes[i] = molecule.isotopes[*old];
+            out.hydrogens[i] = molecule.hydrogens[*old];
+        }
+        out
+    }
+
+    #[test]
+    fn test_write_smiles() {
+        assert_eq!(write_smiles(&parse_smiles("CC(=O)O").unwrap(), false), "CC(=O)O");
+        assert_eq!(write_smiles(&parse_smiles("C1CCCCC1").unwrap(), false), "C1CCCCC1");
+        assert_eq!(write_smiles(&parse_smiles("c1ccccc1-c1ccccc1").unwrap(), false), "c1ccccc1-c1ccccc1");
+        assert_eq!(write_smiles(&parse_smiles("[13CH4].[Na+].[O-2]").unwrap(), false), "[13CH4].[Na+].[O-2]");
+        assert_eq!(write_smiles(&parse_smiles("F/C=C\\F").unwrap(), false), "F/C=C\\F");
+        assert_eq!(write_smiles(&parse_smiles("c1cc[nH]c1").unwrap(), false), "c1cc[nH]c1");
+    }
+
+    #[test]
+    fn test_canonical_same_for_every_order() {
+        let smiles = ["OC(=O)c1ccccc1", "c1ccc(cc1)C(O)=O", "O=C(O)c1ccccc1", "C(=O)(O)c1ccccc1"];
+        let canonical : Vec<String> = smiles.iter().map(|x| write_smiles(&parse_smiles(x).unwrap(), true)).collect();
+        for x in &canonical {
+            assert_eq!(x, &canonical[0]);
+        }
+
+        let smiles = ["CCO.[Na+].[Cl-]", "[Cl-].OCC.[Na+]"];
+        let canonical : Vec<String> = smiles.iter().map(|x| write_smiles(&parse_smiles(x).unwrap(), true)).collect();
+        assert_eq!(canonical[0], canonical[1]);
+
+        // the two mol files of benzoic acid, and every rotation of their atoms
+        let v2000 = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap();
+        let v3000 = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid, V3000.mol")).unwrap();
+        let expected = write_smiles(&v2000, true);
+        assert_eq!(write_smiles(&v3000, true), expected);
+        let n = v2000.atoms.len();
+        for shift in 0..n {
+            let order : Vec<usize> = (0..n).map(|x| (x + shift) % n).rev().collect();
+            assert_eq!(write_smiles(&shuffle(&v2000, &order), true), expected);
+        }
+    }
+
+    #[test]
+    fn test_canonical_kekule_structures() {
+        // o-xylene with either set of double bonds, and aromatic
+        let smiles = ["CC1=CC=CC=C1C", "CC1=C(C)C=CC=C1", "Cc1ccccc1C"];
+        let canonical : Vec<String> = smiles.iter().map(|x| write_smiles(&parse_smiles(x).unwrap(), true)).collect();
+        assert_eq!(canonical[0], "Cc1ccccc1C");
+        for x in &canonical {
+            assert_eq!(x, &canonical[0]);
+        }
+        // .mol files of the Kekulé structures too
+        assert_eq!(smiles_from_mol_file(&parse_smiles("CC1=C(C)C=CC=C1").unwrap()), canonical[0]);
+        // only the canonical form is aromatized
+        assert_eq!(write_smiles(&parse_smiles("CC1=CC=CC=C1C").unwrap(), false), "CC1=CC=CC=C1C");
+        assert_eq!(write_smiles(&parse_smiles("c1cc[nH]c1").unwrap(), true), write_smiles(&parse_smiles("C1=CNC=C1").unwrap(), true));
+    }
+
+    #[test]
+    fn test_round_trip_test_files() {
+        for entry in std::fs::read_dir(TEST_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().unwrap() != "mol" {
+                continue
+            }
+            let molecule = parse_mol(path.to_str().unwrap()).unwrap();
+            let smiles = write_smiles(&molecule, true);
+            let reread = parse_smiles(&smiles).unwrap();
+            assert_eq!(molecule.atoms.len(), reread.atoms.len());
+            assert_eq!(molecule.get_bonds().len(), reread.get_bonds().len());
+            // writing the parsed SMILES again has to give the same string
+            assert_eq!(write_smiles(&reread, true), smiles);
+        }
+    }
+
+    #[test]
+    fn test_symmetric_ranks() {
+        // every carbon in benzene is equivalent, the ranks still have to be distinct
+        let ranks = canonical_ranks(&parse_smiles("c1ccccc1").unwrap());
+        let mut sorted = ranks.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    // Writes the molecule as a .mol file, reads it back, and writes that as SMILES
+    fn smiles_from_mol_file(molecule : &Molecule) -> String {
+        write_smiles(&parse_mol_str(&mol_to_string(molecule)).unwrap(), true)
+    }
+
+    #[test]
+    fn test_mol_file_hydrogens() {
+        let mut ammonium = Molecule::new(vec!["N"]);
+        ammonium.charges[0] = 1;
+        let silane = Molecule::new(vec!["Si"]);
+        let mut methane = Molecule::new(vec!["C"]);
+        methane.isotopes[0] = 13;
+        let mut methyl = Molecule::new(vec!["C"]);
+        methyl.radicals[0] = 2;
+        let mut acetate = Molecule::new(vec!["C", "C", "O", "O"]);
+        acetate.add_bond(0, 1, 1);
+        acetate.add_bond(1, 2, 2);
+        acetate.add_bond(1, 3, 1);
+        acetate.charges[3] = -1;
+        acetate.isotopes[2] = 18;
+        // pyrrole with aromatic bonds, which don't say the nitrogen has a hydrogen
+        let mut pyrrole = Molecule::new(vec!["N", "C", "C", "C", "C"]);
+        for i in 0..5 {
+            pyrrole.add_bond(i, (i + 1) % 5, 4);
+        }
+
+        assert_eq!(smiles_from_mol_file(&ammonium), "[NH4+]");
+        assert_eq!(smiles_from_mol_file(&silane), "[SiH4]");
+        assert_eq!(smiles_from_mol_file(&methane), "[13CH4]");
+        assert_eq!(smiles_from_mol_file(&methyl), "[CH3]");
+        assert_eq!(smiles_from_mol_file(&pyrrole), "c1cc[nH]c1");
+        assert_eq!(smiles_from_mol_file(&acetate), write_smiles(&parse_smiles("CC([O-])=[18O]").unwrap(), true));
+        // only the nitrogen that leaves its ring system without a Kekulé structure gets a hydrogen
+        for smiles in ["c1ccc2[nH]ccc2c1", "c1ccncc1-c1cc[nH]c1", "c1c[nH]cn1"].iter() {
+            let mut molecule = parse_smiles(smiles).unwrap();
+            let expected = write_smiles(&molecule, true);
+            molecule.hydrogens = vec![None; molecule.atoms.len()];
+            assert_eq!(smiles_from_mol_file(&molecule), expected);
+        }
+
+        // reading the SMILES back gives the same atoms, charges, isotopes and hydrogens, and the
+        // pyrrole nitrogen gets its hydrogen
+        let formulas = ["H4N", "H4Si", "CH4", "CH3", "C2H3O2", "C4H5N"];
+        for (molecule, formula) in [&ammonium, &silane, &methane, &methyl, &acetate, &pyrrole].iter().zip(formulas.iter()) {
+            let from_mol_file = parse_mol_str(&mol_to_string(molecule)).unwrap();
+            let reread = parse_smiles(&write_smiles(&from_mol_file, true)).unwrap();
+            assert_eq!(&reread.formula(), formula);
+            let mut charges = reread.charges.clone();
+            let mut isotopes = reread.isotopes.clone();
+            charges.sort();
+            isotopes.sort();
+            let mut expected = (molecule.charges.clone(), molecule.isotopes.clone());
+            expected.0.sort();
+            expected.1.sort();
+            assert_eq!((charges, isotopes), expected);
+            assert!(reread.clone().kekulize().is_ok());
+        }
+    }
+}
@@ -0,0 +1,390 @@
+/**
+ * Parses the V3000 connection table of a .mol file
+ *
+ * V3000 files have a counts line ending in "V3000", and keep the atoms and bonds in blocks of
+ * "M  V30 " lines instead of fixed width columns:
+ *
+ * M  V30 BEGIN CTAB
+ * M  V30 COUNTS 9 9 0 0 0
+ * M  V30 BEGIN ATOM
+ * M  V30 1 C 2.0186 1.7158 0 0
+ * ...
+ * M  V30 END ATOM
+ * M  V30 BEGIN BOND
+ * M  V30 1 2 1 2
+ * ...
+ * M  V30 END BOND
+ * M  V30 END CTAB
+ * M  END
+ **/
+
+use std::collections::HashMap;
+
+use crate::molecule::molecule::{Molecule, Atom, QueryAtom, AtomList};
+use crate::molecule::element::Element;
+use crate::molecule::error::MolParseError;
+use crate::molecule::parser::{MolLines, add_parsed_bond, hydrogen_isotope};
+
+static V30_PREFIX : &str = "M  V30 ";
+
+// A single V3000 line, after joining any continuation lines
+struct V30Line<'a> {
+    // line number of the first line
+    line_no : usize,
+    // the text of the first line, for error messages
+    text : &'a str,
+    // the content after "M  V30 ", or None if the line is not a V3000 line
+    content : Option<String>,
+}
+
+// Reads the next line, joining the lines that are continued with a trailing "-"
+fn next_v30_line<'a>(lines : &mut MolLines<'a>) -> Option<V30Line<'a>> {
+    let text = lines.next_line()?;
+    let line_no = lines.line_no;
+    if !text.starts_with(V30_PREFIX) {
+        return Some(V30Line { line_no, text, content : None })
+    }
+    let mut content = text[V30_PREFIX.len()..].trim_end().to_string();
+    while content.ends_with('-') {
+        content.pop();
+        match lines.next_line() {
+            Some(next) if next.starts_with(V30_PREFIX) => {
+                content += next[V30_PREFIX.len()..].trim_end();
+            },
+            _ => return Some(V30Line { line_no, text, content : None }),
+        }
+    }
+    Some(V30Line { line_no, text, content : Some(content) })
+}
+
+// Splits a V3000 line on whitespace, keeping "quoted strings", (parenthesized) and [bracketed]
+// lists together
+fn tokenize(content : &str) -> Vec<&str> {
+    let mut tokens : Vec<&str> = Vec::new();
+    let mut start : Option<usize> = None;
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in content.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' | '[' if !quoted => depth += 1,
+            ')' | ']' if !quoted && depth > 0 => depth -= 1,
+            _ => {},
+        }
+        if c.is_whitespace() && !quoted && depth == 0 {
+            if let Some(s) = start {
+                tokens.push(&content[s..i]);
+                start = None;
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&content[s..]);
+    }
+    tokens
+}
+
+// A single atom from the atom block
+struct V30Atom {
+    index : i32,
+    // None if the symbol isn't an element or query atom
+    atom : Option<Atom>,
+    coordinate : [f32; 3],
+    charge : i32,
+    isotope : i32,
+    radical : i32,
+}
+
+// parses a single atom line, "index type x y z aamap [KEY=VALUE ...]"
+// The type is an element, or an atom list "[N,O]" or "NOT [N,O]"
+// Only the CHG, MASS and RAD properties are kept
+fn parse_atom(content : &str) -> Option<V30Atom> {
+    let mut tokens = tokenize(content);
+    let exclude = tokens.len() > 2 && tokens[1] == "NOT";
+    if exclude {
+        tokens.remove(1);
+    }
+    if tokens.len() < 6 {
+        return None
+    }
+    let mut isotope = 0;
+    let atom_type = if tokens[1].starts_with('[') && tokens[1].ends_with(']') {
+        let elements : Option<Vec<Element>> = tokens[1][1..tokens[1].len() - 1].split(',')
+            .map(|x| Element::from_symbol(x.trim()))
+            .collect();
+        elements.map(|elements| Atom::Query(QueryAtom::List(AtomList { exclude, elements })))
+    } else if exclude {
+        return None
+    } else {
+        let symbol = tokens[1].trim_matches('"');
+        match hydrogen_isotope(symbol) {
+            Some(x) => { isotope = x; Some(Atom::Element(Element::H)) },
+            None => Atom::from_symbol(symbol),
+        }
+    };
+    let index = tokens[0].parse::<i32>().ok()?;
+    let mut coordinate = [0.0; 3];
+    for i in 0..3 {
+        coordinate[i] = tokens[2 + i].parse::<f32>().ok()?;
+    }
+    tokens[5].parse::<i32>().ok()?;
+    let mut atom = V30Atom { index, atom : atom_type, coordinate, charge : 0, isotope, radical : 0 };
+    for property in &tokens[6..] {
+        let mut split = property.splitn(2, '=');
+        let (key, value) = (split.next()?, split.next()?);
+        match key {
+            "CHG" => atom.charge = value.parse::<i32>().ok()?,
+            "MASS" => atom.isotope = value.parse::<i32>().ok()?,
+            "RAD" => atom.radical = value.parse::<i32>().ok()?,
+            _ => {},
+        }
+    }
+    Some(atom)
+}
+
+// parses a single bond line, "index type atom1 atom2 [KEY=VALUE ...]"
+// returns the bond type and the two atom indices
+fn parse_bond(content : &str) -> Option<(i32, i32, i32)> {
+    let tokens = tokenize(content);
+    if tokens.len() < 4 {
+        return None
+    }
+    tokens[0].parse::<i32>().ok()?;
+    let bond_type = tokens[1].parse::<i32>().ok()?;
+    let atom1 = tokens[2].parse::<i32>().ok()?;
+    let atom2 = tokens[3].parse::<i32>().ok()?;
+    Some((bond_type, atom1, atom2))
+}
+
+// Parses "COUNTS na nb nsg n3d chiral", returning the number of atoms and bonds
+fn parse_counts(content : &str) -> Option<(usize, usize)> {
+    let tokens = tokenize(content);
+    if tokens.len() < 3 || tokens[0] != "COUNTS" {
+        return None
+    }
+    Some((tokens[1].parse::<usize>().ok()?, tokens[2].parse::<usize>().ok()?))
+}
+
+// Parses the atom block, from after "BEGIN ATOM" up to and including "END ATOM"
+// returns the atoms in file order, and a map of the file's atom indices to their position
+fn parse_atom_block(lines : &mut MolLines, num_atoms : usize) -> Result<(Vec<V30Atom>, HashMap<i32, usize>), MolParseError> {
+    let mut atoms : Vec<V30Atom> = Vec::new();
+    let mut indices : HashMap<i32, usize> = HashMap::new();
+    loop {
+        let line = match next_v30_line(lines) {
+            Some(x) => x,
+            None => return Err(MolParseError::BadAtomLine { line : lines.line_no + 1, text : String::new() }),
+        };
+        let bad_atom = || MolParseError::BadAtomLine { line : line.line_no, text : line.text.to_string() };
+        let content = line.content.as_ref().ok_or_else(bad_atom)?;
+        if content.trim() == "END ATOM" {
+            if atoms.len() != num_atoms {
+                return Err(bad_atom())
+            }
+            return Ok((atoms, indices))
+        }
+        let atom = parse_atom(content).ok_or_else(bad_atom)?;
+        if atom.atom.is_none() {
+            return Err(MolParseError::UnknownElement { line : line.line_no, text : line.text.to_string() })
+        }
+        // Atom indices must be unique, since bonds refer to them
+        if indices.insert(atom.index, atoms.len()).is_some() {
+            return Err(bad_atom())
+        }
+        atoms.push(atom);
+    }
+}
+
+// Parses the bond block, from after "BEGIN BOND" up to and including "END BOND"
+fn parse_bond_block(lines : &mut MolLines, num_bonds : usize, indices : &HashMap<i32, usize>,
+    molecule : &mut Molecule) -> Result<(), MolParseError> {
+    let mut count = 0;
+    loop {
+        let line = match next_v30_line(lines) {
+            Some(x) => x,
+            None => return Err(MolParseError::BadBondLine { line : lines.line_no + 1, text : String::new() }),
+        };
+        let bad_bond = || MolParseError::BadBondLine { line : line.line_no, text : line.text.to_string() };
+        let content = line.content.as_ref().ok_or_else(bad_bond)?;
+        if content.trim() == "END BOND" {
+            if count != num_bonds {
+                return Err(bad_bond())
+            }
+            return Ok(())
+        }
+        let (bond_type, atom1, atom2) = parse_bond(content).ok_or_else(bad_bond)?;
+        // Bonds refer to the atom index column, not the position of the atom in the block
+        let (x, y) = match (indices.get(&atom1), indices.get(&atom2)) {
+            (Some(x), Some(y)) => (*x, *y),
+            _ => return Err(MolParseError::AtomIndexOutOfRange { line : line.line_no, text : line.text.to_string() }),
+        };
+        add_parsed_bond(molecule, x, y, bond_type, line.line_no, line.text)?;
+        count += 1;
+    }
+}
+
+// Skips a block that isn't needed to build the Molecule (SGROUP, COLLECTION, ...), up to and
+// including its END line
+fn skip_block(lines : &mut MolLines, name : &str) {
+    let end = "END ".to_owned() + name;
+    while let Some(line) = next_v30_line(lines) {
+        if line.content.is_some_and(|x| x.trim() == end) {
+            return
+        }
+    }
+}
+
+// Constructs a Molecule from the V3000 connection table, starting from the line after the counts
+// line. Parses up to and including "M  V30 END CTAB"
+pub(crate) fn parse_v3000_ctab(lines : &mut MolLines) -> Result<Molecule, MolParseError> {
+    // "BEGIN CTAB" and "COUNTS" take the place of the V2000 counts line
+    let line = next_v30_line(lines).ok_or(
+        MolParseError::TruncatedHeader { line : lines.line_no + 1, text : String::new() })?;
+    if line.content.as_ref().map(|x| x.trim()) != Some("BEGIN CTAB") {
+        return Err(MolParseError::BadCountsLine { line : line.line_no, text : line.text.to_string() })
+    }
+    let line = next_v30_line(lines).ok_or(
+        MolParseError::TruncatedHeader { line : lines.line_no + 1, text : String::new() })?;
+    let (num_atoms, num_bonds) = match line.content.as_ref().and_then(|x| parse_counts(x)) {
+        Some(x) => x,
+        None => return Err(MolParseError::BadCountsLine { line : line.line_no, text : line.text.to_string() }),
+    };
+
+    let mut molecule : Option<Molecule> = None;
+    let mut indices : HashMap<i32, usize> = HashMap::new();
+    let mut bonds_read = false;
+    while let Some(line) = next_v30_line(lines) {
+        let content = match &line.content {
+            Some(x) => x.trim(),
+            None => {
+                // "M  END" without "END CTAB", the table is finished anyway
+                if line.text.starts_with("M  END") {
+                    break
+                }
+                continue
+            },
+        };
+        if content == "END CTAB" {
+            break
+        } else if content == "BEGIN ATOM" {
+            let (atoms, atom_indices) = parse_atom_block(lines, num_atoms)?;
+            let mut mol = Molecule::from_atoms(atoms.iter().map(|x| x.atom.clone().unwrap()).collect());
+            for (i, atom) in atoms.iter().enumerate() {
+                mol.charges[i] = atom.charge;
+                mol.isotopes[i] = atom.isotope;
+                mol.radicals[i] = atom.radical;
+                mol.set_coordinate(i, atom.coordinate);
+            }
+            molecule = Some(mol);
+            indices = atom_indices;
+        } else if content == "BEGIN BOND" {
+            // The bonds can't refer to any atoms if the atom block hasn't been read yet
+            let mol = match molecule.as_mut() {
+                Some(x) => x,
+                None => return Err(MolParseError::BadBondLine { line : line.line_no, text : line.text.to_string() }),
+            };
+            parse_bond_block(lines, num_bonds, &indices, mol)?;
+            bonds_read = true;
+        } else if let Some(name) = content.strip_prefix("BEGIN ") {
+            skip_block(lines, name.trim());
+        }
+    }
+
+    // The bond block is left out when there are no bonds, if COUNTS has bonds it is missing and
+    // the table ended where it should have been
+    if num_bonds > 0 && !bonds_read {
+        return Err(MolParseError::BadBondLine { line : lines.line_no, text : lines.last_line.to_string() })
+    }
+    match molecule {
+        Some(x) => Ok(x),
+        // a molecule with no atoms doesn't need an atom block
+        None if num_atoms == 0 => Ok(Molecule::from_atoms(Vec::new())),
+        None => Err(MolParseError::BadAtomLine { line : lines.line_no + 1, text : String::new() }),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::{parse_mol, parse_mol_str};
+
+    #[test]
+    fn test_v3000_matches_v2000() {
+        let v2000 = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid.mol")).unwrap();
+        let v3000 = parse_mol(&(TEST_DIR.to_owned() + "Benzoic acid, V3000.mol")).unwrap();
+        assert_eq!(v2000.atoms, v3000.atoms);
+        assert_eq!(v2000.get_matrix(), v3000.get_matrix());
+        assert_eq!(v2000.get_coordinates(), v3000.get_coordinates());
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("1 \"Cl\" 0 0 0 0 CHG=-1"), vec!["1", "\"Cl\"", "0", "0", "0", "0", "CHG=-1"]);
+        assert_eq!(tokenize("  1 Q 0 0 0 0 RGROUPS=(1 1)  "), vec!["1", "Q", "0", "0", "0", "0", "RGROUPS=(1 1)"]);
+        assert_eq!(tokenize("1 NOT [N, O] 0 0 0 0"), vec!["1", "NOT", "[N, O]", "0", "0", "0", "0"]);
+    }
+
+    #[test]
+    fn test_continuation_and_indices() {
+        // atoms are numbered out of order, and the bond line is split over two lines
+        let contents = "\n\n\n  0  0  0     0  0            999 V3000\n\
+            M  V30 BEGIN CTAB\nM  V30 COUNTS 2 1 0 0 0\nM  V30 BEGIN ATOM\n\
+            M  V30 10 C 0 0 0 0\nM  V30 20 O 1.2 0 0 0\nM  V30 END ATOM\n\
+            M  V30 BEGIN BOND\nM  V30 1 2 20 -\nM  V30 10\nM  V30 END BOND\n\
+            M  V30 END CTAB\nM  END\n";
+        let molecule = parse_mol_str(contents).unwrap();
+        assert_eq!(molecule.atoms, vec![Atom::Element(Element::C), Atom::Element(Element::O)]);
+        assert_eq!(molecule.get(0, 1), 2);
+    }
+
+    #[test]
+    fn test_atom_properties() {
+        let contents = "\n\n\n  0  0  0     0  0            999 V3000\n\
+            M  V30 BEGIN CTAB\nM  V30 COUNTS 2 0 0 0 0\nM  V30 BEGIN ATOM\n\
+            M  V30 1 Na 0 0 0 0 CHG=1\nM  V30 2 \"Cl\" 1.2 0 0 0 CHG=-1 MASS=37 RAD=2\nM  V30 END ATOM\n\
+            M  V30 END CTAB\nM  END\n";
+        let molecule = parse_mol_str(contents).unwrap();
+        assert_eq!(molecule.atoms, vec![Atom::Element(Element::Na), Atom::Element(Element::Cl)]);
+        assert_eq!(molecule.charges, vec![1, -1]);
+        assert_eq!(molecule.isotopes, vec![0, 37]);
+        assert_eq!(molecule.radicals, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_atom_lists() {
+        let atom = parse_atom("3 NOT [N,O] 0 0 0 0").unwrap();
+        assert_eq!(atom.atom, Some(Atom::Query(QueryAtom::List(AtomList { exclude : true, elements : vec![Element::N, Element::O] }))));
+        let atom = parse_atom("3 [Cl,Br,I] 0 0 0 0").unwrap();
+        assert_eq!(atom.atom.unwrap().symbol(), "L");
+        assert!(parse_atom("3 NOT C 0 0 0 0").is_none());
+        assert_eq!(parse_atom("3 [Cl,Xx] 0 0 0 0").unwrap().atom, None);
+    }
+
+    #[test]
+    fn test_bad_v3000() {
+        let header = "\n\n\n  0  0  0     0  0            999 V3000\nM  V30 BEGIN CTAB\n";
+        assert!(matches!(parse_mol_str(&(header.to_owned() + "M  V30 COUNTS x 1 0 0 0\n")),
+            Err(MolParseError::BadCountsLine { line : 6, .. })));
+
+        let atoms = "M  V30 COUNTS 2 1 0 0 0\nM  V30 BEGIN ATOM\nM  V30 1 C 0 0 0 0\nM  V30 2 C 0 0 0 0\nM  V30 END ATOM\n";
+        assert!(matches!(parse_mol_str(&(header.to_owned() + "M  V30 COUNTS 2 1 0 0 0\nM  V30 BEGIN ATOM\nM  V30 1 C zero 0 0 0\n")),
+            Err(MolParseError::BadAtomLine { line : 8, .. })));
+        assert!(matches!(parse_mol_str(&(header.to_owned() + atoms + "M  V30 BEGIN BOND\nM  V30 1 1 1 3\nM  V30 END BOND\n")),
+            Err(MolParseError::AtomIndexOutOfRange { line : 12, .. })));
+        assert!(matches!(parse_mol_str(&(header.to_owned() + atoms + "M  V30 BEGIN BOND\nM  V30 1 1 1 1\nM  V30 END BOND\n")),
+            Err(MolParseError::BadBondLine { line : 12, .. })));
+        assert!(matches!(parse_mol_str(&(header.to_owned() + atoms + "M  V30 BEGIN BOND\nM  V30 END BOND\n")),
+            Err(MolParseError::BadBondLine { line : 12, .. })));
+        // COUNTS has a bond but there is no bond block
+        assert!(matches!(parse_mol_str(&(header.to_owned() + atoms + "M  V30 END CTAB\nM  END\n")),
+            Err(MolParseError::BadBondLine { line : 11, .. })));
+        assert!(matches!(parse_mol_str(&(header.to_owned() + &atoms.replace("COUNTS 2 1", "COUNTS 3 1"))),
+            Err(MolParseError::BadAtomLine { line : 10, .. })));
+        assert!(matches!(parse_mol_str(&(header.to_owned() + &atoms.replace("2 C", "2 Cx"))),
+            Err(MolParseError::UnknownElement { line : 9, .. })));
+    }
+}
@@ -0,0 +1,248 @@
+/**
+ * Implicit hydrogens
+ *
+ * .mol files and SMILES written without brackets leave out most hydrogens, each atom gets as many
+ * as it needs to reach the lowest of its default valences that fits its bonds. Charges move the
+ * valences the way they move the electrons: N+ takes 4 bonds like carbon, O- takes 1, C+ and C-
+ * take 3, B- takes 4.
+ *
+ * Hydrogens can be turned into atoms and back, so a molecule drawn with its hydrogens and the
+ * same molecule drawn without them end up the same.
+ **/
+
+use crate::molecule::molecule::{Molecule, Atom};
+use crate::molecule::element::Element;
+
+// The bond type used for aromatic bonds, as in the .mol bond block
+static AROMATIC_BOND : i32 = 4;
+
+// Elements that have lone pairs, their lowest valence is 8 minus their valence electrons
+static LONE_PAIR_ELEMENTS : [Element; 15] = [
+    Element::N, Element::P, Element::As, Element::Sb, Element::Bi,
+    Element::O, Element::S, Element::Se, Element::Te, Element::Po,
+    Element::F, Element::Cl, Element::Br, Element::I, Element::At,
+];
+
+// Returns the valences of an element with a charge, from lowest to highest
+pub fn charged_valences(element : Element, charge : i32) -> Vec<i32> {
+    let valences = element.default_valences();
+    let lowest = match valences.first() {
+        Some(x) => *x,
+        None => return Vec::new(),
+    };
+    if charge == 0 {
+        return valences.to_vec()
+    }
+    let valence_electrons = if LONE_PAIR_ELEMENTS.contains(&element) { 8 - lowest } else { lowest };
+    let electrons = valence_electrons - charge;
+    let charged_lowest = if electrons <= 4 { electrons } else { 8 - electrons };
+    if charged_lowest < 0 {
+        return Vec::new()
+    }
+    valences.iter().map(|x| x - lowest + charged_lowest).collect()
+}
+
+// The number of bonds the atom uses up, a double bond counts twice and an aromatic bond once
+pub fn bond_order_sum(molecule : &Molecule, atom : usize) -> i32 {
+    let mut sum = 0;
+    for (_, bond) in molecule.neighbours(atom) {
+        match bond {
+            1..=3 => sum += bond,
+            // the query bond types only stand for a single bond in a real molecule, an aromatic
+            // bond is single or double depending on the Kekulé structure
+            _ => sum += 1,
+        }
+    }
+    sum
+}
+
+// The number of hydrogens on an atom that aren't atoms in the molecule
+// Hydrogen counts from SMILES bracket atoms are used as they are, query atoms and elements
+// without a default valence (metals) get none
+pub fn implicit_hydrogens(molecule : &Molecule, atom : usize) -> i32 {
+    if let Some(count) = molecule.hydrogens[atom] {
+        return count
+    }
+    let element = match molecule.atoms[atom].element() {
+        Some(x) => x,
+        None => return 0,
+    };
+    // a doublet radical has one unpaired electron, singlets and triplets have two
+    let radical_electrons = match molecule.radicals[atom] {
+        0 => 0,
+        2 => 1,
+        _ => 2,
+    };
+    let used = bond_order_sum(molecule, atom) + radical_electrons;
+    let hydrogens = charged_valences(element, molecule.charges[atom]).iter()
+        .find(|x| **x >= used)
+        .map_or(0, |x| x - used);
+    // an aromatic atom with room for it uses one more for the ring's double bond, the sulfur of
+    // thiophene has no room and keeps its two bonds
+    if molecule.neighbours(atom).any(|(_, bond)| bond == AROMATIC_BOND) {
+        return (hydrogens - 1).max(0)
+    }
+    hydrogens
+}
+
+// Adds every implicit hydrogen as an atom single bonded to its parent, at the parent's coordinate
+pub fn add_explicit_hydrogens(molecule : &mut Molecule) {
+    for atom in 0..molecule.atoms.len() {
+        let count = implicit_hydrogens(molecule, atom);
+        for _ in 0..count {
+            let hydrogen = molecule.add_atom(Atom::Element(Element::H));
+            molecule.add_bond(atom, hydrogen, 1);
+            molecule.set_coordinate(hydrogen, molecule.get_coordinate(atom));
+        }
+        if molecule.hydrogens[atom].is_some() {
+            molecule.hydrogens[atom] = Some(0);
+        }
+    }
+}
+
+// Whether a hydrogen atom can become an implicit hydrogen of its one neighbour
+// Hydrogens with an isotope, charge or radical, bridging hydrogens, hydrogens on other hydrogens
+// and hydrogens with a stereo bond direction all carry something that an implicit hydrogen can't
+fn removable_hydrogen(molecule : &Molecule, atom : usize) -> Option<usize> {
+    if !molecule.atoms[atom].is_element(Element::H)
+        || molecule.isotopes[atom] != 0 || molecule.charges[atom] != 0 || molecule.radicals[atom] != 0
+        || molecule.hydrogens[atom].is_some_and(|x| x != 0) {
+        return None
+    }
+    let neighbours : Vec<(usize, i32)> = molecule.neighbours(atom).collect();
+    let parent = match neighbours[..] {
+        [(parent, 1)] => parent,
+        _ => return None,
+    };
+    if molecule.atoms[parent].is_element(Element::H) || molecule.atoms[parent].element().is_none() {
+        return None
+    }
+    if molecule.bond_directions.iter().any(|(x, y, _)| *x == atom || *y == atom) {
+        return None
+    }
+    Some(parent)
+}
+
+// Removes hydrogen atoms that can be implicit hydrogens, the parents keep their hydrogen counts
+// Returns the index each remaining atom had before the hydrogens were removed
+pub fn remove_explicit_hydrogens(molecule : &mut Molecule) -> Vec<usize> {
+    let mut removed = vec![0; molecule.atoms.len()];
+    let mut remove = Vec::new();
+    for atom in 0..molecule.atoms.len() {
+        if let Some(parent) = removable_hydrogen(molecule, atom) {
+            removed[parent] += 1;
+            remove.push(atom);
+        }
+    }
+    if remove.is_empty() {
+        return (0..molecule.atoms.len()).collect()
+    }
+    let before : Vec<i32> = (0..molecule.atoms.len()).map(|x| implicit_hydrogens(molecule, x)).collect();
+    let mut is_removed = vec![false; molecule.atoms.len()];
+    for atom in &remove {
+        is_removed[*atom] = true;
+    }
+    let kept : Vec<usize> = (0..molecule.atoms.len()).filter(|x| !is_removed[*x]).collect();
+    molecule.remove_atoms(&remove);
+
+    for (new, old) in kept.iter().enumerate() {
+        if removed[*old] == 0 {
+            continue
+        }
+        let expected = before[*old] + removed[*old];
+        // atoms that don't get back their hydrogens from their valences, like metal hydrides,
+        // keep them as a count
+        if molecule.hydrogens[new].is_some() || implicit_hydrogens(molecule, new) != expected {
+            molecule.hydrogens[new] = Some(expected);
+        }
+    }
+    kept
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::parser::parse_mol;
+    use crate::molecule::smiles::parse_smiles;
+
+    fn hydrogens(smiles : &str) -> Vec<i32> {
+        let molecule = parse_smiles(smiles).unwrap();
+        (0..molecule.atoms.len()).map(|i| implicit_hydrogens(&molecule, i)).collect()
+    }
+
+    #[test]
+    fn test_charged_valences() {
+        assert_eq!(charged_valences(Element::C, 0), vec![4]);
+        assert_eq!(charged_valences(Element::N, 1), vec![4, 6]);
+        assert_eq!(charged_valences(Element::O, -1), vec![1]);
+        assert_eq!(charged_valences(Element::C, 1), vec![3]);
+        assert_eq!(charged_valences(Element::C, -1), vec![3]);
+        assert_eq!(charged_valences(Element::B, -1), vec![4]);
+        assert_eq!(charged_valences(Element::Fe, 2), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_implicit_hydrogens() {
+        // acetic acid
+        assert_eq!(hydrogens("CC(=O)O"), vec![3, 0, 0, 1]);
+        // pyridine and benzene rings
+        assert_eq!(hydrogens("c1ccncc1"), vec![1, 1, 1, 0, 1, 1]);
+        assert_eq!(hydrogens("c1ccccc1C"), vec![1, 1, 1, 1, 1, 0, 3]);
+        assert_eq!(hydrogens("c1ccsc1"), vec![1, 1, 1, 0, 1]);
+        assert_eq!(hydrogens("c1ccoc1"), vec![1, 1, 1, 0, 1]);
+        // charges, bracket atoms keep their hydrogen count
+        assert_eq!(hydrogens("C[N+](C)(C)C"), vec![3, 0, 3, 3, 3]);
+        assert_eq!(hydrogens("C[NH3+]"), vec![3, 3]);
+        assert_eq!(hydrogens("CC(=O)[O-].[Na+]"), vec![3, 0, 0, 0, 0]);
+        // hypervalent sulfur in dimethyl sulfone
+        assert_eq!(hydrogens("CS(=O)(=O)C"), vec![3, 0, 0, 0, 3]);
+        // a methyl radical
+        let mut methyl = parse_smiles("C").unwrap();
+        methyl.radicals[0] = 2;
+        assert_eq!(implicit_hydrogens(&methyl, 0), 3);
+    }
+
+    #[test]
+    fn test_explicit_hydrogens() {
+        for smiles in ["OC(=O)c1ccccc1", "C[NH3+]", "CC(=O)[O-].[Na+]", "[2H]OC", "[H][H]", "[LiH]"].iter() {
+            let molecule = parse_smiles(smiles).unwrap();
+            let mut explicit = molecule.clone();
+            explicit.add_explicit_hydrogens();
+            assert_eq!(explicit.formula(), molecule.formula(), "{}", smiles);
+            assert!((0..explicit.atoms.len()).all(|x| implicit_hydrogens(&explicit, x) == 0), "{}", smiles);
+
+            explicit.remove_explicit_hydrogens();
+            assert_eq!(explicit.atoms, molecule.atoms, "{}", smiles);
+            assert_eq!(explicit.get_matrix(), molecule.get_matrix(), "{}", smiles);
+            assert_eq!(explicit.formula(), molecule.formula(), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn test_mol_files_hydrogens() {
+        // methane is drawn with its hydrogens
+        let mut methane = parse_mol(&(TEST_DIR.to_owned() + "Methane.mol")).unwrap();
+        // the carbon is the only atom left, it was drawn second
+        assert_eq!(methane.remove_explicit_hydrogens(), vec![1]);
+        assert_eq!(methane.atoms, parse_smiles("C").unwrap().atoms);
+        assert_eq!(implicit_hydrogens(&methane, 0), 4);
+        methane.add_explicit_hydrogens();
+        assert_eq!(methane.explicit_hydrogens(0), 4);
+        assert_eq!(methane.total_hydrogens(0), 4);
+
+        // the borons of decaborane have more bonds than their valence, so they keep their
+        // hydrogens as counts
+        let decaborane = parse_mol(&(TEST_DIR.to_owned() + "Decaborane.mol")).unwrap();
+        let mut removed = decaborane.clone();
+        removed.remove_explicit_hydrogens();
+        assert_eq!(removed.formula(), decaborane.formula());
+        assert!(removed.atoms.iter().all(|x| x.is_element(Element::B)));
+
+        // the bridging hydrogens of diborane bond to two borons and stay as atoms
+        let mut diborane = parse_smiles("[BH2]1[H][BH2][H]1").unwrap();
+        assert_eq!(diborane.remove_explicit_hydrogens(), vec![0, 1, 2, 3]);
+        assert_eq!(diborane.formula(), "B2H6");
+    }
+}
@@ -0,0 +1,312 @@
+/**
+ * Writes Molecules back out as .mol and .sdf files
+ *
+ * Molecules are written as V2000 mol blocks, with the charges, isotopes, radicals and atom lists
+ * in the properties block. Molecules with more than 999 atoms or bonds don't fit in the V2000
+ * columns, so they are written as V3000 instead.
+ **/
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use crate::molecule::molecule::{Molecule, Atom, QueryAtom};
+use crate::molecule::sdf::{SdfRecord, RECORD_END};
+
+// The most atoms or bonds that fit in the 3 character columns of a V2000 file
+static V2000_MAX : usize = 999;
+
+// "M  CHG" style lines can hold at most 8 atom value pairs
+static PAIRS_PER_LINE : usize = 8;
+
+// The second line of the header, the program name and whether the coordinates are 2D or 3D
+fn program_line(molecule : &Molecule) -> String {
+    let dimension = if molecule.get_coordinates().iter().any(|x| x[2] != 0.0) { "3D" } else { "2D" };
+    format!("  {:<8}{:10}{}", "MolSwatr", "", dimension)
+}
+
+// The atom block charge column for a charge and radical, the inverse of the parser's legacy_charge
+fn legacy_charge_code(charge : i32, radical : i32) -> i32 {
+    match charge {
+        1..=3 => 4 - charge,
+        -3..=-1 => 4 - charge,
+        0 if radical == 2 => 4,
+        _ => 0,
+    }
+}
+
+// Writes "M  CHG" style property lines for every atom with a non zero value
+fn property_lines(out : &mut String, tag : &str, values : &[i32]) {
+    let pairs : Vec<(usize, i32)> = values.iter().enumerate()
+        .filter(|(_, x)| **x != 0)
+        .map(|(i, x)| (i + 1, *x))
+        .collect();
+    for chunk in pairs.chunks(PAIRS_PER_LINE) {
+        *out += &format!("M  {}{:>3}", tag, chunk.len());
+        for (atom, value) in chunk {
+            *out += &format!(" {:>3} {:>3}", atom, value);
+        }
+        *out += "\n";
+    }
+}
+
+// Writes the counts line, atom block, bond block and properties block of a V2000 file
+fn v2000_ctab(molecule : &Molecule, out : &mut String) {
+    let bonds = molecule.get_bonds();
+    *out += &format!("{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000\n", molecule.atoms.len(), bonds.len());
+
+    for (i, atom) in molecule.atoms.iter().enumerate() {
+        let [x, y, z] = molecule.get_coordinate(i);
+        let charge = legacy_charge_code(molecule.charges[i], molecule.radicals[i]);
+        *out += &format!("{:>10.4}{:>10.4}{:>10.4} {:<3} 0{:>3}  0  0  0  0  0  0  0  0  0  0\n",
+            x, y, z, atom, charge);
+    }
+    for (x, y, bond_type) in bonds {
+        *out += &format!("{:>3}{:>3}{:>3}  0\n", x + 1, y + 1, bond_type);
+    }
+
+    // The properties replace the charge column, which can't hold every charge
+    property_lines(out, "CHG", &molecule.charges);
+    property_lines(out, "RAD", &molecule.radicals);
+    property_lines(out, "ISO", &molecule.isotopes);
+    for (i, atom) in molecule.atoms.iter().enumerate() {
+        if let Atom::Query(QueryAtom::List(list)) = atom {
+            *out += &format!("M  ALS {:>3}{:>3} {} ", i + 1, list.elements.len(), if list.exclude { "T" } else { "F" });
+            for element in &list.elements {
+                *out += &format!("{:<4}", element);
+            }
+            *out += "\n";
+        }
+    }
+}
+
+// Writes the connection table of a V3000 file, for molecules that are too big for V2000
+fn v3000_ctab(molecule : &Molecule, out : &mut String) {
+    let bonds = molecule.get_bonds();
+    *out += "  0  0  0     0  0            999 V3000\n";
+    *out += "M  V30 BEGIN CTAB\n";
+    *out += &format!("M  V30 COUNTS {} {} 0 0 0\n", molecule.atoms.len(), bonds.len());
+
+    *out += "M  V30 BEGIN ATOM\n";
+    for (i, atom) in molecule.atoms.iter().enumerate() {
+        let [x, y, z] = molecule.get_coordinate(i);
+        let atom_type = match atom {
+            Atom::Query(QueryAtom::List(list)) => {
+                let elements : Vec<&str> = list.elements.iter().map(|x| x.symbol()).collect();
+                format!("{}[{}]", if list.exclude { "NOT " } else { "" }, elements.join(","))
+            },
+            _ => atom.to_string(),
+        };
+        *out += &format!("M  V30 {} {} {:.4} {:.4} {:.4} 0", i + 1, atom_type, x, y, z);
+        if molecule.charges[i] != 0 {
+            *out += &format!(" CHG={}", molecule.charges[i]);
+        }
+        if molecule.radicals[i] != 0 {
+            *out += &format!(" RAD={}", molecule.radicals[i]);
+        }
+        if molecule.isotopes[i] != 0 {
+            *out += &format!(" MASS={}", molecule.isotopes[i]);
+        }
+        *out += "\n";
+    }
+    *out += "M  V30 END ATOM\n";
+
+    *out += "M  V30 BEGIN BOND\n";
+    for (i, (x, y, bond_type)) in bonds.iter().enumerate() {
+        *out += &format!("M  V30 {} {} {} {}\n", i + 1, bond_type, x + 1, y + 1);
+    }
+    *out += "M  V30 END BOND\n";
+    *out += "M  V30 END CTAB\n";
+}
+
+// Returns the mol block of a Molecule, ending with the "M  END" line
+pub fn mol_to_string(molecule : &Molecule) -> String {
+    let mut out = String::new();
+    // The name has to stay on the first line
+    out += molecule.name.lines().next().unwrap_or("");
+    out += "\n";
+    out += &program_line(molecule);
+    out += "\n\n";
+
+    if molecule.atoms.len() > V2000_MAX || molecule.get_bonds().len() > V2000_MAX {
+        v3000_ctab(molecule, &mut out);
+    } else {
+        v2000_ctab(molecule, &mut out);
+    }
+    out += "M  END\n";
+    out
+}
+
+// Writes a Molecule to a .mol file, replacing the file if it exists
+pub fn write_mol(molecule : &Molecule, file_path : &str) -> std::io::Result<()> {
+    let mut file = File::create(file_path)?;
+    file.write_all(mol_to_string(molecule).as_bytes())
+}
+
+// Returns a single .sdf record, the mol block followed by the data items and the "$$$$" line
+// The data items are written in order of their field names. A blank line ends a data item and a
+// "$$$$" line ends the record, so a value with either can't be written and is an InvalidInput error
+pub fn sdf_record_to_string(molecule : &Molecule, data : &HashMap<String, String>) -> std::io::Result<String> {
+    let mut out = mol_to_string(molecule);
+    let mut fields : Vec<&String> = data.keys().collect();
+    fields.sort();
+    for field in fields {
+        out += &format!("> <{}>\n", field);
+        for line in data[field].lines() {
+            if line.trim().is_empty() || line.starts_with(RECORD_END) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                    format!("data item {:?} has a line that would end it early: {:?}", field, line)))
+            }
+            out += line;
+            out += "\n";
+        }
+        out += "\n";
+    }
+    out += RECORD_END;
+    out += "\n";
+    Ok(out)
+}
+
+// Writes .sdf records to any writer, one record at a time
+pub struct SdfWriter<W : Write> {
+    writer : W,
+}
+
+impl<W : Write> SdfWriter<W> {
+    pub fn new(writer : W) -> SdfWriter<W> {
+        SdfWriter { writer }
+    }
+
+    // Writes a molecule and its data items as the next record
+    pub fn write_record(&mut self, molecule : &Molecule, data : &HashMap<String, String>) -> std::io::Result<()> {
+        self.writer.write_all(sdf_record_to_string(molecule, data)?.as_bytes())
+    }
+
+    // Writes a record that was read by the sdf parser
+    pub fn write_sdf_record(&mut self, record : &SdfRecord) -> std::io::Result<()> {
+        self.write_record(&record.molecule, &record.data)
+    }
+}
+
+// Opens an .sdf file for adding records to the end of it, creating it if it doesn't exist
+pub fn append_sdf(file_path : &str) -> std::io::Result<SdfWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(file_path)?;
+    Ok(SdfWriter::new(file))
+}
+
+// Writes every record to an .sdf file, replacing the file if it exists
+pub fn write_sdf(records : &[SdfRecord], file_path : &str) -> std::io::Result<()> {
+    let mut writer = SdfWriter::new(File::create(file_path)?);
+    for record in records {
+        writer.write_sdf_record(record)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::molecule::molecule::AtomList;
+    use crate::molecule::element::Element;
+    use crate::molecule::parser::{parse_mol, parse_mol_str};
+    use crate::molecule::sdf::{parse_sdf, parse_sdf_str};
+
+    fn assert_same_molecule(a : &Molecule, b : &Molecule) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.atoms, b.atoms);
+        assert_eq!(a.get_matrix(), b.get_matrix());
+        assert_eq!(a.get_coordinates(), b.get_coordinates());
+        assert_eq!(a.charges, b.charges);
+        assert_eq!(a.isotopes, b.isotopes);
+        assert_eq!(a.radicals, b.radicals);
+    }
+
+    #[test]
+    fn test_round_trip_test_files() {
+        for entry in std::fs::read_dir(TEST_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().unwrap() != "mol" {
+                continue
+            }
+            let molecule = parse_mol(path.to_str().unwrap()).unwrap();
+            let written = mol_to_string(&molecule);
+            let reread = parse_mol_str(&written).unwrap();
+            assert_same_molecule(&molecule, &reread);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_properties() {
+        let mut molecule = Molecule::new(vec!["C", "N", "O", "O", "L"]);
+        molecule.name = "nitromethane".to_string();
+        molecule.add_bond(0, 1, 1);
+        molecule.add_bond(1, 2, 2);
+        molecule.add_bond(1, 3, 1);
+        molecule.add_bond(0, 4, 1);
+        molecule.charges[1] = 1;
+        molecule.charges[3] = -1;
+        molecule.isotopes[0] = 13;
+        molecule.radicals[2] = 2;
+        molecule.atoms[4] = Atom::Query(QueryAtom::List(AtomList { exclude : true, elements : vec![Element::Cl, Element::Br] }));
+        molecule.set_coordinate(2, [1.5, -2.25, 0.125]);
+
+        let reread = parse_mol_str(&mol_to_string(&molecule)).unwrap();
+        assert_same_molecule(&molecule, &reread);
+    }
+
+    #[test]
+    fn test_round_trip_v3000() {
+        // Too many atoms for the V2000 columns
+        let atoms = vec!["C"; 1200];
+        let mut molecule = Molecule::new(atoms);
+        for i in 1..1200 {
+            molecule.add_bond(i - 1, i, 1);
+        }
+        molecule.charges[5] = -1;
+        molecule.atoms[7] = Atom::Query(QueryAtom::List(AtomList { exclude : false, elements : vec![Element::N, Element::O] }));
+
+        let written = mol_to_string(&molecule);
+        assert!(written.lines().nth(3).unwrap().ends_with("V3000"));
+        let reread = parse_mol_str(&written).unwrap();
+        assert_same_molecule(&molecule, &reread);
+    }
+
+    #[test]
+    fn test_round_trip_sdf() {
+        let records : Vec<SdfRecord> = parse_sdf(&(TEST_DIR.to_owned() + "Methane, Benzoic acid, Pentanoic acid.sdf")).unwrap()
+            .into_iter().map(|x| x.unwrap()).collect();
+        let mut bytes : Vec<u8> = Vec::new();
+        let mut writer = SdfWriter::new(&mut bytes);
+        for record in &records {
+            writer.write_sdf_record(record).unwrap();
+        }
+        let written = String::from_utf8(bytes).unwrap();
+
+        let reread : Vec<SdfRecord> = parse_sdf_str(&written).map(|x| x.unwrap()).collect();
+        assert_eq!(records.len(), reread.len());
+        for (a, b) in records.iter().zip(reread.iter()) {
+            assert_same_molecule(&a.molecule, &b.molecule);
+            assert_eq!(a.data, b.data);
+        }
+    }
+
+    #[test]
+    fn test_sdf_data_lines() {
+        let molecule = parse_mol(&(TEST_DIR.to_owned() + "Methane.mol")).unwrap();
+        let mut data : HashMap<String, String> = HashMap::new();
+        data.insert("SYNONYMS".to_string(), "methane\nmarsh gas\n  CH4".to_string());
+        data.insert("EMPTY".to_string(), String::new());
+        let written = sdf_record_to_string(&molecule, &data).unwrap();
+        let reread = parse_sdf_str(&written).next().unwrap().unwrap();
+        assert_eq!(reread.data, data);
+
+        // a blank line or "$$$$" would end the value early, so the record can't be written
+        data.insert("NOTES".to_string(), "one\n\nthree".to_string());
+        let error = sdf_record_to_string(&molecule, &data).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        data.insert("NOTES".to_string(), "one\n$$$$".to_string());
+        assert!(SdfWriter::new(Vec::new()).write_record(&molecule, &data).is_err());
+    }
+}
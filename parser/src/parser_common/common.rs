@@ -122,8 +122,11 @@ pub fn parse_i32(i: &str) -> IResult<&str, i32> {
     }
     // parses until the next whitespace
     let result = not_whitespace(trimmed)?;
-    // returns the result of parsing the bytes as an int
-    Ok((result.0, result.1.parse::<i32>().unwrap()))
+    // returns the result of parsing the bytes as an int, or an error if they aren't one
+    match result.1.parse::<i32>() {
+        Ok(x) => Ok((result.0, x)),
+        Err(_) => Err(nom::Err::Error(nom::error::Error{ input : trimmed, code : ErrorKind::Digit })),
+    }
 }
 
 
@@ -137,7 +140,10 @@ pub fn parse_f32(i : &str) -> IResult<&str, f32> {
         }
     }
     let result = not_whitespace(i)?;
-    Ok((result.0, result.1.parse::<f32>().unwrap()))
+    match result.1.parse::<f32>() {
+        Ok(x) => Ok((result.0, x)),
+        Err(_) => Err(nom::Err::Error(nom::error::Error{ input : i, code : ErrorKind::Float })),
+    }
 }
 
 // Pass a string to serve as the delimiter
@@ -239,6 +245,7 @@ mod tests {
     fn test_parse_i32() {
         assert_eq!(parse_i32("   1  "), Ok(("  ", 1)));
         assert_eq!(parse_i32("12312   "), Ok(("   ", 12312)));
+        assert!(parse_i32(" 1x2 ").is_err());
     }
     #[test]
     fn test_parse_f32() {
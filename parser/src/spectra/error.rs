@@ -0,0 +1,75 @@
+/*
+Error type returned by the JCAMP-DX parser
+
+Every variant that comes from the contents of the file carries the 1-based line number and the
+text of the offending line, like the .mol errors do.
+*/
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum JcampError {
+    // The file could not be read
+    Io(std::io::Error),
+    // A label the spectrum can't be built without, like FIRSTX or NPOINTS, isn't in the header
+    MissingLabel { label : String },
+    // A label's value or a line of XYDATA isn't a number, or is a number out of range
+    BadNumber { line : usize, text : String },
+    // The XYDATA has a different number of points than NPOINTS
+    PointCount { line : usize, expected : usize, found : usize },
+    // The first value of a line after a line ending in a DIF value doesn't repeat the last value
+    YCheck { line : usize, text : String },
+    // The x value of an XYDATA line, or FIRSTX, LASTX or DELTAX, is further from the x values
+    // fitted to the XYDATA lines than the tolerance allows
+    AbscissaMismatch { line : usize, text : String, expected : f64, found : f64 },
+    // The input isn't a JCAMP-DX file, or its data is in a form other than (X++(Y..Y))
+    UnsupportedForm { line : usize, text : String },
+}
+
+impl fmt::Display for JcampError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JcampError::Io(e) => write!(f, "could not read jcamp file: {}", e),
+            JcampError::MissingLabel { label } => write!(f, "missing required label ##{}=", label),
+            JcampError::BadNumber { line, text } => write!(f, "line {}: bad number: {:?}", line, text),
+            JcampError::PointCount { line, expected, found } =>
+                write!(f, "line {}: NPOINTS is {} but the XYDATA has {} points", line, expected, found),
+            JcampError::YCheck { line, text } =>
+                write!(f, "line {}: the first value doesn't repeat the last value of the DIF line before: {:?}", line, text),
+            JcampError::AbscissaMismatch { line, text, expected, found } =>
+                write!(f, "line {}: x is {} where the XYDATA lines put it at {}: {:?}", line, found, expected, text),
+            JcampError::UnsupportedForm { line, text } =>
+                write!(f, "line {}: not a supported JCAMP-DX spectrum: {:?}", line, text),
+        }
+    }
+}
+
+impl std::error::Error for JcampError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JcampError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for JcampError {
+    fn from(e : std::io::Error) -> Self {
+        JcampError::Io(e)
+    }
+}
+
+// Error type returned when a spectrum can't be converted to other units
+#[derive(Debug, PartialEq)]
+pub struct ConversionError {
+    pub from : String,
+    pub to : String,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't convert {} to {}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for ConversionError {}
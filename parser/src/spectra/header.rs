@@ -0,0 +1,205 @@
+/*
+The labelled data records of a JCAMP-DX header, read in one pass up to the data table.
+
+Labels are compared the way the JCAMP-DX spec asks, ignoring case, spaces, dashes, slashes and
+underscores, so ##CAS REGISTRY NO=, ##cas_registry_no= and ##CAS-REGISTRY-NO= are the same label.
+A value that runs over several lines keeps its lines, joined by "\n", and anything after a $$ is a
+comment and is dropped. Vendor labels start with $, ##$NIST SOURCE= is looked up as "$NIST SOURCE".
+*/
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::error::JcampError;
+
+// The labels that start a data table, the header ends at the first of them
+static DATA_TABLES : [&str; 5] = ["XYDATA", "XYPOINTS", "PEAKTABLE", "DATATABLE", "RADATA"];
+
+// Normalizes a label for comparison, "Cas Registry-No" becomes "CASREGISTRYNO"
+pub fn normalize_label(label : &str) -> String {
+    label.chars()
+        .filter(|x| !matches!(x, ' ' | '-' | '/' | '_'))
+        .map(|x| x.to_ascii_uppercase())
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderEntry {
+    // the label as it was first written, without the ## and =
+    pub label : String,
+    pub value : String,
+    // the 1-based line the label is on
+    pub line : usize,
+}
+
+// The labels of a header in the order they were written
+// A label written twice keeps its first place and takes the later value
+#[derive(Clone, Debug, Default)]
+pub struct Header {
+    entries : Vec<HeaderEntry>,
+    // the position in entries of each normalized label
+    index : HashMap<String, usize>,
+}
+
+impl Header {
+    // Reads the header from the start of a file, returning it and the rest of the file after the
+    // line with the data table label, which is the last entry of the header
+    pub fn parse(contents : &str) -> Result<(Header, &str), JcampError> {
+        let mut header = Header::default();
+        let mut rest = contents;
+        let mut line_number = 0;
+        while !rest.is_empty() {
+            let (line, next) = match rest.find('\n') {
+                Some(x) => (&rest[..x], &rest[x + 1..]),
+                None => (rest, ""),
+            };
+            rest = next;
+            line_number += 1;
+            let text = line.split("$$").next().unwrap_or("").trim();
+
+            if let Some(labelled) = text.strip_prefix("##") {
+                let equals = labelled.find('=').ok_or_else(|| JcampError::UnsupportedForm {
+                    line : line_number, text : line.trim_end().to_string() })?;
+                let label = labelled[..equals].trim();
+                header.insert(label, labelled[equals + 1..].trim(), line_number);
+                if DATA_TABLES.contains(&normalize_label(label).as_str()) {
+                    return Ok((header, rest))
+                }
+            } else if text.is_empty() {
+                continue
+            } else if let Some(last) = header.entries.last_mut() {
+                // a line without a label carries on the value above it
+                if !last.value.is_empty() {
+                    last.value.push('\n');
+                }
+                last.value.push_str(text);
+            } else {
+                return Err(JcampError::UnsupportedForm { line : line_number, text : line.trim_end().to_string() })
+            }
+        }
+        Err(JcampError::MissingLabel { label : "XYDATA".to_string() })
+    }
+
+    fn insert(&mut self, label : &str, value : &str, line : usize) {
+        let key = normalize_label(label);
+        match self.index.get(&key) {
+            Some(i) => {
+                self.entries[*i].value = value.to_string();
+                self.entries[*i].line = line;
+            },
+            None => {
+                self.index.insert(key, self.entries.len());
+                self.entries.push(HeaderEntry { label : label.to_string(), value : value.to_string(), line });
+            }
+        }
+    }
+
+    // Changes the value of a label, keeping its place and line, labels that aren't in the header
+    // aren't added
+    pub fn set(&mut self, label : &str, value : &str) {
+        if let Some(i) = self.index.get(&normalize_label(label)) {
+            self.entries[*i].value = value.to_string();
+        }
+    }
+
+    // Takes a label out of the header, returning its entry if it was there
+    pub fn remove(&mut self, label : &str) -> Option<HeaderEntry> {
+        let i = self.index.remove(&normalize_label(label))?;
+        for x in self.index.values_mut() {
+            if *x > i {
+                *x -= 1;
+            }
+        }
+        Some(self.entries.remove(i))
+    }
+
+    // Every label in the order it was written
+    pub fn entries(&self) -> &[HeaderEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, label : &str) -> Option<&HeaderEntry> {
+        self.index.get(&normalize_label(label)).map(|x| &self.entries[*x])
+    }
+
+    // The value of a label, if it is in the header
+    pub fn get(&self, label : &str) -> Option<&str> {
+        self.entry(label).map(|x| x.value.as_str())
+    }
+
+    // The value of a label as a number, None if the label isn't in the header
+    pub fn number<T : FromStr>(&self, label : &str) -> Result<Option<T>, JcampError> {
+        match self.entry(label) {
+            None => Ok(None),
+            Some(entry) => entry.value.parse::<T>().map(Some).map_err(|_| JcampError::BadNumber {
+                line : entry.line, text : format!("##{}={}", entry.label, entry.value) }),
+        }
+    }
+
+    // The value of a label the spectrum can't be built without, as a number
+    pub fn required_number<T : FromStr>(&self, label : &str) -> Result<T, JcampError> {
+        self.number(label)?.ok_or_else(|| JcampError::MissingLabel { label : label.to_string() })
+    }
+
+    // The number a value starts with, for values with units after them like "2.0 cm-1"
+    pub fn leading_number(&self, label : &str) -> Option<f32> {
+        self.get(label)?.split_whitespace().next()?.parse::<f32>().ok()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TEST_DIR;
+    use crate::parser_common::common::read_file_to_string;
+
+    #[test]
+    fn test_parse_header() {
+        let contents = read_file_to_string(&(TEST_DIR.to_string() + "Water.jdx")).unwrap();
+        let (header, rest) = Header::parse(&contents).unwrap();
+        assert!(rest.starts_with("388.677000 0.4380"));
+        assert_eq!(header.entries()[0], HeaderEntry { label : "TITLE".to_string(), value : "WATER".to_string(), line : 1 });
+        assert_eq!(header.entries().last().unwrap().label, "XYDATA");
+
+        // labels are found whatever their case, spaces and dashes
+        assert_eq!(header.get("CAS REGISTRY NO"), Some("7732-18-5"));
+        assert_eq!(header.get("cas_registry-no"), Some("7732-18-5"));
+        assert_eq!(header.get("Molform"), Some("H2 O"));
+        assert_eq!(header.get("$NIST SOURCE"), Some("COBLENTZ"));
+        assert_eq!(header.get("SPECTROMETERDATASYSTEM"), Some("DOW KBr FOREPRISM-GRATING"));
+        assert_eq!(header.get("PEAK TABLE"), None);
+        // the copyright lines carry on the owner
+        assert_eq!(header.get("OWNER").unwrap().lines().count(), 3);
+
+        assert_eq!(header.number::<i32>("NPOINTS").unwrap(), Some(3917));
+        assert_eq!(header.number::<f32>("FIRST X").unwrap(), Some(388.677));
+        assert_eq!(header.number::<f32>("MISSING").unwrap(), None);
+        assert!(matches!(header.number::<f32>("STATE"), Err(JcampError::BadNumber { line : 17, .. })));
+        assert!(matches!(header.required_number::<f32>("MISSING"), Err(JcampError::MissingLabel { .. })));
+        assert_eq!(header.leading_number("RESOLUTION"), Some(4.0));
+    }
+
+    #[test]
+    fn test_header_lines() {
+        let (header, rest) = Header::parse("##TITLE=A $$ comment\n$$ a comment line\n\n##Long Value=one\ntwo\n##title=B\n##XYDATA=(X++(Y..Y))\n1 2\n").unwrap();
+        assert_eq!(rest, "1 2\n");
+        assert_eq!(header.get("TITLE"), Some("B"));
+        assert_eq!(header.entries()[0].label, "TITLE");
+        assert_eq!(header.get("LONGVALUE"), Some("one\ntwo"));
+        assert!(matches!(Header::parse("##TITLE=A\n"), Err(JcampError::MissingLabel { .. })));
+        assert!(matches!(Header::parse("##TITLE\n"), Err(JcampError::UnsupportedForm { line : 1, .. })));
+        assert!(matches!(Header::parse("TITLE=A\n"), Err(JcampError::UnsupportedForm { line : 1, .. })));
+
+        // changing and removing labels keeps the rest in place
+        let mut header = header;
+        header.set("long value", "three");
+        header.set("MISSING", "four");
+        assert_eq!(header.get("LONGVALUE"), Some("three"));
+        assert_eq!(header.get("MISSING"), None);
+        assert_eq!(header.remove("Title").map(|x| x.value), Some("B".to_string()));
+        assert_eq!(header.remove("TITLE"), None);
+        assert_eq!(header.entries()[0].label, "Long Value");
+        assert_eq!(header.get("XYDATA"), Some("(X++(Y..Y))"));
+    }
+}
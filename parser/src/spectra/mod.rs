@@ -1,4 +1,6 @@
 pub mod spectrum;
 pub mod parser;
+pub mod header;
+pub mod error;
 
 extern crate nom;
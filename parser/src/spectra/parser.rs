@@ -3,197 +3,402 @@
  *
  **/
 
+use std::io::Read;
+
 use nom::IResult;
 
 use crate::parser_common::common::*;
 use crate::debug_println;
 use crate::constants::*;
 
+use super::error::JcampError;
+use super::header::{Header, normalize_label};
 use super::spectrum::Spectrum;
 use super::spectrum::Xunits;
 use super::spectrum::Yunits;
 
-// Parses through the header until reaching the desired field.
-// This assumes that the field is actually in the header.
-fn parse_until_field<'a>(i : &'a str, field : &'a str) -> IResult<&'a str, &'a str> {
-    let mut out = i; 
-    loop {
-        // If the next char is numeric, it means this parser has failed to find the field
-        if is_next_tag_x(out, "##XYDATA=") && field != "XYDATA"{
-            return Err(nom::Err::Error(nom::error::Error{ input : out, code : nom::error::ErrorKind::Not}));
+// The kinds of value in a line of XYDATA
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AsdfToken {
+    // an AFFN number or an SQZ value
+    Value(f64),
+    // a DIF value, the difference from the previous value
+    Dif(f64),
+    // a DUP count, the previous value or difference repeated this many times, itself included
+    Dup(usize),
+}
+
+// The kind of token a value's first character starts
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AsdfForm {
+    // AFFN or SQZ
+    Value,
+    Dif,
+    Dup,
+}
+
+impl AsdfForm {
+    fn token(self, x : f64) -> AsdfToken {
+        match self {
+            AsdfForm::Value => AsdfToken::Value(x),
+            AsdfForm::Dif => AsdfToken::Dif(x),
+            AsdfForm::Dup => AsdfToken::Dup(x as usize),
         }
-        // If the next char is not a ##, and not numeric, it should skip to the next line
-        if !is_next_char_x(out, '#') {
-            out = not_newline(out)?.0;
+    }
+}
+
+// The form of the token an ASDF pseudo digit starts, and the digit it stands for
+// SQZ is @ for 0, A-I for 1 to 9 and a-i for -1 to -9, DIF is % and J-R, j-r the same way and DUP
+// is S-Z for 1 to 8 and s for 9
+fn pseudo_digit(c : char) -> Option<(AsdfForm, i32)> {
+    let offset = |from : char| c as i32 - from as i32 + 1;
+    match c {
+        '@' => Some((AsdfForm::Value, 0)),
+        'A'..='I' => Some((AsdfForm::Value, offset('A'))),
+        'a'..='i' => Some((AsdfForm::Value, -offset('a'))),
+        '%' => Some((AsdfForm::Dif, 0)),
+        'J'..='R' => Some((AsdfForm::Dif, offset('J'))),
+        'j'..='r' => Some((AsdfForm::Dif, -offset('j'))),
+        'S'..='Z' => Some((AsdfForm::Dup, offset('S'))),
+        's' => Some((AsdfForm::Dup, 9)),
+        _ => None,
+    }
+}
+
+// Splits a line of XYDATA into its values, None if it has a character that isn't part of a value
+// AFFN numbers are separated by spaces, commas or their sign, the ASDF forms by their pseudo digit
+fn asdf_tokens(line : &str) -> Option<Vec<AsdfToken>> {
+    let chars : Vec<char> = line.chars().collect();
+    let mut tokens : Vec<AsdfToken> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' || c == ';' {
+            i += 1;
             continue
         }
-        match parse_field(out, field, "##") {
-            // This assumes that all errors mean that the field has yet to be found in the header
-            // only skips to the next line
-            Err(_) => {
-                out = not_newline(out)?.0;
-            }, Ok(x) => {
-                return Ok(x)
+        let affn = c.is_ascii_digit() || c == '.' || c == '+' || c == '-';
+        let (form, mut text) = match c {
+            '0'..='9' | '.' | '+' | '-' => (AsdfForm::Value, c.to_string()),
+            _ => {
+                let (form, digit) = pseudo_digit(c)?;
+                (form, digit.to_string())
+            }
+        };
+        i += 1;
+        while i < chars.len() {
+            if chars[i].is_ascii_digit() || chars[i] == '.' {
+                text.push(chars[i]);
+                i += 1;
+            // an exponent is only read after an AFFN number, E and e are SQZ digits otherwise
+            } else if affn && (chars[i] == 'E' || chars[i] == 'e')
+                && i + 1 < chars.len() && (chars[i + 1] == '+' || chars[i + 1] == '-') {
+                text.push(chars[i]);
+                text.push(chars[i + 1]);
+                i += 2;
+            } else {
+                break
             }
         }
+        tokens.push(form.token(text.parse::<f64>().ok()?));
     }
+    Some(tokens)
 }
 
-// Parses until reaching a line which does not start with ##, or reaching the line that starts with
-// ##header
-// Assumes that the ## has not been parsed yet from i
-fn parse_field<'a>(i : &'a str, field : &'a str, escape_str : &'a str) -> IResult<&'a str, &'a str> {
-    let mut out : &'a str = i;
-    // Removes the "##"
-    out = tag_x(out, escape_str)?.0;
-    // Parses the field, returns an error otherwise
-    out = tag_x(out, field)?.0;
-    // Parses the equals sign after the field
-    out = char_x(out, '=')?.0; 
-    let value = not_newline(out)?;
-    Ok(value)
+// A line of XYDATA
+#[derive(Debug, PartialEq)]
+struct XyLine {
+    // the x value that starts the line, the x of its first y value
+    x : f64,
+    // the y values, after the x value that starts the line
+    y : Vec<f32>,
+    // if the line's last value is a DIF value, the next line starts with that y value again as a
+    // check, the Y-value check
+    ends_in_dif : bool,
 }
 
-// Parses a line of xy data, returning the parsed &str and a vector of the N y values found on the
-// current line
-// For some reason, some of the jdx files are "+" and "-" delimited, which requires special handling performed by the 
-// parse_f32_delimited()
-// delimiter param is a list of chars that count as a delimiter
-fn parse_xydata_line<'a>(i : &'a str, delimiter : Option<&'a str>) -> IResult<&'a str, Vec<f32>> {
-    // Delimiter defaults to " \n" 
-    let delimiter : &str = delimiter.unwrap_or(" \n");
-    // Parses the inconcequential x value that is at the start of every XYDATA line
-    let mut out = parse_f32_delimited(i, delimiter)?.0;
-    let mut vec : Vec<f32> = Vec::new();
-    loop {
-        // Breaks and consumes the "\n" if "\n" is next
-        match char_x(out, '\n'){
-            Err(_) => {},
-            Ok(x) => {
-                out = x.0;
-                break
-            }
+// Parses a line of xy data, returning the parsed &str and the y values found on the line
+// The values can be AFFN numbers separated by spaces or by their "+" and "-" signs, or any mix of
+// the ASDF compressed forms, SQZ, DIF and DUP
+// Blank lines and lines that are only a comment give None
+fn parse_xydata_line(i : &str) -> IResult<&str, Option<XyLine>> {
+    let (line, out) = match i.find('\n') {
+        Some(x) => (&i[..x], &i[x + 1..]),
+        None => (i, ""),
+    };
+    // anything after $$ is a comment
+    let line = line.split("$$").next().unwrap_or("");
+    let bad_line = || nom::Err::Error(nom::error::Error{ input : i, code : nom::error::ErrorKind::Char});
+
+    let mut values : Vec<f64> = Vec::new();
+    let mut previous : Option<AsdfToken> = None;
+    let mut ends_in_dif = false;
+    for token in asdf_tokens(line).ok_or_else(bad_line)? {
+        // the line starts with the x value, which can't be differenced or repeated
+        if values.len() < 2 && !matches!(token, AsdfToken::Value(_)) {
+            return Err(bad_line())
         }
-        // Parses 1 f32 from out
-        let res = parse_f32_delimited(out, delimiter)?;
-        out = res.0;
-        vec.push(res.1);
+        match token {
+            AsdfToken::Value(x) => {
+                values.push(x);
+                previous = Some(token);
+                ends_in_dif = false;
+            },
+            AsdfToken::Dif(x) => {
+                values.push(values.last().ok_or_else(bad_line)? + x);
+                previous = Some(token);
+                ends_in_dif = true;
+            },
+            AsdfToken::Dup(count) => {
+                for _ in 1..count {
+                    match previous.ok_or_else(bad_line)? {
+                        AsdfToken::Dif(x) => values.push(values.last().unwrap() + x),
+                        _ => values.push(*values.last().unwrap()),
+                    }
+                }
+            },
+        }
+    }
+    // Blank lines and lines with only a comment have no values, and give None
+    if values.is_empty() {
+        return Ok((out, None))
     }
-    Ok((out, vec))
+    Ok((out, Some(XyLine { x : values[0], y : values[1..].iter().map(|x| *x as f32).collect(), ends_in_dif })))
 }
 
-// Returns the value from the field, or the default value
-fn get_field_or_default<'a>(i : &'a str, field : &'a str, default : &'a str) -> (&'a str, &'a str) {
-    match parse_until_field(i, field) {
-        Ok(x) => {
-            return (x.0, x.1)
-        }, Err(_) => {
-            return (i, default)    
-        }
+// The 1-based line number and the text of the line of file that at starts on
+// at has to be a slice of file, anything else is reported as line 0
+fn line_of(file : &str, at : &str) -> (usize, String) {
+    let offset = match (at.as_ptr() as usize).checked_sub(file.as_ptr() as usize) {
+        Some(x) if x <= file.len() => x,
+        _ => return (0, at.lines().next().unwrap_or("").to_string()),
+    };
+    let start = file[..offset].rfind('\n').map_or(0, |x| x + 1);
+    let end = file[offset..].find('\n').map_or(file.len(), |x| offset + x);
+    (file[..offset].matches('\n').count() + 1, file[start..end].trim_end().to_string())
+}
+
+// How the x values of the XYDATA lines are checked against each other and the header
+#[derive(Clone, Copy, Debug)]
+pub struct JcampOptions {
+    // how far the x value of a line, or FIRSTX, LASTX or DELTAX, can be from the x values fitted
+    // to all of the lines, as a fraction of the spacing between points
+    pub x_tolerance : f64,
+    // whether x values further off than x_tolerance are an error, or a warning kept on the Spectrum
+    pub x_mismatch_is_error : bool,
+}
+
+impl Default for JcampOptions {
+    fn default() -> JcampOptions {
+        JcampOptions { x_tolerance : 0.25, x_mismatch_is_error : false }
     }
 }
 
 // Feed this function a jdx filepath and get back a Spectrum struct
-pub fn parse_jdx(filepath : &str) -> Result<Spectrum, &str> {
-    // Reads to string all at once
-    let file : &str;
-    let res = &read_file_to_string(filepath);
-    match res {
-        Err(_) => {
-            return Err("Error reading file! Does this file exist?")
-        }, 
-        Ok(x) => {
-            file = x;
-        }
+pub fn parse_jdx(filepath : &str) -> Result<Spectrum, JcampError> {
+    parse_jdx_reader(std::fs::File::open(filepath)?)
+}
+
+// Same as parse_jdx, checking the x values with the given options
+pub fn parse_jdx_with(filepath : &str, options : &JcampOptions) -> Result<Spectrum, JcampError> {
+    parse_jdx_str_with(&read_file_to_string(filepath)?, options)
+}
+
+// Reads all of the jdx data out of reader, then parses it with parse_jdx_str
+pub fn parse_jdx_reader(mut reader : impl Read) -> Result<Spectrum, JcampError> {
+    let mut file = String::new();
+    reader.read_to_string(&mut file)?;
+    parse_jdx_str(&file)
+}
+
+// Feed this function the contents of a jdx file and get back a Spectrum struct
+pub fn parse_jdx_str(contents : &str) -> Result<Spectrum, JcampError> {
+    parse_jdx_str_with(contents, &JcampOptions::default())
+}
+
+// Fits x = a + b * index to the x values of the lines and the index of the point each is the x of,
+// returning (a, b). With only one line the spacing is delta
+fn fit_abscissae(abscissae : &[(f64, usize, &str)], delta : f64) -> (f64, f64) {
+    let n = abscissae.len() as f64;
+    let mean_i = abscissae.iter().map(|x| x.1 as f64).sum::<f64>() / n;
+    let mean_x = abscissae.iter().map(|x| x.0).sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (x, i, _) in abscissae {
+        sxy += (*i as f64 - mean_i) * (x - mean_x);
+        sxx += (*i as f64 - mean_i) * (*i as f64 - mean_i);
     }
-    // If this block is run, the file is prpbably not a proper .jdx format
-    if !is_next_char_x(file, '#') {
-        println!("Error! {} is not a valid .jdx format!", filepath);
-        return Err("invalid file")
-    }
-
-    // Parses the header to construct the initial Spectrum
-    // The order of these statements is important
-    let (mut file, mut spectrum) = {
-        let (file, title) = get_field_or_default(file, "TITLE", "UNKNOWN TITLE");
-        let (file, spectrum_type) = get_field_or_default(file, "DATA TYPE", "UNKNOWN TYPE");
-        let (file, state) = get_field_or_default(file, "STATE", "UNKNOWN STATE");
-        debug_println!("{}, {}, {}", title, spectrum_type, state);
-
-        let (file, xunits_str) = get_field_or_default(file, "XUNITS", "1/CM");
-        let mut xunits : Xunits = Xunits::cm;
-        // Sets xunits to one of the possibilities in the Xunits enum
-        if xunits_str == "MICROMETERS" {
-            xunits = Xunits::um;
-        } else {
-            xunits = Xunits::cm;
-        }
+    let b = if sxx > 0.0 { sxy / sxx } else { delta };
+    (mean_x - b * mean_i, b)
+}
 
-        let (file, yunits_str) = get_field_or_default(file, "YUNITS", "TRANSMITTACE");
-        let mut yunits : Yunits = Yunits::Absorbance;
-        if yunits_str == "ABSORBANCE" {
-            yunits = Yunits::Absorbance;
-        } else {
-            yunits = Yunits::Transmittance;
-        }
+// Same as parse_jdx_str, checking the x values with the given options
+// The x values come from the x value that starts each XYDATA line, scaled by XFACTOR. Lines that
+// are evenly spaced give an even grid fitted to them, otherwise, like when points are missing
+// between two lines, each point keeps the x of its own line
+pub fn parse_jdx_str_with(contents : &str, options : &JcampOptions) -> Result<Spectrum, JcampError> {
+    // Parses the header
+    let (header, mut file) = Header::parse(contents)?;
+    let title = header.get("TITLE").unwrap_or("UNKNOWN TITLE");
+    let spectrum_type = header.get("DATA TYPE").unwrap_or("UNKNOWN TYPE");
+    let state = header.get("STATE").unwrap_or("UNKNOWN STATE");
+    debug_println!("{}, {}, {}", title, spectrum_type, state);
 
+    // Files without XUNITS are taken as 1/cm
+    let xunits : Xunits = header.get("XUNITS").map_or(Xunits::Wavenumber, Xunits::from_label);
 
-        let (file, y_factor) = get_field_or_default(file, "YFACTOR", "1");
-        let y_factor : f32 = y_factor.parse::<f32>().unwrap_or(1.0);
-        debug_println!("y_factor {}", y_factor);
+    // Files without YUNITS are taken as transmittance, like they always were
+    let yunits : Yunits = header.get("YUNITS").map_or(Yunits::Transmittance, Yunits::from_label);
 
-        let (file, first_x) = parse_until_field(file, "FIRSTX").unwrap();
-        let first_x : f32 = first_x.parse::<f32>().unwrap();
-        debug_println!("first_x {}", first_x);
 
-        let (file, last_x) = parse_until_field(file, "LASTX").unwrap();
-        let last_x : f32 = last_x.parse::<f32>().unwrap();
-        debug_println!("last_x {}", last_x);
+    let y_factor : f32 = header.number("YFACTOR")?.unwrap_or(1.0);
+    debug_println!("y_factor {}", y_factor);
 
-        let (file, npoints) = parse_until_field(file, "NPOINTS").unwrap();
-        let npoints : i32 = npoints.parse::<i32>().unwrap();
-        debug_println!("npoints {}", npoints);
+    let x_factor : f64 = header.number("XFACTOR")?.unwrap_or(1.0);
+    debug_println!("x_factor {}", x_factor);
 
-        let mut spec = Spectrum::new(title, spectrum_type, state, xunits, yunits, first_x, last_x, npoints);
-        spec.set_y_factor(y_factor);
+    let first_x : f64 = header.required_number("FIRSTX")?;
+    debug_println!("first_x {}", first_x);
 
-        (file, spec)
-    };
+    let last_x : f64 = header.required_number("LASTX")?;
+    debug_println!("last_x {}", last_x);
 
-    // Parses until the XYDATA
-    file = parse_until_field(file, "XYDATA").unwrap().0;
+    let npoints : i32 = header.required_number("NPOINTS")?;
+    debug_println!("npoints {}", npoints);
+    // a spectrum needs two points to have a spacing
+    if npoints < 2 {
+        let entry = header.entry("NPOINTS").unwrap();
+        return Err(JcampError::BadNumber { line : entry.line, text : format!("##{}={}", entry.label, entry.value) })
+    }
 
-    // Finds what delimiter the XYDATA line uses
-    // The only possible delimiters are " " and "+"
-    let mut delimiter = " \n";
-    let not_numeric : &str = not_numeric(file).unwrap().0;
-    if is_next_char_x(not_numeric, '+') {
-        delimiter = "+\n";
+    // The header ends at the data table, only (X++(Y..Y)) XYDATA is read
+    let table = header.entries().last().unwrap();
+    if normalize_label(&table.label) != "XYDATA" || table.value.replace(' ', "") != "(X++(Y..Y))" {
+        return Err(JcampError::UnsupportedForm { line : table.line, text : format!("##{}={}", table.label, table.value) })
     }
-    // Parses until reaching the ##END
-    loop {
 
-        // If at the end of the data section
-        if is_next_tag_x(file, "##END") {
-            break
-        }
-        let res = parse_xydata_line(file, Some(delimiter));
-        match res {
-            Err(_) => {
-                break
-            },
-            Ok(x) => {
-                file = x.0;
-                for val in x.1 {
-                    spectrum.add_y(val.to_owned());
-                }
+    // The x value of each line scaled by XFACTOR, the index of the point it is the x of, and
+    // where the line starts
+    let mut abscissae : Vec<(f64, usize, &str)> = Vec::new();
+    let mut y_values : Vec<f32> = Vec::new();
+    // The y value the next line has to start with, when the last line ended in a DIF value
+    let mut check : Option<f32> = None;
+    // Parses until reaching the ##END, or the next label
+    while !file.is_empty() && !is_next_tag_x(file, "##") {
+        let line_start = file;
+        let (rest, line) = parse_xydata_line(file).map_err(|_| {
+            let (line, text) = line_of(contents, line_start);
+            JcampError::BadNumber { line, text }
+        })?;
+        file = rest;
+        // blank lines have no x value
+        let line = match line {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let mut y : &[f32] = &line.y;
+        let mut index = y_values.len();
+        if let Some(check) = check {
+            if y.first() != Some(&check) {
+                let (line, text) = line_of(contents, line_start);
+                return Err(JcampError::YCheck { line, text })
             }
+            // the line's x value is the x of the repeated point
+            y = &y[1..];
+            index -= 1;
         }
+        abscissae.push((line.x * x_factor, index, line_start));
+        y_values.extend_from_slice(y);
+        check = if line.ends_in_dif { line.y.last().copied() } else { None };
     }
 
     // If the spectrum doesn't have all of the y values alloted for every x value, something has
-    // probably gone wrong in the mol file
-    assert!(spectrum.is_complete());
+    // probably gone wrong in the jdx file
+    let npoints = npoints as usize;
+    if y_values.len() != npoints {
+        let (line, _) = line_of(contents, file);
+        return Err(JcampError::PointCount { line, expected : npoints, found : y_values.len() })
+    }
+
+    // Checks the lines and the header against the x values fitted to the lines
+    let mut warnings : Vec<String> = Vec::new();
+    let mut mismatch = |error : JcampError| {
+        if options.x_mismatch_is_error {
+            return Err(error)
+        }
+        warnings.push(error.to_string());
+        Ok(())
+    };
+    let (a, b) = fit_abscissae(&abscissae, (last_x - first_x) / (npoints as f64 - 1.0));
+    let tolerance = options.x_tolerance * b.abs();
+    // Whether every line is on the one evenly spaced grid
+    let mut regular = true;
+    for (found, index, line_start) in &abscissae {
+        let expected = a + b * *index as f64;
+        if (found - expected).abs() > tolerance {
+            regular = false;
+            let (line, text) = line_of(contents, line_start);
+            mismatch(JcampError::AbscissaMismatch { line, text, expected, found : *found })?;
+        }
+    }
+    // The spacing from one line to the next, the lower middle one so a gap between two lines
+    // doesn't change it
+    let mut steps : Vec<f64> = abscissae.windows(2)
+        .filter(|w| w[1].1 > w[0].1)
+        .map(|w| (w[1].0 - w[0].0) / (w[1].1 - w[0].1) as f64)
+        .collect();
+    steps.sort_by(f64::total_cmp);
+    let step = steps.get(steps.len().saturating_sub(1) / 2).copied().unwrap_or(b);
+
+    // When the lines aren't evenly spaced, each point is DELTAX on from the x of its line
+    let mut x_values : Vec<f64> = Vec::with_capacity(npoints);
+    if !regular {
+        let delta = header.number::<f64>("DELTAX")?.map_or(step, |x| x.abs().copysign(step));
+        for (j, (x, index, _)) in abscissae.iter().enumerate() {
+            let end = abscissae.get(j + 1).map_or(npoints, |next| next.1);
+            x_values.extend((*index..end).map(|i| x + delta * (i - index) as f64));
+        }
+        if b < 0.0 {
+            x_values.reverse();
+        }
+        // lines that overlap each other can't be put in order, they keep the grid
+        if x_values.windows(2).any(|w| w[1] <= w[0]) {
+            x_values.clear();
+        }
+    }
+
+    let ends = match (x_values.first(), x_values.last()) {
+        (Some(first), Some(last)) if b < 0.0 => (*last, *first),
+        (Some(first), Some(last)) => (*first, *last),
+        _ => (a, a + b * (npoints - 1) as f64),
+    };
+    for (label, expected) in [("FIRSTX", ends.0), ("LASTX", ends.1), ("DELTAX", step)].iter() {
+        if let (Some(found), Some(entry)) = (header.number::<f64>(label)?, header.entry(label)) {
+            // plenty of files give DELTAX without the minus sign of falling x values
+            let found = if *label == "DELTAX" { found.abs().copysign(*expected) } else { found };
+            if (found - expected).abs() > tolerance {
+                let text = format!("##{}={}", entry.label, entry.value);
+                mismatch(JcampError::AbscissaMismatch { line : entry.line, text, expected : *expected, found })?;
+            }
+        }
+    }
+
+    let mut spectrum = Spectrum::new(title, spectrum_type, state, xunits, yunits, ends.0 as f32, ends.1 as f32, npoints as i32);
+    spectrum.set_y_factor(y_factor);
+    // The spectrum goes from low to high x, in the file's units, the lines can run either way
+    if b < 0.0 {
+        y_values.reverse();
+    }
+    for val in y_values {
+        spectrum.add_y(val);
+    }
+    if !x_values.is_empty() {
+        spectrum.set_x_values(x_values.iter().map(|x| *x as f32).collect());
+    }
+    spectrum.set_header(header);
+    for warning in warnings {
+        spectrum.add_warning(warning);
+    }
 
     Ok(spectrum)
 }
@@ -207,55 +412,92 @@ mod tests {
         println!("{}", res.unwrap().0);
     }
 
-    #[test]
-    fn test_parse_field() {
-        let res = parse_field("##SUBJECT NAME=COOL BREEZE\n##MORE STUFF=NULL", "SUBJECT NAME", "##").unwrap();
-        println!("{:?},\t {:?}", res.0, res.1);
-        assert_eq!(res, ("##MORE STUFF=NULL", "COOL BREEZE"));
-    }
-
     #[test]
     fn test_parse_xydata_line() {
-        let res = parse_xydata_line("234.221345 0.4380 0.4380 0.4370 0.4370 0.4269\nTrampled under foot", None);
-        let res_vec = res.unwrap().1;
-        let should_be = vec!(0.4380, 0.4380, 0.4370, 0.4370, 0.4269);
-        for i in 0..should_be.len() {
-            assert_eq!(should_be[i], res_vec[i]);
-        }
+        let res = parse_xydata_line("234.221345 0.4380 0.4380 0.4370 0.4370 0.4269\nTrampled under foot");
+        let (rest, line) = res.unwrap();
+        assert_eq!(line.unwrap().y, vec!(0.4380, 0.4380, 0.4370, 0.4370, 0.4269));
+        assert_eq!(rest, "Trampled under foot");
     }
 
     #[test]
     fn test_parse_delimited_line() {
-        let res = parse_xydata_line("-42.1 420.69-18.2-12.1+13.5\nSomething else", None);
-        let res_vec = res.unwrap().1;
+        let res = parse_xydata_line("-42.1 420.69-18.2-12.1+13.5\nSomething else");
         // The -42.1 is skipped because parse_xydata_line() skips the first number
-        let should_be = vec!(420.69, -18.2, -12.1);
-        for i in 0..should_be.len() {
-            assert_eq!(should_be[i], res_vec[i]);
+        assert_eq!(res.unwrap().1.unwrap().y, vec!(420.69, -18.2, -12.1, 13.5));
+        let res = parse_xydata_line("1.5E+03 2.0e-01,3 $$ a comment\n");
+        assert_eq!(res.unwrap().1.unwrap().y, vec!(0.2, 3.0));
+        assert_eq!(parse_xydata_line("  $$ only a comment\n1 2\n").unwrap(), ("1 2\n", None));
+    }
+
+    #[test]
+    fn test_parse_asdf_line() {
+        let y = |x : &str| parse_xydata_line(x).unwrap().1.unwrap();
+        // SQZ
+        assert_eq!(y("A000E00e10@I\n").y, vec!(500.0, -510.0, 0.0, 9.0));
+        // DIF, each value is the previous one plus the difference
+        let line = y("A000E00J0k5%\n");
+        assert_eq!(line.y, vec!(500.0, 510.0, 485.0, 485.0));
+        assert!(line.ends_in_dif);
+        // DUP repeats a value or a difference
+        assert_eq!(y("1000 E00U\n").y, vec!(500.0, 500.0, 500.0));
+        assert_eq!(y("A000E00J0TK1\n").y, vec!(500.0, 510.0, 520.0, 541.0));
+        assert!(!y("A000E00J0E00\n").ends_in_dif);
+        assert!(parse_xydata_line("A000Q00\n").is_err());
+        assert!(parse_xydata_line("A000 J0\n").is_err());
+        assert_eq!(y("A000 E00 J0 \n").y, vec!(500.0, 510.0));
+        assert!(parse_xydata_line("A000 E00 ? \n").is_err());
+    }
+
+    #[test]
+    fn test_asdf_files() {
+        let affn = test_parser("ASDF AFFN.jdx");
+        let should_be = vec!(0.5, 0.51, 0.53, 0.56, 0.56, 0.56, 0.56, 0.54, 0.52, 0.9);
+        for (x, y) in affn.get_y_values().iter().zip(should_be) {
+            assert!((x - y).abs() < 1e-6);
+        }
+        for file in ["ASDF SQZ.jdx", "ASDF DIF.jdx", "ASDF DIFDUP.jdx"].iter() {
+            assert_eq!(test_parser(file).get_y_values(), affn.get_y_values(), "{}", file);
         }
+
+        // the first value of a line after a DIF line has to repeat the last value
+        let contents = read_file_to_string(&(TEST_DIR.to_string() + "ASDF DIF.jdx")).unwrap();
+        assert!(matches!(parse_jdx_str(&contents.replace("A400E60", "A400E61")), Err(JcampError::YCheck { line : 16, .. })));
     }
 
     #[test]
-    fn test_until_field() {
-        let content = &read_file_to_string(&(TEST_DIR.to_string() + "Water.jdx")).unwrap();
-        let res = parse_until_field(content, "TITLE").unwrap();
-        assert_eq!(res.1, "WATER");
-        let res = parse_until_field(content, "MOLFORM").unwrap();
-        assert_eq!(res.1, "H2 O");
+    fn test_errors() {
+        let contents = read_file_to_string(&(TEST_DIR.to_string() + "ASDF AFFN.jdx")).unwrap();
+        let error = |x : &str, y : &str| parse_jdx_str(&contents.replace(x, y)).err().unwrap();
+        assert!(matches!(error("##NPOINTS=10\n", ""), JcampError::MissingLabel { label } if label == "NPOINTS"));
+        assert!(matches!(error("##LASTX=1900", "##LASTX=19OO"), JcampError::BadNumber { line : 11, .. }));
+        assert!(matches!(error("##NPOINTS=10", "##NPOINTS=1"), JcampError::BadNumber { line : 13, .. }));
+        assert!(matches!(error("##NPOINTS=10", "##NPOINTS=12"), JcampError::PointCount { line : 17, expected : 12, found : 10 }));
+        assert!(matches!(error("1500 560", "1500 5#0"), JcampError::BadNumber { line : 16, .. }));
+        assert!(matches!(error("(X++(Y..Y))", "(XY..XY)"), JcampError::UnsupportedForm { line : 14, .. }));
+        assert!(matches!(parse_jdx_str("not a jcamp file"), Err(JcampError::UnsupportedForm { line : 1, .. })));
+        assert!(matches!(parse_jdx("src/molecule/test_files/missing.jdx"), Err(JcampError::Io(_))));
+        println!("{}", error("##NPOINTS=10", "##NPOINTS=12"));
     }
 
     #[test]
     fn test_xunits_file() {
         // This file contains um xunits 
         let mut spectrum = test_parser("2,4-Pentadienenitrile.jdx");
-        spectrum = spectrum.transform(1000.0, 2000.0, 10);
+        assert_eq!(spectrum.xunits(), Xunits::Micrometers);
+        assert!((spectrum.get_x_values()[0] - 2.02).abs() < 0.01);
+        spectrum = spectrum.convert_x(Xunits::Wavenumber).unwrap().transform(1000.0, 2000.0, 10);
         println!("{}", spectrum.to_string());
         print_xy(&spectrum);
         // If this equates to true, it means the spectrum is not converting to 1/cm units
         assert!(spectrum.get_y_values()[0] != -1.0);
         spectrum = test_parser("Water.jdx");
+        assert_eq!(spectrum.xunits(), Xunits::Wavenumber);
         spectrum.transform(2000.0, 3000.0, 11);
         assert!(spectrum.get_y_values()[3] != -1.0);
+        // written as 1/cm and cm-1
+        assert_eq!(test_parser("Styrene, oligomers.jdx").xunits(), Xunits::Wavenumber);
+        assert_eq!(test_parser("Ethane, pentafluoro-.jdx").xunits(), Xunits::Wavenumber);
     }
 
     #[test]
@@ -269,6 +511,17 @@ mod tests {
         test_parser("Ethane, pentafluoro-.jdx");
     }
 
+    #[test]
+    fn test_parse_from_memory() {
+        let contents = read_file_to_string(&(TEST_DIR.to_string() + "Methane.jdx")).unwrap();
+        let from_file = test_parser("Methane.jdx");
+        let from_str = parse_jdx_str(&contents).unwrap();
+        let from_reader = parse_jdx_reader(contents.as_bytes()).unwrap();
+        assert_eq!(from_file.get_y_values(), from_str.get_y_values());
+        assert_eq!(from_file.get_x_values(), from_reader.get_x_values());
+        assert!(parse_jdx_str("not a jcamp file").is_err());
+    }
+
     fn test_parser(filepath : &str) -> Spectrum {
         let spec = parse_jdx(&(TEST_DIR.to_string() + filepath)).unwrap();
         println!("{}", spec.to_string());
@@ -283,4 +536,69 @@ mod tests {
         }
         println!("");
     }
+
+    #[test]
+    fn test_abscissae() {
+        // the x values come from the lines scaled by XFACTOR
+        let affn = test_parser("ASDF AFFN.jdx");
+        let contents = read_file_to_string(&(TEST_DIR.to_string() + "ASDF AFFN.jdx")).unwrap();
+        let scaled = contents.replace("##XFACTOR=1.000000", "##XFACTOR=10").replace("1000 500", "100 500").replace("1500 560", "150 560");
+        let scaled = parse_jdx_str(&scaled).unwrap();
+        assert_eq!(scaled.get_x_values(), affn.get_x_values());
+        assert!(scaled.warnings().is_empty());
+        // without the XFACTOR the lines don't agree with the header
+        let unscaled = parse_jdx_str(&contents.replace("1000 500", "100 500").replace("1500 560", "150 560")).unwrap();
+        assert_eq!(unscaled.warnings().len(), 3);
+
+        // falling x values are read from the low end up
+        let cumidine = test_parser("Cumidine.jdx");
+        assert!(cumidine.warnings().is_empty());
+        assert_eq!(cumidine.get_y_values().last(), Some(&0.8861));
+        assert!((cumidine.get_x_values().last().unwrap() - 3960.396).abs() < 0.01);
+
+        // a line that has drifted from the rest is a warning, or an error when asked
+        let water = read_file_to_string(&(TEST_DIR.to_string() + "Water.jdx")).unwrap();
+        let drifted = water.replace("397.386854", "398.386854");
+        let spectrum = parse_jdx_str(&drifted).unwrap();
+        assert_eq!(spectrum.warnings().len(), 1);
+        assert!(spectrum.warnings()[0].starts_with("line 38:"));
+        let strict = JcampOptions { x_mismatch_is_error : true, ..JcampOptions::default() };
+        assert!(matches!(parse_jdx_str_with(&drifted, &strict), Err(JcampError::AbscissaMismatch { line : 38, .. })));
+        let loose = JcampOptions { x_tolerance : 2.0, x_mismatch_is_error : true };
+        assert!(parse_jdx_str_with(&drifted, &loose).is_ok());
+        assert!(parse_jdx_str_with(&water, &strict).is_ok());
+        assert!(matches!(parse_jdx_str_with(&water.replace("##DELTAX=0.870985", "##DELTAX=1.2"), &strict),
+            Err(JcampError::AbscissaMismatch { line : 26, .. })));
+        assert!(parse_jdx_with(&(TEST_DIR.to_string() + "Water.jdx"), &strict).is_ok());
+    }
+
+    #[test]
+    fn test_gaps() {
+        // the points from 1400 to 1900 are missing, the lines after the gap keep their own x
+        let contents = "##TITLE=GAP\n##XUNITS=MICROMETERS\n##DELTAX=100\n##FIRSTX=1000\n##LASTX=2300\n##NPOINTS=12\n\
+            ##XYDATA=(X++(Y..Y))\n1000 1 2 3 4\n1400 5 6 7 8\n2000 9 10 11 12\n##END=\n";
+        let spectrum = parse_jdx_str(contents).unwrap();
+        let expected : Vec<f32> = vec![1000.0, 1100.0, 1200.0, 1300.0, 1400.0, 1500.0, 1600.0, 1700.0,
+            2000.0, 2100.0, 2200.0, 2300.0];
+        assert_eq!(spectrum.get_x_values(), expected);
+        assert!(!spectrum.warnings().is_empty());
+        assert_eq!(spectrum.f_of(1710.0), 8.0);
+        assert_eq!(spectrum.f_of(1900.0), 9.0);
+        assert_eq!(spectrum.f_of(2340.0), 12.0);
+        assert_eq!(spectrum.f_of(2360.0), -1.0);
+        // each slice averages from the point nearest its start to the one nearest its end, so 1600
+        // to 1900 is 1600, 1700 and 2000
+        let transformed = spectrum.transform(1600.0, 2200.0, 2);
+        assert_eq!(transformed.get_y_values(), vec![8.0, 10.0]);
+        let nm = spectrum.convert_x(Xunits::Nanometers).unwrap();
+        assert_eq!(nm.get_x_values()[8], 2e6);
+        // read from the low end, falling lines end up in the same places
+        let falling = "##TITLE=GAP\n##DELTAX=-100\n##FIRSTX=2300\n##LASTX=1000\n##NPOINTS=12\n\
+            ##XYDATA=(X++(Y..Y))\n2300 12 11 10 9\n1700 8 7 6 5\n1300 4 3 2 1\n##END=\n";
+        let falling = parse_jdx_str(falling).unwrap();
+        assert_eq!(falling.get_x_values(), expected);
+        assert_eq!(falling.get_y_values(), spectrum.get_y_values());
+        let strict = JcampOptions { x_mismatch_is_error : true, ..JcampOptions::default() };
+        assert!(matches!(parse_jdx_str_with(contents, &strict), Err(JcampError::AbscissaMismatch { .. })));
+    }
 }
@@ -5,20 +5,160 @@
 use crate::debug_println;
 use crate::constants::DEBUG_LEVEL;
 
-// Handles whether the Xunits are 1/cm or 1/um
-#[derive(Copy, Clone)]
+use super::header::{Header, normalize_label};
+use super::error::ConversionError;
+
+// The units of the x values
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Xunits {
-    cm,
-    um,
+    // 1/cm
+    Wavenumber,
+    Micrometers,
+    Nanometers,
+    Hertz,
+    // parts per million of the spectrometer frequency, for NMR spectra
+    Ppm,
+    // mass to charge ratio, for mass spectra
+    MassToCharge,
+    Seconds,
+    // anything else, which can't be converted
+    Arbitrary,
+}
+
+impl Xunits {
+    // Reads an XUNITS label, ignoring case, spaces, dashes and slashes, None for units that aren't known
+    pub fn parse(label : &str) -> Option<Xunits> {
+        match normalize_label(label).as_str() {
+            "1CM" | "CM1" | "CM^1" | "WAVENUMBER" | "WAVENUMBERS" => Some(Xunits::Wavenumber),
+            "MICROMETERS" | "MICROMETER" | "MICRONS" | "UM" => Some(Xunits::Micrometers),
+            "NANOMETERS" | "NANOMETER" | "NM" => Some(Xunits::Nanometers),
+            "HZ" | "HERTZ" => Some(Xunits::Hertz),
+            "PPM" => Some(Xunits::Ppm),
+            "MZ" => Some(Xunits::MassToCharge),
+            "SECONDS" | "SECOND" | "SEC" | "S" | "TIME" => Some(Xunits::Seconds),
+            "ARBITRARYUNITS" | "ARBITRARY" => Some(Xunits::Arbitrary),
+            _ => None,
+        }
+    }
+
+    // Reads an XUNITS label, units that aren't known are Arbitrary
+    pub fn from_label(label : &str) -> Xunits {
+        Xunits::parse(label).unwrap_or(Xunits::Arbitrary)
+    }
+
+    // The label the units are written as in a jcamp file
+    pub fn label(&self) -> &'static str {
+        match self {
+            Xunits::Wavenumber => "1/CM",
+            Xunits::Micrometers => "MICROMETERS",
+            Xunits::Nanometers => "NANOMETERS",
+            Xunits::Hertz => "HZ",
+            Xunits::Ppm => "PPM",
+            Xunits::MassToCharge => "M/Z",
+            Xunits::Seconds => "SECONDS",
+            Xunits::Arbitrary => "ARBITRARY UNITS",
+        }
+    }
+
+    // Converts a value of light in these units to its wavelength in micrometers
+    // None for units that aren't a measure of light
+    fn to_micrometers(self, x : f64) -> Option<f64> {
+        match self {
+            Xunits::Wavenumber => Some(1e4 / x),
+            Xunits::Micrometers => Some(x),
+            Xunits::Nanometers => Some(x / 1e3),
+            _ => None,
+        }
+    }
+
+    // Converts a wavelength in micrometers to these units
+    fn micrometers_to(self, x : f64) -> f64 {
+        match self {
+            Xunits::Wavenumber => 1e4 / x,
+            Xunits::Nanometers => x * 1e3,
+            _ => x,
+        }
+    }
 }
 
-// Handles whether the Yunits are Absorbance or Transmittance
-#[derive(Copy, Clone)]
+// The units of the y values
+// Transmittance and reflectance are fractions of the light, from 0 to 1
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Yunits {
-    Absorbance,
     Transmittance,
+    // transmittance times 100
+    PercentTransmittance,
+    // -log10 of the transmittance
+    Absorbance,
+    Reflectance,
+    // (1 - R)^2 / 2R of the reflectance R
+    KubelkaMunk,
+    // anything else, like "dispersion index", which can't be converted
+    Arbitrary,
 }
 
+impl Yunits {
+    // Reads a YUNITS label, ignoring case, spaces and dashes, None for units that aren't known
+    pub fn parse(label : &str) -> Option<Yunits> {
+        match normalize_label(label).as_str() {
+            "TRANSMITTANCE" | "T" => Some(Yunits::Transmittance),
+            "%TRANSMITTANCE" | "PERCENTTRANSMITTANCE" | "TRANSMITTANCE(%)" | "%T" => Some(Yunits::PercentTransmittance),
+            "ABSORBANCE" | "A" => Some(Yunits::Absorbance),
+            "REFLECTANCE" | "R" => Some(Yunits::Reflectance),
+            "KUBELKAMUNK" => Some(Yunits::KubelkaMunk),
+            "ARBITRARYUNITS" | "ARBITRARY" => Some(Yunits::Arbitrary),
+            _ => None,
+        }
+    }
+
+    // Reads a YUNITS label, units that aren't known are Arbitrary
+    pub fn from_label(label : &str) -> Yunits {
+        Yunits::parse(label).unwrap_or(Yunits::Arbitrary)
+    }
+
+    // The label the units are written as in a jcamp file
+    pub fn label(&self) -> &'static str {
+        match self {
+            Yunits::Transmittance => "TRANSMITTANCE",
+            Yunits::PercentTransmittance => "% TRANSMITTANCE",
+            Yunits::Absorbance => "ABSORBANCE",
+            Yunits::Reflectance => "REFLECTANCE",
+            Yunits::KubelkaMunk => "KUBELKA-MUNK",
+            Yunits::Arbitrary => "ARBITRARY UNITS",
+        }
+    }
+
+    // Converts a value to transmittance or reflectance, whichever the units are a form of
+    fn to_fraction(self, y : f32) -> f32 {
+        match self {
+            Yunits::PercentTransmittance => y / 100.0,
+            Yunits::Absorbance => 10f32.powf(-y),
+            Yunits::KubelkaMunk => 1.0 + y - (y * y + 2.0 * y).sqrt(),
+            _ => y,
+        }
+    }
+
+    // Converts a transmittance or reflectance to these units
+    fn fraction_to(self, y : f32) -> f32 {
+        match self {
+            Yunits::PercentTransmittance => y * 100.0,
+            Yunits::Absorbance => -y.log10(),
+            Yunits::KubelkaMunk => (1.0 - y) * (1.0 - y) / (2.0 * y),
+            _ => y,
+        }
+    }
+
+    // The units that can be converted between each other
+    fn family(self) -> Option<Yunits> {
+        match self {
+            Yunits::Transmittance | Yunits::PercentTransmittance | Yunits::Absorbance => Some(Yunits::Transmittance),
+            Yunits::Reflectance | Yunits::KubelkaMunk => Some(Yunits::Reflectance),
+            Yunits::Arbitrary => None,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Spectrum {
     // ex : "WATER"
     name : String,
@@ -26,8 +166,8 @@ pub struct Spectrum {
     spectrum_type : String,
     // ex : "LIQUID (NEAT)"
     state : String,
-    // xunits can ONLY be cm
-    // yunits are enum Yunits
+    // the units of the x values, whichever the file was written in
+    xunits : Xunits,
     yunits : Yunits,
     // delta_x is very important, in each column of the XYDATA=(X++(Y..Y)), the Y values in each
     // column have their corresponding x values incremented by delta_x
@@ -43,10 +183,17 @@ pub struct Spectrum {
     // y_values is the meat of the spectrum, each point is incremented by delta_x starting from
     // first_x
     y_values : Vec<f32>,
+    // the x of each point from low to high, when the points aren't evenly spaced, like a file with
+    // points missing. delta_x is then the average spacing
+    x_values : Option<Vec<f32>>,
     // number of data points
     npoints : i32,
     // multiply y values by this
     y_factor : f32,
+    // every label of the jcamp header, empty for spectra made in code
+    header : Header,
+    // problems found reading the file that didn't stop it being read, see JcampOptions
+    warnings : Vec<String>,
 }
 
 impl Spectrum {
@@ -68,45 +215,199 @@ impl Spectrum {
             first_x = last_x;
             last_x = temp;
         }
-        // Handles different xunit cases
-        match xunits {
-            Xunits::cm => {},
-            Xunits::um => {
-                let temp = first_x;
-                first_x = 10000.0 / last_x;
-                last_x = 10000.0 / temp;
-            }
-        }
-
         assert!(npoints > 1 );
         let delta_x : f32 = (last_x - first_x) / (npoints as f32 - 1.0);
         Spectrum {
             name : name.to_string(),
             spectrum_type : spectrum_type.to_string(),
             state : state.to_string(),
+            xunits,
             yunits,
             delta_x,
             npoints,
             first_x,
             last_x,
             y_factor : 1.0,
-            y_values : Vec::new()
+            y_values : Vec::new(),
+            x_values : None,
+            header : Header::default(),
+            warnings : Vec::new(),
         }
     }
 
     pub fn set_y_factor(&mut self, y_factor : f32) {
         self.y_factor = y_factor;
     }
+
+    pub fn set_header(&mut self, header : Header) {
+        self.header = header;
+    }
+
+    // Gives each point its own x value, from low to high, for points that aren't evenly spaced
+    pub fn set_x_values(&mut self, x_values : Vec<f32>) {
+        assert_eq!(x_values.len(), self.npoints as usize);
+        self.first_x = x_values[0];
+        self.last_x = x_values[x_values.len() - 1];
+        self.delta_x = (self.last_x - self.first_x) / (self.npoints as f32 - 1.0);
+        self.x_values = Some(x_values);
+    }
+
+    pub fn add_warning(&mut self, warning : String) {
+        self.warnings.push(warning);
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    // The header of the jcamp file the spectrum was read from
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn title(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data_type(&self) -> &str {
+        &self.spectrum_type
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    // ex : "7732-18-5"
+    pub fn cas_registry_no(&self) -> Option<&str> {
+        self.header.get("CAS REGISTRY NO")
+    }
+
+    // The molecular formula as written in the file, ex : "H2 O"
+    pub fn molform(&self) -> Option<&str> {
+        self.header.get("MOLFORM")
+    }
+
+    pub fn origin(&self) -> Option<&str> {
+        self.header.get("ORIGIN")
+    }
+
+    // The resolution in x units, the number the RESOLUTION value starts with
+    pub fn resolution(&self) -> Option<f32> {
+        self.header.leading_number("RESOLUTION")
+    }
+
+    // Written as either SPECTROMETER/DATA SYSTEM or just SPECTROMETER
+    pub fn spectrometer(&self) -> Option<&str> {
+        self.header.get("SPECTROMETER/DATA SYSTEM").or_else(|| self.header.get("SPECTROMETER"))
+    }
+
+    // ex : "TRANSMISSION"
+    pub fn sampling_procedure(&self) -> Option<&str> {
+        self.header.get("SAMPLING PROCEDURE")
+    }
     
-    // Add a single y value to the Spectra
+    // Add a single y value to the Spectra, it is kept in the spectrum's y units
     pub fn add_y(&mut self, val : f32) {
-        match self.yunits {
-            Yunits::Transmittance => {
-                self.y_values.push(val * self.y_factor);            
-            }
-            Yunits::Absorbance => {
-                self.y_values.push(1.0 - val * self.y_factor);
+        self.y_values.push(val * self.y_factor);
+    }
+
+    pub fn xunits(&self) -> Xunits {
+        self.xunits
+    }
+
+    pub fn yunits(&self) -> Yunits {
+        self.yunits
+    }
+
+    // Returns a copy of this spectrum with its y values in other units
+    // Transmittance, percent transmittance and absorbance convert between each other, as do
+    // reflectance and Kubelka-Munk. A transmittance of 0 or less has no absorbance and gives
+    // infinity or NaN
+    pub fn convert_y(&self, target : Yunits) -> Result<Spectrum, ConversionError> {
+        if target != self.yunits && (self.yunits.family().is_none() || self.yunits.family() != target.family()) {
+            return Err(ConversionError { from : self.yunits.label().to_string(), to : target.label().to_string() })
+        }
+        let mut spec = self.clone();
+        spec.yunits = target;
+        if target != self.yunits {
+            for y in spec.y_values.iter_mut() {
+                *y = target.fraction_to(self.yunits.to_fraction(*y));
             }
+            spec.update_data_labels();
+        }
+        Ok(spec)
+    }
+
+    // Returns a copy of this spectrum with its x values in other units
+    // Wavenumbers, micrometers and nanometers convert between each other, as do Hz and ppm when
+    // the header has the .OBSERVE FREQUENCY in MHz. A wavenumber is the reciprocal of a
+    // wavelength, so those conversions resample the y values onto a grid that is even in the new
+    // units, with the same number of points, interpolating between the old points
+    pub fn convert_x(&self, target : Xunits) -> Result<Spectrum, ConversionError> {
+        let error = || ConversionError { from : self.xunits.label().to_string(), to : target.label().to_string() };
+        if target == self.xunits {
+            return Ok(self.clone())
+        }
+        match (self.xunits, target) {
+            (Xunits::Hertz, Xunits::Ppm) | (Xunits::Ppm, Xunits::Hertz) => {
+                let mhz = match self.header.number::<f64>(".OBSERVE FREQUENCY") {
+                    Ok(Some(x)) if x > 0.0 => x,
+                    _ => return Err(error()),
+                };
+                let factor = if target == Xunits::Ppm { 1.0 / mhz } else { mhz };
+                Ok(self.scale_x(target, factor))
+            },
+            (Xunits::Micrometers, Xunits::Nanometers) => Ok(self.scale_x(target, 1e3)),
+            (Xunits::Nanometers, Xunits::Micrometers) => Ok(self.scale_x(target, 1e-3)),
+            _ => {
+                if self.xunits.to_micrometers(1.0).is_none() || target.to_micrometers(1.0).is_none() || self.first_x <= 0.0 {
+                    return Err(error())
+                }
+                let to_target = |x : f64| target.micrometers_to(self.xunits.to_micrometers(x).unwrap());
+                let to_self = |x : f64| self.xunits.micrometers_to(target.to_micrometers(x).unwrap());
+                let ends = (to_target(self.first_x as f64), to_target(self.last_x as f64));
+                let (first_x, last_x) = (ends.0.min(ends.1), ends.0.max(ends.1));
+                let delta_x = (last_x - first_x) / (self.npoints as f64 - 1.0);
+
+                let mut spec = self.clone();
+                spec.xunits = target;
+                spec.first_x = first_x as f32;
+                spec.last_x = last_x as f32;
+                spec.delta_x = delta_x as f32;
+                spec.x_values = None;
+                spec.y_values = (0..self.npoints)
+                    .map(|i| self.interpolate(to_self(first_x + i as f64 * delta_x)))
+                    .collect();
+                spec.update_data_labels();
+                Ok(spec)
+            },
+        }
+    }
+
+    // A copy of this spectrum with its x values multiplied by a positive factor
+    fn scale_x(&self, target : Xunits, factor : f64) -> Spectrum {
+        let mut spec = self.clone();
+        spec.xunits = target;
+        spec.first_x = (self.first_x as f64 * factor) as f32;
+        spec.last_x = (self.last_x as f64 * factor) as f32;
+        spec.delta_x = (self.delta_x as f64 * factor) as f32;
+        spec.x_values = self.x_values.as_ref().map(|xs| xs.iter().map(|x| (*x as f64 * factor) as f32).collect());
+        spec.update_data_labels();
+        spec
+    }
+
+    // Makes the header's labels about the data describe this spectrum's points instead of the
+    // file's, for spectra made from another one. The values that can't be worked out for the new
+    // points are dropped
+    fn update_data_labels(&mut self) {
+        self.header.set("NPOINTS", &self.npoints.to_string());
+        self.header.set("FIRSTX", &self.first_x.to_string());
+        self.header.set("LASTX", &self.last_x.to_string());
+        self.header.set("DELTAX", &self.delta_x.to_string());
+        self.header.set("XUNITS", self.xunits.label());
+        self.header.set("YUNITS", self.yunits.label());
+        for label in ["XFACTOR", "YFACTOR", "FIRSTY", "MINX", "MAXX", "MINY", "MAXY"].iter() {
+            self.header.remove(label);
         }
     }
  
@@ -118,7 +419,10 @@ impl Spectrum {
         // Can only have positive non-zero number of npoints
         assert!(npoints > 1);
         let mut spec = Spectrum::new(
-            &self.name, &self.spectrum_type, &self.state, Xunits::cm, Yunits::Transmittance, first_x, last_x, npoints); 
+            &self.name, &self.spectrum_type, &self.state, self.xunits, self.yunits, first_x, last_x, npoints); 
+        // the metadata still describes the sample
+        spec.set_header(self.header.clone());
+        spec.update_data_labels();
         let delta_x: f32 = (last_x - first_x) / (npoints as f32);
         // Iterate curr_x from first_x to last_x by delta_x
         for i in 1..npoints + 1 {
@@ -137,6 +441,9 @@ impl Spectrum {
 
     // Generates and returns all of the x values
     pub fn get_x_values(&self) -> Vec<f32> {
+        if let Some(x_values) = &self.x_values {
+            return x_values.clone()
+        }
         let mut out : Vec<f32> = Vec::new();
         for count in 0..self.y_values.len() {
             out.push(count as f32 * self.delta_x + self.first_x); 
@@ -195,8 +502,39 @@ impl Spectrum {
         self.f_of_i(index)        
     }
 
+    // Get the value of y at any x, interpolated between the two points either side of it
+    fn interpolate(&self, x : f64) -> f32 {
+        let (i, t) = match &self.x_values {
+            Some(xs) => {
+                // the point at or before x, and how far x is from it to the next one
+                let i = xs.partition_point(|v| *v as f64 <= x).clamp(1, xs.len() - 1) - 1;
+                let (x0, x1) = (xs[i] as f64, xs[i + 1] as f64);
+                (i, ((x - x0) / (x1 - x0)).clamp(0.0, 1.0) as f32)
+            },
+            None => {
+                let last = (self.npoints - 1) as f64;
+                let pos = ((x - self.first_x as f64) / self.delta_x as f64).clamp(0.0, last);
+                let i = pos.floor() as usize;
+                (i, (pos - i as f64) as f32)
+            },
+        };
+        if t == 0.0 {
+            return self.f_of_i(i)
+        }
+        self.f_of_i(i) * (1.0 - t) + self.f_of_i(i + 1) * t
+    }
+
     // Get the nearest index of any x
     fn i_of(&self, x : f32) -> usize {
+        if let Some(xs) = &self.x_values {
+            let i = xs.partition_point(|v| *v < x);
+            if i == 0 {
+                return 0
+            }
+            // past the last point, the next one would be as far on as the last two are apart
+            let next = xs.get(i).copied().unwrap_or(2.0 * xs[i - 1] - xs[i - 2]);
+            return if next - x <= x - xs[i - 1] { i } else { i - 1 }
+        }
         let i = ((x - self.first_x) / self.delta_x).round() as usize;
         if i > self.npoints as usize{
             return self.npoints as usize
@@ -227,16 +565,20 @@ mod tests {
             name : "KRYPTONITE".to_string(),
             spectrum_type : "WAFER SPECTRUM".to_string(),
             state : "PLASMA".to_string(),
+            xunits : Xunits::Wavenumber,
             yunits : Yunits::Transmittance,
             delta_x : 1.0,
             npoints : 4,
             first_x : 100.0,
             last_x : 103.0,
             y_factor : 1.0,
-            y_values : vec![0.1, 0.2, 0.3, 0.4]
+            y_values : vec![0.1, 0.2, 0.3, 0.4],
+            x_values : None,
+            header : Header::default(),
+            warnings : Vec::new(),
         };
 
-        let mut spectrum_2 = Spectrum::new("KRYPTONITE", "WAFER SPECTRUM", "PLASMA", Xunits::cm, Yunits::Transmittance, 1.0, 4.0, 4);
+        let mut spectrum_2 = Spectrum::new("KRYPTONITE", "WAFER SPECTRUM", "PLASMA", Xunits::Wavenumber, Yunits::Transmittance, 1.0, 4.0, 4);
         let to_add = vec!(0.1, 0.2, 0.3, 0.4);
         for y in to_add {
             spectrum_2.add_y(y);
@@ -247,7 +589,7 @@ mod tests {
     }
     #[test]
     fn test_private_methods() {
-        let mut spectrum = Spectrum::new("Pretend molecule", "stethescope", "beam", Xunits::cm, Yunits::Transmittance, 4.0, 8.0, 3);
+        let mut spectrum = Spectrum::new("Pretend molecule", "stethescope", "beam", Xunits::Wavenumber, Yunits::Transmittance, 4.0, 8.0, 3);
         assert_eq!(spectrum.is_complete(), false);
         spectrum.add_y(1.0);
         assert_eq!(spectrum.is_complete(), false);
@@ -288,7 +630,7 @@ mod tests {
 
     #[test]
     fn test_transform_spectrum() {
-        let mut spectrum = Spectrum::new("Pretend molecule", "stethescope", "beam", Xunits::cm, Yunits::Transmittance, 4.0, 8.0, 3);
+        let mut spectrum = Spectrum::new("Pretend molecule", "stethescope", "beam", Xunits::Wavenumber, Yunits::Transmittance, 4.0, 8.0, 3);
         spectrum.add_y(1.0);
         spectrum.add_y(1.1);
         spectrum.add_y(1.2);
@@ -351,4 +693,148 @@ mod tests {
         println!("{}", spectrum.to_string());
         spectrum
     }
+
+    #[test]
+    fn test_metadata() {
+        let spectrum = get_spectrum("Water.jdx");
+        assert_eq!(spectrum.title(), "WATER");
+        assert_eq!(spectrum.data_type(), "INFRARED SPECTRUM");
+        assert_eq!(spectrum.state(), "LIQUID (NEAT)");
+        assert_eq!(spectrum.cas_registry_no(), Some("7732-18-5"));
+        assert_eq!(spectrum.molform(), Some("H2 O"));
+        assert_eq!(spectrum.origin(), Some("DOW CHEMICAL COMPANY"));
+        assert_eq!(spectrum.resolution(), Some(4.0));
+        assert_eq!(spectrum.spectrometer(), Some("DOW KBr FOREPRISM-GRATING"));
+        assert_eq!(spectrum.sampling_procedure(), Some("TRANSMISSION"));
+        assert_eq!(spectrum.header().get("$NIST IMAGE"), Some("cob10116"));
+        assert_eq!(spectrum.transform(1000.0, 2000.0, 10).cas_registry_no(), Some("7732-18-5"));
+        // the labels about the data follow the points they describe
+        let transformed = spectrum.transform(1000.0, 2000.0, 11);
+        assert_eq!(transformed.header().get("NPOINTS"), Some("11"));
+        assert_eq!(transformed.header().get("FIRSTX"), Some("1000"));
+        assert_eq!(transformed.header().get("LASTX"), Some("2000"));
+        assert_eq!(transformed.header().get("DELTAX"), Some("100"));
+        assert_eq!(transformed.header().get("YFACTOR"), None);
+        assert_eq!(transformed.header().get("ORIGIN"), spectrum.header().get("ORIGIN"));
+        assert_eq!(spectrum.header().get("NPOINTS"), Some("3917"));
+        let absorbance = spectrum.convert_y(Yunits::Absorbance).unwrap();
+        assert_eq!(absorbance.header().get("YUNITS"), Some("ABSORBANCE"));
+        let micrometers = spectrum.convert_x(Xunits::Micrometers).unwrap();
+        assert_eq!(micrometers.header().get("XUNITS"), Some("MICROMETERS"));
+        assert_eq!(micrometers.header().number::<f32>("FIRSTX").unwrap(), Some(micrometers.get_x_values()[0]));
+
+        let spectrum = get_spectrum("Styrene, oligomers.jdx");
+        assert_eq!(spectrum.resolution(), Some(0.48212986));
+        assert_eq!(spectrum.header().leading_number("INSTRUMENT RESOLUTION"), Some(2.0));
+        assert_eq!(spectrum.sampling_procedure(), None);
+        let made = Spectrum::new("A", "B", "C", Xunits::Wavenumber, Yunits::Transmittance, 1.0, 2.0, 2);
+        assert_eq!(made.header().entries().len(), 0);
+    }
+
+    #[test]
+    fn test_convert_y() {
+        let mut spectrum = Spectrum::new("A", "B", "C", Xunits::Wavenumber, Yunits::Absorbance, 1.0, 4.0, 4);
+        for y in [0.0, 1.0, 2.0, std::f32::consts::LOG10_2].iter() {
+            spectrum.add_y(*y);
+        }
+        // T = 10^-A
+        let transmittance = spectrum.convert_y(Yunits::Transmittance).unwrap();
+        assert_eq!(transmittance.yunits(), Yunits::Transmittance);
+        let expected = [1.0, 0.1, 0.01, 0.5];
+        for (y, e) in transmittance.get_y_values().iter().zip(expected.iter()) {
+            assert!((y - e).abs() < 1e-5, "{} {}", y, e);
+        }
+        let percent = spectrum.convert_y(Yunits::PercentTransmittance).unwrap();
+        assert!((percent.get_y_values()[1] - 10.0).abs() < 1e-4);
+        let back = percent.convert_y(Yunits::Absorbance).unwrap();
+        for (y, e) in back.get_y_values().iter().zip(spectrum.get_y_values().iter()) {
+            assert!((y - e).abs() < 1e-5);
+        }
+        // the raw values stay as they were read
+        assert_eq!(spectrum.get_y_values(), vec![0.0, 1.0, 2.0, std::f32::consts::LOG10_2]);
+        assert!(matches!(spectrum.convert_y(Yunits::Reflectance), Err(ConversionError { .. })));
+
+        let mut reflectance = Spectrum::new("A", "B", "C", Xunits::Wavenumber, Yunits::Reflectance, 1.0, 2.0, 2);
+        reflectance.add_y(0.5);
+        reflectance.add_y(0.25);
+        let km = reflectance.convert_y(Yunits::KubelkaMunk).unwrap();
+        assert_eq!(km.get_y_values(), vec![0.25, 1.125]);
+        let back = km.convert_y(Yunits::Reflectance).unwrap();
+        assert!((back.get_y_values()[1] - 0.25).abs() < 1e-6);
+        assert!(reflectance.convert_y(Yunits::Absorbance).is_err());
+
+        // the Polystyrene file is a dispersion index, which can only stay as it is
+        let styrene = get_spectrum("Styrene, oligomers.jdx");
+        assert_eq!(styrene.yunits(), Yunits::Arbitrary);
+        assert!(styrene.convert_y(Yunits::Transmittance).is_err());
+        assert!(styrene.convert_y(Yunits::Arbitrary).is_ok());
+        assert_eq!(get_spectrum("(5Z)-3-Methyl-1,5-heptadiene.jdx").yunits(), Yunits::Absorbance);
+    }
+
+    #[test]
+    fn test_yunits_labels() {
+        assert_eq!(Yunits::parse("transmittance"), Some(Yunits::Transmittance));
+        assert_eq!(Yunits::parse("% Transmittance"), Some(Yunits::PercentTransmittance));
+        assert_eq!(Yunits::parse("Kubelka-Munk"), Some(Yunits::KubelkaMunk));
+        assert_eq!(Yunits::parse("dispersion index"), None);
+        assert_eq!(Yunits::from_label("(micromol/mol)-1m-1 (base 10)"), Yunits::Arbitrary);
+        for units in [Yunits::Transmittance, Yunits::PercentTransmittance, Yunits::Absorbance,
+            Yunits::Reflectance, Yunits::KubelkaMunk, Yunits::Arbitrary].iter() {
+            assert_eq!(Yunits::parse(units.label()), Some(*units));
+        }
+    }
+
+    #[test]
+    fn test_xunits_labels() {
+        assert_eq!(Xunits::parse("1/cm"), Some(Xunits::Wavenumber));
+        assert_eq!(Xunits::parse("cm-1"), Some(Xunits::Wavenumber));
+        assert_eq!(Xunits::parse("Micrometers"), Some(Xunits::Micrometers));
+        assert_eq!(Xunits::parse("nm"), Some(Xunits::Nanometers));
+        assert_eq!(Xunits::parse("m/z"), Some(Xunits::MassToCharge));
+        assert_eq!(Xunits::parse("furlongs"), None);
+        assert_eq!(Xunits::from_label("furlongs"), Xunits::Arbitrary);
+        for units in [Xunits::Wavenumber, Xunits::Micrometers, Xunits::Nanometers, Xunits::Hertz,
+            Xunits::Ppm, Xunits::MassToCharge, Xunits::Seconds, Xunits::Arbitrary].iter() {
+            assert_eq!(Xunits::parse(units.label()), Some(*units));
+        }
+    }
+
+    #[test]
+    fn test_convert_x() {
+        // 2 to 4 um, y is the wavelength so the resampled y should be 10^4 / x
+        let mut spectrum = Spectrum::new("A", "B", "C", Xunits::Micrometers, Yunits::Transmittance, 2.0, 4.0, 201);
+        for i in 0..201 {
+            spectrum.add_y(2.0 + i as f32 * 0.01);
+        }
+        let nm = spectrum.convert_x(Xunits::Nanometers).unwrap();
+        assert_eq!(nm.xunits(), Xunits::Nanometers);
+        assert_eq!(nm.get_x_values()[0], 2000.0);
+        assert_eq!(nm.get_y_values(), spectrum.get_y_values());
+
+        let cm = spectrum.convert_x(Xunits::Wavenumber).unwrap();
+        let x_values = cm.get_x_values();
+        assert_eq!(x_values[0], 2500.0);
+        assert!((x_values[200] - 5000.0).abs() < 1e-2);
+        // still evenly spaced
+        assert!((x_values[1] - x_values[0] - 12.5).abs() < 1e-3);
+        for (x, y) in x_values.iter().zip(cm.get_y_values().iter()) {
+            assert!((1e4 / x - y).abs() < 1e-3, "{} {}", x, y);
+        }
+        let back = cm.convert_x(Xunits::Micrometers).unwrap();
+        assert!((back.get_x_values()[0] - 2.0).abs() < 1e-5);
+        for (a, b) in back.get_y_values().iter().zip(spectrum.get_y_values().iter()) {
+            assert!((a - b).abs() < 1e-3, "{} {}", a, b);
+        }
+
+        assert_eq!(spectrum.convert_x(Xunits::Hertz).err(),
+            Some(ConversionError { from : "MICROMETERS".to_string(), to : "HZ".to_string() }));
+        // Hz and ppm need the spectrometer frequency
+        let mut nmr = Spectrum::new("A", "B", "C", Xunits::Hertz, Yunits::Arbitrary, 0.0, 4000.0, 2);
+        assert!(nmr.convert_x(Xunits::Ppm).is_err());
+        let (header, _) = Header::parse("##.OBSERVE FREQUENCY=400\n##XYDATA=(X++(Y..Y))\n").unwrap();
+        nmr.set_header(header);
+        let ppm = nmr.convert_x(Xunits::Ppm).unwrap();
+        assert_eq!((ppm.first_x, ppm.last_x), (0.0, 10.0));
+        assert!(ppm.convert_x(Xunits::Seconds).is_err());
+    }
 }